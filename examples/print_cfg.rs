@@ -2,7 +2,13 @@ use decomp::prelude::*;
 
 
 fn main() {
-    let module = Module::from_ir_path("/path/to/file.ll").unwrap();
+    let module = match (decomp::load_module("/path/to/file.ll")) {
+        Ok(module) => module,
+        Err(err)   => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
     for function in &module.functions {
         let cfg = ControlFlowGraph::new(function);
         println!();
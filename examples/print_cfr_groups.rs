@@ -2,7 +2,13 @@ use decomp::prelude::*;
 
 
 fn main() {
-    let module = Module::from_ir_path("/home/kyle/Code/rust/wasmdf/examples/hello_world/target/wasm32-unknown-unknown/release/deps/hello_world.ll").unwrap();
+    let module = match (decomp::load_module("/home/kyle/Code/rust/wasmdf/examples/hello_world/target/wasm32-unknown-unknown/release/deps/hello_world.ll")) {
+        Ok(module) => module,
+        Err(err)   => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
     for function in &module.functions {
         let cfg    = ControlFlowGraph::new(function);
         let prims  = CFAPrim::find_all(cfg).unwrap();
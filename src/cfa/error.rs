@@ -0,0 +1,33 @@
+use super::*;
+
+
+/// An error encountered while finding the primitives in a `ControlFlowGraph`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum CfaError {
+
+    /// No primitive could be found in what remains of the graph, such as in an irreducible graph.
+    Unreducible {
+        /// The number of nodes left in the graph.
+        nodes : usize
+    },
+
+    /// The graph was not reduced within the allowed number of steps.
+    BudgetExceeded {
+        /// The number of steps which were allowed.
+        max_steps : usize
+    }
+
+}
+
+
+impl fmt::Display for CfaError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::Unreducible    { nodes }     => write!(f, "No primitive found with {} nodes left in the graph", nodes),
+            Self::BudgetExceeded { max_steps } => write!(f, "Graph was not reduced within {} steps", max_steps)
+        }
+    }
+}
+
+impl std::error::Error for CfaError { }
@@ -0,0 +1,23 @@
+use super::*;
+
+
+/// An edge which was dropped when a primitive was merged, even though it crossed the boundary of the primitive.
+/// 
+/// Merging a primitive only keeps the edges into its entry and out of its exit, so any other edge between a node of
+///   the primitive and a node outside of it is lost. This only happens when a finder accepts a region it should
+///   not have, so the groups recovered from it are wrong. Collected by `CFAPrim::merge`, and kept in
+///   `CFAPrims::lost_edges`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LostEdge {
+    pub from   : CFGNode,
+    pub to     : CFGNode,
+    /// The node the primitive was merged into.
+    pub merged : CFGNode
+}
+
+
+impl fmt::Display for LostEdge {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {} lost when merging {}", self.from, self.to, self.merged)
+    }
+}
@@ -8,32 +8,67 @@ impl CFAPrim {
     /// 
     /// **This method may add temporary nodes to the graph to handle certain special cases.**
     /// These temporary nodes can be removed later in the decompilation process.
-    pub fn merge(&mut self, cfg : &mut ControlFlowGraph) -> () {
+    /// 
+    /// Returns every edge between a node of the primitive and a node outside of it which the merged node does not
+    ///   keep, which is empty unless the primitive was found wrongly.
+    pub fn merge(&mut self, cfg : &mut ControlFlowGraph) -> Vec<LostEdge> {
         match (self) {
             CFAPrim::PreconditionLoop        (prim) => prim.insert_needed_node(cfg),
             CFAPrim::PostconditionLoop       (prim) => prim.insert_needed_node(cfg),
+            CFAPrim::InfiniteLoop            (prim) => prim.insert_needed_node(cfg),
             CFAPrim::OnewayConditional       (prim) => prim.insert_needed_node(cfg),
             CFAPrim::OnewayReturnConditional (prim) => prim.insert_needed_node(cfg),
             CFAPrim::TwowayConditional       (prim) => prim.insert_needed_node(cfg),
+            CFAPrim::Switch                  (prim) => prim.insert_needed_node(cfg),
+            CFAPrim::SwitchLoop              (prim) => prim.insert_needed_node(cfg),
             CFAPrim::StatementSequence       (prim) => prim.insert_needed_node(cfg),
+            // The shared node keeps its other predecessors, so no temporary node is needed.
+            CFAPrim::ShortCircuit            (_)    => { },
+            CFAPrim::LoopBreak               (prim) => prim.insert_needed_node(cfg),
+            CFAPrim::Unstructured            (prim) => prim.insert_needed_node(cfg)
         }
 
         let entry = self.entry();
         let exit  = self.exit();
         let nodes = self.nodes();
 
-        let is_root_node = entry == cfg.entry();
+        // Every primitive adds a temporary node rather than using the root node as its exit, so the root node can
+        //   only be the entry of a primitive. Check all of the nodes anyway, so that the root is never lost.
+        let is_root_node = nodes.contains(&cfg.entry());
 
         let entry_preds = cfg.preds(entry).map(|x| x.clone());
         let exit_succs = cfg.succs(exit).map(|x| x.clone());
 
+        // The merged node is only connected to the predecessors of entry and the successors of exit, so any other
+        //   edge leaving the primitive is lost. The way out of a loop break is dropped on purpose, as it is written
+        //   as a `break`.
+        let new_node    = CFGNode::between(entry, exit);
+        let breaks_from = match (&*self) {
+            CFAPrim::LoopBreak(CFALoopBreak { cond, body, .. }) => Some(body.as_ref().unwrap_or(cond)),
+            _                                                   => None
+        };
+        let mut lost_edges = Vec::new();
+        for &node in &nodes {
+            for pred in cfg.preds(node).into_iter().flatten() {
+                let kept = entry_preds.as_ref().map(|entry_preds| entry_preds.contains(pred)).unwrap_or(false);
+                if (! nodes.contains(&pred) && ! kept) {
+                    lost_edges.push(LostEdge { from : pred.clone(), to : node.clone(), merged : new_node.clone() });
+                }
+            }
+            if (Some(node) == breaks_from) { continue; }
+            for succ in cfg.succs(node).into_iter().flatten() {
+                let kept = exit_succs.as_ref().map(|exit_succs| exit_succs.contains(succ)).unwrap_or(false);
+                if (! nodes.contains(&succ) && ! kept) {
+                    lost_edges.push(LostEdge { from : node.clone(), to : succ.clone(), merged : new_node.clone() });
+                }
+            }
+        }
+
         // Remove old nodes.
         for &node in &nodes {
             cfg.remove_node(node);
         }
 
-        let new_node = CFGNode::new(entry.from_pred().clone(), exit.to_succ().clone());
-
         // Connect incoming edges.
         if let Some(entry_preds) = entry_preds {
             for entry_pred in &entry_preds {
@@ -49,6 +84,10 @@ impl CFAPrim {
                 if (! nodes.contains(&exit_succ)) {
                     cfg.add_edge(&new_node, exit_succ);
                 }
+                // If exit loops back to entry, the merged node loops back to itself.
+                else if (exit_succ == entry) {
+                    cfg.add_edge(&new_node, &new_node);
+                }
             }
         }
 
@@ -56,7 +95,61 @@ impl CFAPrim {
             cfg.set_entry(new_node);
         }
 
+        // Keep the merged node where its entry was.
+        cfg.sort_nodes_by_layout();
+
+        lost_edges
+    }
+
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::{
+        graph,
+        node
+    };
+
+    #[test]
+    fn sequence_looping_to_itself() -> () {
+        // `if (g) { do { b c } while (c) } x`, where merging the sequence leaves a single node looping back to itself.
+        let mut cfg  = graph(&[ ("g", "b"), ("g", "x"), ("b", "c"), ("c", "b"), ("c", "x") ]);
+        let seq      = CFAStatementSequence::find_first(&cfg).expect("no sequence found");
+        assert!(seq.entry == node("b") && seq.exit == node("c"));
+        let mut prim = CFAPrim::StatementSequence(seq);
+        assert!(prim.merge(&mut cfg).is_empty());
+        let merged = CFGNode::between(&node("b"), &node("c"));
+        assert!(cfg.succs(merged.clone()).is_some_and(|succs| succs.contains(&merged) && succs.contains(&node("x"))));
+    }
+
+    /// Reduces a graph one step at a time, checking that the entry is always a node of the graph.
+    fn reduce_keeping_entry(cfg : ControlFlowGraph) -> ControlFlowGraph {
+        let mut last = cfg;
+        for (prim, cfg) in CFAPrim::reduce_steps(last.clone()) {
+            assert!(cfg.nodes().contains(cfg.entry()), "entry {} lost after merging {}\n{}", cfg.entry(), prim, cfg);
+            last = cfg;
+        }
+        assert!(last.is_reduced(), "graph was not reduced");
+        last
+    }
+
+    #[test]
+    fn entry_is_loop_header() -> () {
+        // `while (h) { b } x`, where the function starts at the loop condition.
+        let cfg = reduce_keeping_entry(graph(&[ ("h", "b"), ("b", "h"), ("h", "x") ]));
+        assert!(cfg.nodes().get(0) == Some(cfg.entry()));
+        assert!(cfg.entry().from_pred() == node("h").from_pred());
     }
 
+    #[test]
+    fn entry_is_loop_body() -> () {
+        // `do { b } while (c) x`, where the function starts at the loop body.
+        let cfg = reduce_keeping_entry(graph(&[ ("b", "c"), ("c", "b"), ("c", "x") ]));
+        assert!(cfg.nodes().get(0) == Some(cfg.entry()));
+        assert!(cfg.entry().from_pred() == node("b").from_pred());
+    }
 
 }
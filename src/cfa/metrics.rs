@@ -0,0 +1,92 @@
+use super::*;
+
+use std::time::{
+    Duration,
+    Instant
+};
+
+
+/// How long reducing a `ControlFlowGraph` took, for profiling.
+/// 
+/// Created by `CFAPrim::find_all_instrumented`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ReductionMetrics {
+    /// The number of primitives merged, which is the number of times the graph was scanned successfully.
+    pub iterations : usize,
+    /// The time taken by the whole reduction.
+    pub total      : Duration,
+    /// The time spent scanning for primitives.
+    pub find       : Duration,
+    /// The time spent merging primitives into the graph.
+    pub merge      : Duration,
+    /// The time taken by the slowest single scan and merge.
+    pub slowest    : Duration
+}
+
+
+impl CFAPrim {
+
+    /// Finds all of the primitives in a `ControlFlowGraph` the same way as `find_all`, recording how long each step
+    ///   took.
+    /// 
+    /// The metrics are returned even when the graph could not be reduced, covering the steps taken until then.
+    /// 
+    /// ```rust,no_run
+    /// use decomp::prelude::*;
+    /// # let module   = Module::from_bc_path("/path/to/file.bc").unwrap();
+    /// # let function = &module.functions[0];
+    /// let (prims, metrics) = CFAPrim::find_all_instrumented(ControlFlowGraph::new(&function));
+    /// println!("{}", metrics);
+    /// ```
+    pub fn find_all_instrumented(mut cfg : ControlFlowGraph) -> (Option<CFAPrims>, ReductionMetrics) {
+        let mut metrics    = ReductionMetrics::default();
+        let start          = Instant::now();
+        let asm_gotos      = cfg.asm_gotos();
+        let mut prims      = Vec::new();
+        let mut lost_edges = Vec::new();
+        while (! cfg.is_reduced()) {
+            let Some(prim) = CFAPrim::reduce_one(&mut cfg, &mut lost_edges, Some(&mut metrics)) else {
+                metrics.total = start.elapsed();
+                return (None, metrics);
+            };
+            prims.push(prim);
+        }
+        metrics.total = start.elapsed();
+        (Some(CFAPrims::from_reduced(cfg, prims, lost_edges, asm_gotos)), metrics)
+    }
+
+}
+
+
+impl fmt::Display for ReductionMetrics {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} iterations in {:?} ({:?} finding, {:?} merging, slowest {:?})",
+            self.iterations, self.total, self.find, self.merge, self.slowest
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::graph;
+
+    #[test]
+    fn iterations_counted() -> () {
+        // `if (a) { b } x`
+        let (prims, metrics) = CFAPrim::find_all_instrumented(graph(&[ ("a", "b"), ("a", "x"), ("b", "x") ]));
+        let prims = prims.expect("graph was not reduced");
+        assert!(metrics.iterations == prims.prims().len());
+        assert!(metrics.find + metrics.merge <= metrics.total);
+    }
+
+    #[test]
+    fn iterations_counted_when_stuck() -> () {
+        // An irreducible loop between `b` and `c`, entered through both of them.
+        let (prims, metrics) = CFAPrim::find_all_instrumented(graph(&[ ("a", "b"), ("a", "c"), ("b", "c"), ("c", "b") ]));
+        assert!(prims.is_none());
+        assert!(metrics.total >= metrics.find);
+    }
+
+}
@@ -6,7 +6,7 @@
 //! ```text
 //! -> loop { if (! %bb8 ) { break; } -> %bb10
 //! -> if ( %bb2 ) { %bb3 } -> %bb4
-//! -> if ( %bb7 ) { (%bb8...%bb10) } -> %bb13
+//! -> if ( ! %bb7 ) { (%bb8...%bb10) } -> %bb13
 //! -> if ( (%bb2...%bb4) ) { %bb5 } else { %bb6 } -> %@DECOMP_TEMPORARY_0
 //! -> while ( %bb1 ) { (%bb2...%@DECOMP_TEMPORARY_0) } -> (%bb7...%bb13)
 //! -> %start -> (%bb1...%bb13)
@@ -16,30 +16,67 @@
 pub mod prims;
 use prims::*;
 
+mod error;
+pub use error::CfaError;
+
+mod rejection;
+pub use rejection::RejectionReason;
+use rejection::reason;
+
+mod metrics;
+pub use metrics::ReductionMetrics;
+
 mod merge;
 
+mod lost_edge;
+pub use lost_edge::LostEdge;
+
+mod tree;
+
+mod upstream;
+
 use crate::cfg::{
     ControlFlowGraph,
-    CFGNode
+    CFGNode,
+    CFGBranch
 };
 
 use crate::util::unique_vec::UniqueVec;
+use crate::theme::{
+    Theme,
+    Themed
+};
 
 use std::fmt;
+use std::time::Instant;
+use std::collections::HashMap;
 
 use llvm_ir::Name;
 
 
+/// Finds the first primitive of one kind, recording why each candidate was rejected if `rejected` is given.
+type Finder = fn(&ControlFlowGraph, Option<&mut Vec<RejectionReason>>) -> Option<CFAPrim>;
+
+
 /// A group of control flow graph nodes which perform a small task.
 /// 
 /// For more information, see documentation for individual variants.
+/// 
+/// More primitives may be added in future versions, so matches outside of this crate need a wildcard arm.
+#[non_exhaustive]
 pub enum CFAPrim {
     PreconditionLoop        (CFAPreconditionLoop        ),
     PostconditionLoop       (CFAPostconditionLoop       ),
+    InfiniteLoop            (CFAInfiniteLoop            ),
     OnewayConditional       (CFAOnewayConditional       ),
     OnewayReturnConditional (CFAOnewayReturnConditional ),
     TwowayConditional       (CFATwowayConditional       ),
-    StatementSequence       (CFAStatementSequence       )
+    Switch                  (CFASwitch                  ),
+    SwitchLoop              (CFASwitchLoop              ),
+    StatementSequence       (CFAStatementSequence       ),
+    ShortCircuit            (CFAShortCircuit            ),
+    LoopBreak               (CFALoopBreak               ),
+    Unstructured            (CFAUnstructured            )
 }
 
 
@@ -47,50 +84,197 @@ impl CFAPrim {
 
 
     /// Finds all of the primitives in a `ControlFlowGraph`, or `None` if it failed to reduce the graph.
-    pub fn find_all(mut cfg : ControlFlowGraph) -> Option<CFAPrims> {
-        let mut prims = Vec::new();
-        while (cfg.nodes().len() > 1) {
-            let mut prim = CFAPrim::find_first(&mut cfg)?;
-            prim.merge(&mut cfg);
-            prims.push(prim);
-        }
-        Some(CFAPrims {
-            entry : cfg.entry().clone(),
-            temps : cfg.temps().clone(),
-            prims
-        })
+    /// 
+    /// An irreducible graph can never be fully reduced, which `ControlFlowGraph::is_reducible` checks for up front.
+    /// 
+    /// The graph is reduced in place, so it is taken by value to avoid copying it. Use `find_all_preserving` to
+    ///   keep the graph.
+    pub fn find_all(cfg : ControlFlowGraph) -> Option<CFAPrims> {
+        Self::find_all_with_budget(cfg, usize::MAX).ok()
     }
 
 
-    /// Find the first primitive in a `ControlFlowGraph`, or `None` if none could be found.
-    pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+    /// Finds all of the primitives in a copy of a `ControlFlowGraph`, or `None` if it failed to reduce the graph.
+    /// 
+    /// Reducing the graph merges its nodes together, so `find_all` takes ownership of it. This leaves `cfg`
+    ///   untouched, for when the original graph is still needed alongside the primitives, such as to visualise
+    ///   both.
+    pub fn find_all_preserving(cfg : &ControlFlowGraph) -> Option<CFAPrims> {
+        Self::find_all(cfg.clone())
+    }
 
-        if let Some(prim) = CFAPreconditionLoop::find_first(cfg) {
-            return Some(CFAPrim::PreconditionLoop(prim));
-        }
 
-        if let Some(prim) = CFAPostconditionLoop::find_first(cfg) {
-            return Some(CFAPrim::PostconditionLoop(prim));
-        }
+    /// Finds all of the primitives in a borrowed `ControlFlowGraph`, cloning it internally.
+    /// 
+    /// The same as `find_all_preserving`, for callers who expect a borrowing version of `find_all`.
+    pub fn find_all_ref(cfg : &ControlFlowGraph) -> Option<CFAPrims> {
+        Self::find_all_preserving(cfg)
+    }
 
-        if let Some(prim) = CFAOnewayConditional::find_first(cfg) {
-            return Some(CFAPrim::OnewayConditional(prim));
-        }
 
-        if let Some(prim) = CFAOnewayReturnConditional::find_first(cfg) {
-            return Some(CFAPrim::OnewayReturnConditional(prim));
+    /// Finds all of the primitives in a `ControlFlowGraph`, giving up with `CfaError::BudgetExceeded` after
+    ///   `max_steps` primitives have been merged without reducing the graph.
+    /// 
+    /// Useful as a safety limit when running on untrusted input.
+    pub fn find_all_with_budget(mut cfg : ControlFlowGraph, max_steps : usize) -> Result<CFAPrims, CfaError> {
+        let asm_gotos      = cfg.asm_gotos();
+        let mut prims      = Vec::new();
+        let mut lost_edges = Vec::new();
+        while (! cfg.is_reduced()) {
+            if (prims.len() >= max_steps) {
+                return Err(CfaError::BudgetExceeded { max_steps });
+            }
+            let Some(prim) = Self::reduce_one(&mut cfg, &mut lost_edges, None) else {
+                return Err(CfaError::Unreducible { nodes : cfg.nodes().len() });
+            };
+            prims.push(prim);
         }
+        Ok(CFAPrims::from_reduced(cfg, prims, lost_edges, asm_gotos))
+    }
+
 
-        if let Some(prim) = CFATwowayConditional::find_first(cfg) {
-            return Some(CFAPrim::TwowayConditional(prim));
+    /// Finds all of the primitives in a `ControlFlowGraph`, keeping each region which can not be reduced as a
+    ///   `CFAUnstructured` rather than failing.
+    /// 
+    /// Whenever no other primitive can be found, the smallest region around the part of the graph which is stuck,
+    ///   such as a loop entered through more than one of its nodes, is merged as a `CFAUnstructured`, and the rest
+    ///   of the graph is reduced as usual. After `max_steps` primitives have been merged, everything left is merged
+    ///   as a single `CFAUnstructured`. `CFRGroups::new_partial` recovers each of them as a `CFRGroup::Unstructured`.
+    pub fn find_all_partial(mut cfg : ControlFlowGraph, max_steps : usize) -> CFAPrims {
+        let original       = cfg.clone();
+        let asm_gotos      = cfg.asm_gotos();
+        let mut prims      = Vec::new();
+        let mut lost_edges = Vec::new();
+        while (! cfg.is_reduced()) {
+            if (prims.len() >= max_steps) {
+                let mut prim = CFAPrim::Unstructured(CFAUnstructured::whole(&cfg, &original, &prims));
+                lost_edges.extend(prim.merge(&mut cfg));
+                prims.push(prim);
+                break;
+            }
+            let prim = match (Self::reduce_one(&mut cfg, &mut lost_edges, None)) {
+                Some(prim) => prim,
+                None       => {
+                    let mut prim = CFAPrim::Unstructured(CFAUnstructured::find_first(&cfg, &original, &prims));
+                    lost_edges.extend(prim.merge(&mut cfg));
+                    prim
+                }
+            };
+            prims.push(prim);
         }
+        CFAPrims::from_reduced(cfg, prims, lost_edges, asm_gotos)
+    }
 
-        if let Some(prim) = CFAStatementSequence::find_first(cfg) {
-            return Some(CFAPrim::StatementSequence(prim));
+
+    /// Reduces a `ControlFlowGraph` one primitive at a time, yielding each primitive along with the graph after it
+    ///   was merged.
+    /// 
+    /// This is the same reduction as `find_all`, so the iterator ends once the graph is reduced to a single node,
+    ///   or when no primitive can be found. Useful for watching the graph collapse, or for finding the step where
+    ///   reduction goes wrong.
+    /// 
+    /// ```rust,no_run
+    /// use decomp::prelude::*;
+    /// # let module   = Module::from_bc_path("/path/to/file.bc").unwrap();
+    /// # let function = &module.functions[0];
+    /// for (prim, cfg) in CFAPrim::reduce_steps(ControlFlowGraph::new(function)) {
+    ///     println!("{}\n{}", prim, cfg);
+    /// }
+    /// ```
+    pub fn reduce_steps(mut cfg : ControlFlowGraph) -> impl Iterator<Item = (CFAPrim, ControlFlowGraph)> {
+        std::iter::from_fn(move || {
+            if (cfg.is_reduced()) { return None; }
+            let prim = Self::reduce_one(&mut cfg, &mut Vec::new(), None)?;
+            Some((prim, cfg.clone()))
+        })
+    }
+
+
+    /// Finds the first primitive in a `ControlFlowGraph` and merges it, or `None` if none could be found.
+    /// 
+    /// Every edge lost by merging it is added to `lost_edges`, and how long the step took is added to `metrics` if
+    ///   it is given. This is a single step of every way of reducing a graph, such as `find_all`.
+    fn reduce_one(cfg : &mut ControlFlowGraph, lost_edges : &mut Vec<LostEdge>, mut metrics : Option<&mut ReductionMetrics>) -> Option<Self> {
+        let find_start = Instant::now();
+        let found      = Self::find_first(cfg);
+        let find_time  = find_start.elapsed();
+        if let Some(metrics) = metrics.as_deref_mut() { metrics.find += find_time; }
+        let mut prim = found?;
+
+        let merge_start = Instant::now();
+        lost_edges.extend(prim.merge(cfg));
+        let merge_time  = merge_start.elapsed();
+        if let Some(metrics) = metrics {
+            metrics.merge      += merge_time;
+            metrics.iterations += 1;
+            metrics.slowest     = metrics.slowest.max(find_time + merge_time);
         }
+        Some(prim)
+    }
 
+
+    /// Find the first primitive in a `ControlFlowGraph`, or `None` if none could be found.
+    pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first primitive in a `ControlFlowGraph` the same way as `find_first`, along with why each candidate
+    ///   tried before it was rejected.
+    /// 
+    /// Candidates are listed in the order they were tried, so if no primitive is found, every candidate in the
+    ///   graph is listed. Useful for finding out why part of a graph was not recovered as expected.
+    /// 
+    /// ```text
+    /// while %bb1: body %bb2 has 2 successors, expected 1
+    /// while %bb2: body %bb3 does not loop back to cond
+    /// while %bb2: body %bb4 has 2 predecessors, expected 1
+    /// ...
+    /// ```
+    pub fn explain_first(cfg : &ControlFlowGraph) -> (Option<Self>, Vec<RejectionReason>) {
+        let mut rejected = Vec::new();
+        let prim = Self::find_first_explained(cfg, Some(&mut rejected));
+        (prim, rejected)
+    }
+
+    /// Finds the first primitive, recording why each candidate before it was rejected if `rejected` is given.
+    fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain = rejected.is_some();
+        let finders : [(&'static str, Finder); 11] = [
+            ("while",         |cfg, rejected| CFAPreconditionLoop::find_first_explained(cfg, rejected).map(Self::PreconditionLoop)),
+            ("loop",          |cfg, rejected| CFAPostconditionLoop::find_first_explained(cfg, rejected).map(Self::PostconditionLoop)),
+            ("infinite loop", |cfg, rejected| CFAInfiniteLoop::find_first_explained(cfg, rejected).map(Self::InfiniteLoop)),
+            ("switch",        |cfg, rejected| CFASwitch::find_first_explained(cfg, rejected).map(Self::Switch)),
+            ("switch loop",   |cfg, rejected| CFASwitchLoop::find_first_explained(cfg, rejected).map(Self::SwitchLoop)),
+            ("if",            |cfg, rejected| CFAOnewayConditional::find_first_explained(cfg, rejected).map(Self::OnewayConditional)),
+            ("if-return",     |cfg, rejected| CFAOnewayReturnConditional::find_first_explained(cfg, rejected).map(Self::OnewayReturnConditional)),
+            ("if-else",       |cfg, rejected| CFATwowayConditional::find_first_explained(cfg, rejected).map(Self::TwowayConditional)),
+            ("sequence",      |cfg, rejected| CFAStatementSequence::find_first_explained(cfg, rejected).map(Self::StatementSequence)),
+            // Only tried once nothing else matches, so that nested conditionals are left as they are.
+            ("short-circuit", |cfg, rejected| CFAShortCircuit::find_first_explained(cfg, rejected).map(Self::ShortCircuit)),
+            // Only tried once nothing else matches, so that the conditions of loops are joined first.
+            ("if-break",      |cfg, rejected| CFALoopBreak::find_first_explained(cfg, rejected).map(Self::LoopBreak))
+        ];
+        for (name, finder) in finders {
+            let Some(prim) = finder(cfg, rejected.as_deref_mut()) else { continue };
+            // The destination of an `asm goto` is not a condition, so it is left for `CFAUnstructured`.
+            match (prim.asm_goto(cfg)) {
+                Some(node) => RejectionReason::record(&mut rejected, name, node, reason(explain, format_args!("{} is an asm goto", node))),
+                None       => return Some(prim)
+            }
+        }
         None
+    }
+
 
+    /// Gets a node of the primitive which ends in an `asm goto` with more than one destination, other than the
+    ///   exit it leaves through, or `None` if there is none.
+    fn asm_goto<'l>(&'l self, cfg : &ControlFlowGraph) -> Option<&'l CFGNode> {
+        // The second condition of a short-circuit is also its exit, but is still branched on.
+        let exit = (! matches!(self, Self::ShortCircuit(_))).then(|| self.exit());
+        self.nodes().into_iter()
+            .filter(|&node| Some(node) != exit)
+            .find(|&node| cfg.is_asm_goto(node) && cfg.succs(node).is_some_and(|succs| succs.len() > 1))
     }
 
 
@@ -99,10 +283,16 @@ impl CFAPrim {
         match (self) {
             Self::PreconditionLoop        (CFAPreconditionLoop        { cond,  .. }) => cond,
             Self::PostconditionLoop       (CFAPostconditionLoop       { cond,  .. }) => cond,
+            Self::InfiniteLoop            (CFAInfiniteLoop            { body,  .. }) => body,
             Self::OnewayConditional       (CFAOnewayConditional       { cond,  .. }) => cond,
             Self::OnewayReturnConditional (CFAOnewayReturnConditional { cond,  .. }) => cond,
             Self::TwowayConditional       (CFATwowayConditional       { cond,  .. }) => cond,
-            Self::StatementSequence       (CFAStatementSequence       { entry, .. }) => entry
+            Self::Switch                  (CFASwitch                  { cond,  .. }) => cond,
+            Self::SwitchLoop              (CFASwitchLoop              { cond,  .. }) => cond,
+            Self::StatementSequence       (CFAStatementSequence       { entry, .. }) => entry,
+            Self::ShortCircuit            (CFAShortCircuit            { first, .. }) => first,
+            Self::LoopBreak               (CFALoopBreak               { cond,  .. }) => cond,
+            Self::Unstructured            (CFAUnstructured            { entry, .. }) => entry
         }
     }
 
@@ -112,10 +302,16 @@ impl CFAPrim {
         match (self) {
             Self::PreconditionLoop        (CFAPreconditionLoop        { exit, .. }) => exit,
             Self::PostconditionLoop       (CFAPostconditionLoop       { exit, .. }) => exit,
+            Self::InfiniteLoop            (CFAInfiniteLoop            { exit, .. }) => exit,
             Self::OnewayConditional       (CFAOnewayConditional       { exit, .. }) => exit,
             Self::OnewayReturnConditional (CFAOnewayReturnConditional { exit, .. }) => exit,
             Self::TwowayConditional       (CFATwowayConditional       { exit, .. }) => exit,
-            Self::StatementSequence       (CFAStatementSequence       { exit, .. }) => exit
+            Self::Switch                  (CFASwitch                  { exit, .. }) => exit,
+            Self::SwitchLoop              (CFASwitchLoop              { exit, .. }) => exit,
+            Self::StatementSequence       (CFAStatementSequence       { exit, .. }) => exit,
+            Self::ShortCircuit            (CFAShortCircuit            { second, .. }) => second,
+            Self::LoopBreak               (CFALoopBreak               { exit, .. }) => exit,
+            Self::Unstructured            (CFAUnstructured            { exit, .. }) => exit
         }
     }
 
@@ -123,12 +319,44 @@ impl CFAPrim {
     /// Get all of the nodes in the primitive.
     pub fn nodes(&self) -> Vec<&CFGNode> {
         match (self) {
-            Self::PreconditionLoop        (CFAPreconditionLoop        { cond,  body,           exit }) => vec![ cond,  body,          exit ],
+            Self::PreconditionLoop        (CFAPreconditionLoop        { cond,  body,           exit, .. }) => vec![ cond,  body,          exit ],
             Self::PostconditionLoop       (CFAPostconditionLoop       { cond,                  exit }) => vec![ cond,                 exit ],
-            Self::OnewayConditional       (CFAOnewayConditional       { cond,  body,           exit }) => vec![ cond, body,           exit ],
-            Self::OnewayReturnConditional (CFAOnewayReturnConditional { cond,  body,           exit }) => vec![ cond,  body,          exit ],
+            Self::InfiniteLoop            (CFAInfiniteLoop            { body,                  exit }) => vec![ body,                 exit ],
+            Self::OnewayConditional       (CFAOnewayConditional       { cond,  body,           exit, .. }) => vec![ cond, body,           exit ],
+            Self::OnewayReturnConditional (CFAOnewayReturnConditional { cond,  body,           exit, .. }) => vec![ cond,  body,          exit ],
             Self::TwowayConditional       (CFATwowayConditional       { cond,  body_a, body_b, exit }) => vec![ cond, body_a, body_b, exit ],
-            Self::StatementSequence       (CFAStatementSequence       { entry,                 exit }) => vec![ entry,                exit ]
+            Self::Switch                  (prim @ CFASwitch           { cond,                  exit, .. }) => {
+                let mut nodes = vec![ cond ];
+                nodes.extend(prim.bodies());
+                nodes.push(exit);
+                nodes
+            },
+            Self::SwitchLoop              (prim @ CFASwitchLoop       { cond,  latch,          exit, .. }) => {
+                let mut nodes = vec![ cond ];
+                nodes.extend(prim.bodies());
+                nodes.extend(latch);
+                nodes.push(exit);
+                nodes
+            },
+            Self::StatementSequence       (CFAStatementSequence       { entry, middle,         exit }) => {
+                let mut nodes = vec![ entry ];
+                nodes.extend(middle);
+                nodes.push(exit);
+                nodes
+            },
+            Self::ShortCircuit            (CFAShortCircuit            { first,  second,        .. }) => vec![ first,                second ],
+            Self::LoopBreak               (CFALoopBreak               { cond,   body,          exit, .. }) => {
+                let mut nodes = vec![ cond ];
+                nodes.extend(body);
+                nodes.push(exit);
+                nodes
+            },
+            Self::Unstructured            (CFAUnstructured            { entry,  body,          exit, .. }) => {
+                let mut nodes = vec![ entry ];
+                nodes.extend(body);
+                nodes.push(exit);
+                nodes
+            }
         }
     }
 
@@ -138,13 +366,26 @@ impl CFAPrim {
 
 /// A collection of `CFAPrim`s.
 pub struct CFAPrims {
-    entry : CFGNode,
-    temps : UniqueVec<Name>,
-    prims : Vec<CFAPrim>
+    entry      : CFGNode,
+    temps      : UniqueVec<Name>,
+    prims      : Vec<CFAPrim>,
+    lost_edges : Vec<LostEdge>,
+    asm_gotos  : HashMap<Name, Vec<Name>>
 }
 
 impl CFAPrims {
 
+    /// Collects the primitives merged into `cfg`, along with the `asm_gotos` of the graph before it was reduced.
+    fn from_reduced(cfg : ControlFlowGraph, prims : Vec<CFAPrim>, lost_edges : Vec<LostEdge>, asm_gotos : HashMap<Name, Vec<Name>>) -> Self {
+        Self {
+            entry      : cfg.entry().clone(),
+            temps      : cfg.temps().clone(),
+            prims,
+            lost_edges,
+            asm_gotos
+        }
+    }
+
     /// Get the entry node of the CFG.
     pub fn entry(&self) -> &CFGNode { &self.entry }
 
@@ -154,31 +395,126 @@ impl CFAPrims {
     /// Gets all primitives that were found.
     pub fn prims(&self) -> &Vec<CFAPrim> { &self.prims }
 
+    /// Gets every edge which was dropped by merging a primitive, which is empty unless a primitive was found
+    ///   wrongly. Anything recovered from primitives which lost edges can not be trusted.
+    pub fn lost_edges(&self) -> &Vec<LostEdge> { &self.lost_edges }
+
+    /// Gets every block which ends in an `asm goto`, along with every block it can continue to, starting with the
+    ///   one it falls through to.
+    pub fn asm_gotos(&self) -> &HashMap<Name, Vec<Name>> { &self.asm_gotos }
+
 }
 
 
 impl fmt::Display for CFAPrim {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAPrim {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
         match (self) {
-            Self::PreconditionLoop        (prim) => write!(f, "{}", prim)?,
-            Self::PostconditionLoop       (prim) => write!(f, "{}", prim)?,
-            Self::OnewayConditional       (prim) => write!(f, "{}", prim)?,
-            Self::OnewayReturnConditional (prim) => write!(f, "{}", prim)?,
-            Self::TwowayConditional       (prim) => write!(f, "{}", prim)?,
-            Self::StatementSequence       (prim) => write!(f, "{}", prim)?
+            Self::PreconditionLoop        (prim) => prim.fmt_themed(f, theme),
+            Self::PostconditionLoop       (prim) => prim.fmt_themed(f, theme),
+            Self::InfiniteLoop            (prim) => prim.fmt_themed(f, theme),
+            Self::OnewayConditional       (prim) => prim.fmt_themed(f, theme),
+            Self::OnewayReturnConditional (prim) => prim.fmt_themed(f, theme),
+            Self::TwowayConditional       (prim) => prim.fmt_themed(f, theme),
+            Self::Switch                  (prim) => prim.fmt_themed(f, theme),
+            Self::SwitchLoop              (prim) => prim.fmt_themed(f, theme),
+            Self::StatementSequence       (prim) => prim.fmt_themed(f, theme),
+            Self::ShortCircuit            (prim) => prim.fmt_themed(f, theme),
+            Self::LoopBreak               (prim) => prim.fmt_themed(f, theme),
+            Self::Unstructured            (prim) => prim.fmt_themed(f, theme)
         }
-        Ok(())
     }
 }
 
 impl fmt::Display for CFAPrims {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAPrims {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        if (f.alternate()) { return self.fmt_tree_themed(f, theme); }
         let mut first = true;
         for prim in &self.prims {
             if (first) { first = false; }
             else { writeln!(f)?; }
-            write!(f, "{}", prim)?;
+            prim.fmt_themed(f, theme)?;
         }
         Ok(())
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::{
+        asm_goto,
+        graph,
+        node
+    };
+
+    /// Reduces a graph, panicking if it could not be reduced or an edge was lost.
+    pub(crate) fn reduce(cfg : ControlFlowGraph) -> CFAPrims {
+        let prims = CFAPrim::find_all(cfg).expect("graph was not reduced");
+        assert!(prims.lost_edges().is_empty(), "lost edges while reducing");
+        prims
+    }
+
+    #[test]
+    fn if_with_shared_exit() -> () {
+        // `if (a) { if (b) { c } } x`, where the inner condition does not dominate the exit it shares with the outer one.
+        let prims = reduce(graph(&[ ("a", "b"), ("a", "x"), ("b", "c"), ("b", "x"), ("c", "x") ]));
+        let CFAPrim::OnewayConditional(inner) = &prims.prims()[0] else { panic!("expected an if, found {}", prims.prims()[0]) };
+        assert!(inner.cond == node("b") && inner.body == node("c"));
+    }
+
+    #[test]
+    fn asm_goto_not_structured() -> () {
+        // `e a`, where `a` jumps to `b` or `c` from inline assembly, so only the sequence leading to it is merged.
+        let mut cfg = graph(&[ ("e", "a"), ("a", "b"), ("a", "c"), ("b", "d"), ("c", "d") ]);
+        asm_goto(&mut cfg, "a");
+        let Some(mut prim @ CFAPrim::StatementSequence(_)) = CFAPrim::find_first(&cfg) else { panic!("expected a sequence") };
+        prim.merge(&mut cfg);
+        let (prim, rejected) = CFAPrim::explain_first(&cfg);
+        assert!(prim.is_none());
+        assert!(rejected.iter().any(|rejected| rejected.prim == "if-else" && rejected.reason.ends_with("is an asm goto")));
+    }
+
+    #[test]
+    fn if_at_loop_header() -> () {
+        // `loop { if (h) { b } l }`, where the condition is reached from the end of the loop, which does not dominate it.
+        let prims = reduce(graph(&[ ("e", "h"), ("h", "b"), ("h", "l"), ("b", "l"), ("l", "h") ]));
+        let CFAPrim::OnewayConditional(prim) = &prims.prims()[0] else { panic!("expected an if, found {}", prims.prims()[0]) };
+        assert!(prim.cond == node("h") && prim.body == node("b"));
+        assert!(prims.prims().iter().any(|prim| matches!(prim, CFAPrim::InfiniteLoop(_))));
+    }
+
+    #[test]
+    fn steps_match_find_all() -> () {
+        // `while (h) { if (b) { c } } x`
+        let cfg   = graph(&[ ("h", "b"), ("h", "x"), ("b", "c"), ("b", "l"), ("c", "l"), ("l", "h") ]);
+        let steps = CFAPrim::reduce_steps(cfg.clone()).collect::<Vec<_>>();
+        let prims = reduce(cfg);
+        assert!(steps.len() == prims.prims().len());
+        assert!(steps.iter().zip(prims.prims()).all(|((step, _), prim)| step.to_string() == prim.to_string()));
+        assert!(steps.last().is_some_and(|(_, cfg)| cfg.is_reduced()));
+    }
+
+    #[test]
+    fn ref_keeps_graph() -> () {
+        // `if (a) { b } c`
+        let cfg    = graph(&[ ("a", "b"), ("a", "c"), ("b", "c") ]);
+        let before = cfg.to_string();
+        let prims  = CFAPrim::find_all_ref(&cfg).expect("graph was not reduced");
+        assert!(prims.prims().len() == reduce(cfg.clone()).prims().len());
+        assert!(cfg.to_string() == before && cfg.nodes().len() == 3 && ! cfg.is_reduced());
+    }
+
+}
@@ -0,0 +1,142 @@
+use super::*;
+
+
+/// ```text
+/// PSEUDOCODE                   GRAPH
+/// 
+/// loop {                       A <-.
+///     BODY                      \_/
+/// }
+/// ```
+/// 
+/// Body is a single node whose only successor is itself, so the loop never ends. This is how functions which never
+///   return end, such as event loops.
+/// 
+/// Nothing follows the loop, so exit is a temporary node with no successors, added when the primitive is merged.
+pub struct CFAInfiniteLoop {
+    pub body : CFGNode,
+    pub exit : CFGNode
+}
+
+
+impl CFAInfiniteLoop {
+
+
+    /// Find the first infinite loop primitive in a `ControlFlowGraph`, or `None` if none could be found.
+    pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first infinite loop primitive in a `ControlFlowGraph`, recording why each candidate before it was
+    ///   rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |body : &CFGNode, reason| RejectionReason::record(&mut rejected, "infinite loop", body, reason);
+        for body in cfg.nodes() {
+
+            // Verify that body has one successor (body).
+            let Some(body_succs) = cfg.succs(body) else { continue };
+            if (body_succs.len() != 1 || ! body_succs.contains(body)) { continue; }
+
+            match (Self::is_valid(cfg, body, explain)) {
+                Ok(())      => return Some(Self { body : body.clone(), exit : body.clone() }),
+                Err(reason) => reject(body, reason)
+            }
+
+        }
+        None
+    }
+
+
+    /// Checks that `body` is an infinite loop, or gets the reason it is not.
+    fn is_valid(cfg : &ControlFlowGraph, body : &CFGNode, explain : bool) -> Result<(), String> {
+
+        // Temporaries sanity check.
+        if (cfg.temps().contains(body.to_succ())) { return Err(reason(explain, format_args!("body ends in a temporary"))); }
+
+        // Verify that body has one successor (body).
+        let Some(body_succs) = cfg.succs(body) else { return Err(reason(explain, format_args!("body has no successors"))) };
+        if (body_succs.len() != 1) { return Err(reason(explain, format_args!("body has {} successors, expected 1", body_succs.len()))); }
+        if (! body_succs.contains(body)) { return Err(reason(explain, format_args!("body does not loop back to itself"))); }
+
+        Ok(())
+    }
+
+
+    /// Adds the temporary node which the loop exits to, so that the merged node is not mistaken for the body.
+    /// 
+    /// ```text
+    /// loop {
+    ///     BODY
+    /// }
+    /// TEMPORARY     <- This temporary node is never reached, and has no successors.
+    /// ```
+    pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
+        let temporary = cfg.create_temporary_node();
+        cfg.add_edge(&self.body, &temporary);
+        self.exit = (&temporary).into();
+    }
+
+
+}
+
+
+impl fmt::Display for CFAInfiniteLoop {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAInfiniteLoop {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}loop{reset} {punctuation}{{{reset} ")?;
+        write!(f, "{block}{}{reset}", self.body)?;
+        write!(f, " {punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::{
+        CFRGroups,
+        CFRGroup
+    };
+    use crate::cfg::tests::function;
+
+    /// Recovers a function built from its blocks, which must have no exit.
+    fn recover(blocks : &[(&str, &[&str])]) -> CFRGroups {
+        let cfg = ControlFlowGraph::new(&function(blocks));
+        assert!(cfg.exits().is_empty());
+        let prims = CFAPrim::find_all(cfg).expect("graph was not reduced");
+        CFRGroups::new(&prims).expect("groups were not recovered")
+    }
+
+    #[test]
+    fn event_loop() -> () {
+        // `loop { poll(); }`, where `p` calls `poll` and branches back to itself.
+        let groups = recover(&[ ("e", &[ "p" ]), ("p", &[ "p" ]) ]);
+        assert!(matches!(groups.groups.as_slice(), [
+            CFRGroup::Block(_),
+            CFRGroup::InfiniteLoop { body }
+        ] if matches!(body.groups.as_slice(), [ CFRGroup::Block(name) ] if name == &Name::from("p"))), "expected a top-level loop, found\n{}", groups);
+    }
+
+    #[test]
+    fn event_loop_with_handler() -> () {
+        // `loop { if (poll()) { handle(); } }`
+        let groups = recover(&[ ("e", &[ "p" ]), ("p", &[ "h", "p" ]), ("h", &[ "p" ]) ]);
+        assert!(matches!(groups.groups.as_slice(), [
+            CFRGroup::Block(_),
+            CFRGroup::InfiniteLoop { .. }
+        ]), "expected a top-level loop, found\n{}", groups);
+    }
+
+}
@@ -0,0 +1,228 @@
+use super::*;
+
+use std::collections::{
+    HashMap,
+    HashSet
+};
+
+
+/// ```text
+/// PSEUDOCODE                GRAPH
+/// 
+/// while (HEAD) {            HEAD <-.
+///     if (COND) {            | \    |
+///         BODY              COND \  |
+///         break;            |  |  | |
+///     }                     B  |  | |
+///     EXIT                  |  C  | |
+/// }                         |   \_|_/
+///                           D <---'
+/// ```
+/// 
+/// Cond is inside of a loop, and leaves it to the same place as the condition of the loop does, either directly or
+///   through body. Once the extra way out is merged away, the loop has a single exit and can be found by the loop
+///   primitives.
+/// 
+/// Only breaks out of the innermost loop containing cond are found, and the loop must still have a way out through
+///   its header or the source of one of its back edges. The way out of the loop itself is its primary exit, and
+///   every break must leave to it.
+/// 
+/// Loops which are left to more than one place are not recovered. A break which leaves to somewhere else, such as
+///   out of an outer loop as well, would need a labelled `break`, which no primitive models, so the loop is left
+///   unreduced. `CFAPrim::find_all_partial` keeps such a loop as a `CFAUnstructured`.
+pub struct CFALoopBreak {
+    pub cond    : CFGNode,
+    /// Run before leaving the loop, or `None` if cond leaves the loop directly.
+    pub body    : Option<CFGNode>,
+    pub exit    : CFGNode,
+    /// Whether the loop is left when the condition is false, meaning the condition should be negated.
+    pub negated : bool
+}
+
+
+impl CFALoopBreak {
+
+
+    /// Find the first loop break primitive in a `ControlFlowGraph`, or `None` if none could be found.
+    pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first loop break primitive in a `ControlFlowGraph`, recording why each candidate before it was
+    ///   rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |cond : &CFGNode, reason| RejectionReason::record(&mut rejected, "if-break", cond, reason);
+        let loops      = cfg.natural_loops();
+        let back_edges = cfg.back_edges();
+        for cond in cfg.nodes() {
+
+            // Verify that cond has two successors (exit and the way out of the loop).
+            let Some(cond_succs) = cfg.succs(cond) else { continue };
+            if (cond_succs.len() != 2) { continue; }
+
+            // The innermost loop containing a node is the smallest one.
+            let Some((header, body)) = loops.iter().filter(|(_, body)| body.contains(cond)).min_by_key(|(_, body)| body.len()) else {
+                reject(cond, reason(explain, format_args!("cond is not in a loop")));
+                continue;
+            };
+            let innermost = InnermostLoop { loops : &loops, back_edges : &back_edges, header, body };
+
+            let mut cond_succs = cond_succs.into_iter();
+            let a = cond_succs.next().unwrap();
+            let b = cond_succs.next().unwrap();
+
+            for (exit, out) in [ (a, b), (b, a) ] {
+                match (Self::is_valid(cfg, &innermost, cond, exit, out, explain)) {
+                    Ok(body)    => return Some(Self { cond : cond.clone(), body, exit : exit.clone(), negated : cfg.is_false_edge(cond, out) }),
+                    Err(reason) => reject(cond, reason)
+                }
+            }
+
+        }
+        None
+    }
+
+
+    /// Checks that `cond` continues the innermost loop around it through `exit` and leaves it through `out`, or gets
+    ///   the reason it does not.
+    /// 
+    /// Gets the body run before leaving the loop, if there is one.
+    fn is_valid(cfg : &ControlFlowGraph, innermost : &InnermostLoop, cond : &CFGNode, exit : &CFGNode, out : &CFGNode, explain : bool) -> Result<Option<CFGNode>, String> {
+        let InnermostLoop { loops, back_edges, header, body } = *innermost;
+
+        // Temporaries sanity check.
+        if (cfg.temps().contains(cond.to_succ())) { return Err(reason(explain, format_args!("cond ends in a temporary"))); }
+
+        // The header leaving the loop is the condition of the loop itself.
+        if (cond == header) { return Err(reason(explain, format_args!("cond is the loop header"))); }
+
+        // Verify that exit stays in the loop, and out leaves it.
+        if (! body.contains(exit)) { return Err(reason(explain, format_args!("exit {} is not in the loop", exit))); }
+        if (body.contains(out)) { return Err(reason(explain, format_args!("{} is in the loop", out))); }
+
+        // Find where the loop is left by its condition, from the header or the source of a back edge.
+        let loop_exits = back_edges.iter()
+            .filter(|(_, to)| to == header)
+            .map(|(from, _)| from)
+            .chain([ header ])
+            .filter(|&node| node != cond)
+            .filter_map(|node| cfg.succs(node))
+            .flat_map(|succs| succs.into_iter())
+            .filter(|succ| ! body.contains(succ))
+            .collect::<Vec<_>>();
+        if (loop_exits.is_empty()) { return Err(reason(explain, format_args!("the loop has no other way out"))); }
+
+        // Verify that out is where the loop is left, or leads there. Leaving anywhere else would need a labelled
+        //   `break`.
+        if (loop_exits.contains(&out)) { return Ok(None); }
+        let leads_out = cfg.succs(out).is_some_and(|out_succs| out_succs.len() == 1 && out_succs.iter().any(|succ| loop_exits.contains(&succ)));
+        if (! leads_out) {
+            let leaves_outer = loops.values().any(|outer| outer.len() > body.len() && outer.contains(cond) && ! outer.contains(out));
+            return Err(if (leaves_outer) {
+                reason(explain, format_args!("{} leaves an outer loop as well, which needs a labelled break", out))
+            } else {
+                reason(explain, format_args!("{} leaves the loop somewhere else than the loop does, which needs a labelled break", out))
+            });
+        }
+        if (cfg.pred_count(out) != 1) { return Err(reason(explain, format_args!("{} has {} predecessors, expected 1", out, cfg.pred_count(out)))); }
+
+        Ok(Some(out.clone()))
+    }
+
+
+    /// Handles the special case where exit is reached from elsewhere in the loop.
+    /// An additional temporary node will be added if needed.
+    pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
+        // If exit does not have one predecessor (cond), insert a temporary node.
+        if (cfg.pred_count(&self.exit) != 1) {
+            let temporary = cfg.create_temporary_node();
+            cfg.insert_node(&temporary, &self.cond, &self.exit);
+            self.exit = (&temporary).into();
+        }
+    }
+
+
+}
+
+
+/// The innermost loop around a condition being checked by `CFALoopBreak::is_valid`.
+struct InnermostLoop<'l> {
+    /// Every natural loop of the graph, by header.
+    loops      : &'l HashMap<CFGNode, HashSet<CFGNode>>,
+    back_edges : &'l [(CFGNode, CFGNode)],
+    header     : &'l CFGNode,
+    body       : &'l HashSet<CFGNode>
+}
+
+
+impl fmt::Display for CFALoopBreak {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFALoopBreak {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, negation, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}if{reset} {punctuation}({reset} ")?;
+        if (self.negated) { write!(f, "{negation}!{reset} ")?; }
+        write!(f, "{block}{}{reset}", self.cond)?;
+        write!(f, " {punctuation}) {{{reset} ")?;
+        if let Some(body) = &self.body {
+            write!(f, "{block}{}{reset} ", body)?;
+        }
+        write!(f, "{keyword}break{reset}{muted};{reset} ")?;
+        write!(f, "{punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfa::tests::reduce;
+    use crate::cfg::tests::{
+        graph,
+        node
+    };
+
+    /// Gets why the loop break at `cond` was rejected.
+    fn rejections(cfg : &ControlFlowGraph, cond : &str) -> Vec<String> {
+        let mut rejected = Vec::new();
+        assert!(CFALoopBreak::find_first_explained(cfg, Some(&mut rejected)).is_none());
+        rejected.into_iter().filter(|rejection| rejection.node == node(cond)).map(|rejection| rejection.reason).collect()
+    }
+
+    #[test]
+    fn break_to_loop_exit() -> () {
+        // `while (h) { if (c) { y break; } b } x`
+        let cfg  = graph(&[ ("e", "h"), ("h", "c"), ("h", "x"), ("c", "b"), ("c", "y"), ("y", "x"), ("b", "h") ]);
+        let prim = CFALoopBreak::find_first(&cfg).expect("no break found");
+        assert!(prim.cond == node("c") && prim.body == Some(node("y")) && prim.exit == node("b"));
+        reduce(cfg);
+    }
+
+    #[test]
+    fn break_to_other_exit() -> () {
+        // `while (h) { if (c) { y goto j; } b } x j`, where the break skips the code after the loop.
+        let cfg = graph(&[ ("e", "h"), ("h", "c"), ("h", "x"), ("c", "b"), ("c", "y"), ("b", "h"), ("x", "j"), ("y", "j") ]);
+        assert!(rejections(&cfg, "c").iter().any(|reason| reason.contains("needs a labelled break")));
+        let prims = CFAPrim::find_all_partial(cfg, usize::MAX);
+        assert!(prims.prims().iter().any(|prim| matches!(prim, CFAPrim::Unstructured(_))));
+    }
+
+    #[test]
+    fn break_out_of_nested_loops() -> () {
+        // `'o: while (o) { while (i) { if (c) { break 'o; } b } l } x`
+        let cfg = graph(&[ ("e", "o"), ("o", "i"), ("o", "x"), ("i", "c"), ("i", "l"), ("c", "b"), ("c", "x"), ("b", "i"), ("l", "o") ]);
+        assert!(rejections(&cfg, "c").iter().any(|reason| reason.contains("leaves an outer loop as well")));
+    }
+
+}
+
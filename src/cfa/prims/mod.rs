@@ -7,6 +7,9 @@ pub use precondition_loop::CFAPreconditionLoop;
 mod postcondition_loop;
 pub use postcondition_loop::CFAPostconditionLoop;
 
+mod infinite_loop;
+pub use infinite_loop::CFAInfiniteLoop;
+
 mod oneway_conditional;
 pub use oneway_conditional::CFAOnewayConditional;
 
@@ -16,8 +19,120 @@ pub use oneway_return_conditional::CFAOnewayReturnConditional;
 mod twoway_conditional;
 pub use twoway_conditional::CFATwowayConditional;
 
+mod switch;
+pub use switch::{
+    CFASwitch,
+    CFASwitchCase
+};
+
+mod switch_loop;
+pub use switch_loop::{
+    CFASwitchLoop,
+    CFASwitchLoopCase
+};
+
+mod short_circuit;
+pub use short_circuit::CFAShortCircuit;
+
+mod loop_break;
+pub use loop_break::CFALoopBreak;
+
 mod statement_sequence;
 pub use statement_sequence::CFAStatementSequence;
 
+mod unstructured;
+pub use unstructured::CFAUnstructured;
+
 
 use super::*;
+
+
+/// Checks that `exit` is not a temporary, unless cond branches to it directly, or gets the reason it is.
+/// 
+/// A temporary which cond branches to is the join point left by a primitive merged inside of this one, so it is
+///   the exit of this primitive too. Any other temporary stands in for a node outside of the primitive, such as
+///   the header of the loop it is in.
+pub(crate) fn check_temporary_exit(cfg : &ControlFlowGraph, cond : &CFGNode, exit : &CFGNode, explain : bool) -> Result<(), String> {
+    let is_succ = cfg.succs(cond).is_some_and(|succs| succs.contains(exit));
+    if (cfg.temps().contains(exit.from_pred()) && ! is_succ) { return Err(reason(explain, format_args!("exit {} is a temporary", exit))); }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::{
+        graph,
+        node
+    };
+
+    /// Finds the first primitive in a graph built from its edges.
+    fn first(edges : &[(&str, &str)]) -> CFAPrim {
+        CFAPrim::find_first(&graph(edges)).expect("no primitive found")
+    }
+
+    #[test]
+    fn sequence() -> () {
+        let CFAPrim::StatementSequence(prim) = first(&[ ("a", "b"), ("b", "c") ]) else { panic!("expected a sequence") };
+        assert!(prim.entry == node("a") && prim.middle.iter().eq([ &node("b") ]) && prim.exit == node("c"));
+    }
+
+    #[test]
+    fn triangle() -> () {
+        let CFAPrim::OnewayConditional(prim) = first(&[ ("a", "b"), ("a", "x"), ("b", "x") ]) else { panic!("expected an if") };
+        assert!(prim.cond == node("a") && prim.body == node("b") && prim.exit == node("x"));
+    }
+
+    #[test]
+    fn diamond() -> () {
+        let CFAPrim::TwowayConditional(prim) = first(&[ ("a", "b"), ("a", "c"), ("b", "x"), ("c", "x") ]) else { panic!("expected an if-else") };
+        assert!(prim.cond == node("a") && prim.body_a == node("b") && prim.body_b == node("c") && prim.exit == node("x"));
+    }
+
+    #[test]
+    fn while_loop() -> () {
+        let CFAPrim::PreconditionLoop(prim) = first(&[ ("e", "h"), ("h", "b"), ("b", "h"), ("h", "x") ]) else { panic!("expected a while") };
+        assert!(prim.cond == node("h") && prim.body == node("b") && prim.exit == node("x"));
+    }
+
+    #[test]
+    fn do_while_loop() -> () {
+        let CFAPrim::PostconditionLoop(prim) = first(&[ ("e", "b"), ("b", "b"), ("b", "x") ]) else { panic!("expected a do-while") };
+        assert!(prim.cond == node("b") && prim.exit == node("x"));
+    }
+
+    #[test]
+    fn infinite_loop() -> () {
+        let CFAPrim::InfiniteLoop(prim) = first(&[ ("e", "b"), ("b", "b") ]) else { panic!("expected a loop") };
+        assert!(prim.body == node("b"));
+    }
+
+    #[test]
+    fn if_in_loop() -> () {
+        // `while (h) { if (c) { t } j } x`, where the inner if is found before the loop around it.
+        let CFAPrim::OnewayConditional(prim) = first(&[ ("e", "h"), ("h", "c"), ("h", "x"), ("c", "t"), ("c", "j"), ("t", "j"), ("j", "h") ]) else { panic!("expected an if") };
+        assert!(prim.cond == node("c") && prim.body == node("t") && prim.exit == node("j"));
+    }
+
+    #[test]
+    fn temporary_exit() -> () {
+        // The join of `if (a) { b }`, replaced with a temporary which cond branches to directly.
+        let mut cfg   = graph(&[ ("a", "b"), ("a", "x"), ("b", "x") ]);
+        let temporary = cfg.create_temporary_node();
+        cfg.insert_node(&temporary, node("a"), node("x"));
+        cfg.insert_node(&temporary, node("b"), node("x"));
+        assert!(check_temporary_exit(&cfg, &node("a"), &(&temporary).into(), false).is_ok());
+        assert!(matches!(CFAPrim::find_first(&cfg), Some(CFAPrim::OnewayConditional(_))));
+
+        // The join of `if (a) { b } else { c }`, where cond does not branch to the temporary.
+        let mut cfg   = graph(&[ ("a", "b"), ("a", "c"), ("b", "x"), ("c", "x") ]);
+        let temporary = cfg.create_temporary_node();
+        cfg.insert_node(&temporary, node("b"), node("x"));
+        cfg.insert_node(&temporary, node("c"), node("x"));
+        assert!(check_temporary_exit(&cfg, &node("a"), &(&temporary).into(), false).is_err());
+        assert!(CFATwowayConditional::find_first(&cfg).is_none());
+    }
+
+}
+
@@ -11,9 +11,11 @@ use super::*;
 /// EXIT                  C
 /// ```
 pub struct CFAOnewayReturnConditional {
-    pub cond : CFGNode,
-    pub body : CFGNode,
-    pub exit : CFGNode
+    pub cond    : CFGNode,
+    pub body    : CFGNode,
+    pub exit    : CFGNode,
+    /// Whether body is reached when the condition is false, meaning the condition should be negated.
+    pub negated : bool
 }
 
 
@@ -22,6 +24,15 @@ impl CFAOnewayReturnConditional {
 
     /// Find the first one-way conditional primitive in a `ControlFlowGraph`, or `None` if none could be found.
     pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first one-way return conditional primitive in a `ControlFlowGraph`, recording why each candidate
+    ///   before it was rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |cond : &CFGNode, reason| RejectionReason::record(&mut rejected, "if-return", cond, reason);
         for cond in cfg.nodes() {
 
             // Verify that cond has two successors (body and end).
@@ -32,12 +43,14 @@ impl CFAOnewayReturnConditional {
             let a = cond_succs.next().unwrap();
             let b = cond_succs.next().unwrap();
 
-            if (Self::is_valid(cfg, cond, a, b)) {
-                return Some(Self { cond : cond.clone(), body : a.clone(), exit : b.clone() });
+            match (Self::is_valid(cfg, cond, a, b, explain)) {
+                Ok(())      => return Some(Self { cond : cond.clone(), body : a.clone(), exit : b.clone(), negated : cfg.is_false_edge(cond, a) }),
+                Err(reason) => reject(cond, reason)
             }
 
-            if (Self::is_valid(cfg, cond, b, a)) {
-                return Some(Self { cond : cond.clone(), body : b.clone(), exit : a.clone() });
+            match (Self::is_valid(cfg, cond, b, a, explain)) {
+                Ok(())      => return Some(Self { cond : cond.clone(), body : b.clone(), exit : a.clone(), negated : cfg.is_false_edge(cond, b) }),
+                Err(reason) => reject(cond, reason)
             }
 
         }
@@ -45,48 +58,65 @@ impl CFAOnewayReturnConditional {
     }
 
 
-    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, body : &CFGNode, exit : &CFGNode) -> bool {
+    /// Checks that `body` and `exit` form a one-way return conditional with `cond`, or gets the reason they do not.
+    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, body : &CFGNode, exit : &CFGNode, explain : bool) -> Result<(), String> {
 
         // Temporaries sanity check.
-        if (cfg.temps().contains(cond.to_succ())) { return false; }
-        if (cfg.temps().contains(exit.from_pred())) { return false; }
+        if (cfg.temps().contains(cond.to_succ())) { return Err(reason(explain, format_args!("cond ends in a temporary"))); }
+        check_temporary_exit(cfg, cond, exit, explain)?;
 
         // Dominator sanity check.
-        if (! cfg.dominates(cond, body)) { return false; }
-        if (! cfg.dominates(cond, exit)) { return false; }
+        if (! cfg.dominates(cond, body)) { return Err(reason(explain, format_args!("cond does not dominate body {}", body))); }
 
-        // Verify that cond is dominated by its predecessors.
         let cond_preds = cfg.preds(cond);
-        if let Some(cond_preds) = cond_preds {
-            for cond_pred in cond_preds {
-                if (! cfg.dominates(cond_pred, cond)) { return false; }
-            }
-        }
 
         // Verify that cond has two successors (body and exit).
-        let Some(cond_succs) = cfg.succs(cond) else { return false };
-        if (cond_succs.len() != 2) { return false; }
-        if (! cond_succs.contains(body)) { return false; }
-        if (! cond_succs.contains(exit)) { return false; }
+        let Some(cond_succs) = cfg.succs(cond) else { return Err(reason(explain, format_args!("cond has no successors"))) };
+        if (cond_succs.len() != 2) { return Err(reason(explain, format_args!("cond has {} successors, expected 2", cond_succs.len()))); }
+        if (! cond_succs.contains(body)) { return Err(reason(explain, format_args!("cond does not branch to body {}", body))); }
+        if (! cond_succs.contains(exit)) { return Err(reason(explain, format_args!("cond does not branch to exit {}", exit))); }
 
         // Verify that body has one predecessor (cond).
-        let Some(body_preds) = cfg.preds(body) else { return false };
-        if (body_preds.len() != 1) { return false; }
+        let Some(body_preds) = cfg.preds(body) else { return Err(reason(explain, format_args!("body {} has no predecessors", body))) };
+        if (body_preds.len() != 1) { return Err(reason(explain, format_args!("body {} has {} predecessors, expected 1", body, body_preds.len()))); }
 
         // Verify that body has zero successors.
         if let Some(body_succs) = cfg.succs(body) {
-            if (body_succs.len() > 0) { return false; }
+            if (body_succs.len() > 0) { return Err(reason(explain, format_args!("body {} has {} successors, expected 0", body, body_succs.len()))); }
         }
 
         // Verify that cond has no predecessors dominated by cond.
         //   (Indicates a loop construct)
         if let Some(cond_preds) = cond_preds {
             for cond_pred in cond_preds {
-                if (cfg.dominates(cond, cond_pred)) { return false; }
+                if (cfg.strictly_dominates(cond, cond_pred)) { return Err(reason(explain, format_args!("cond is a loop header, looped back to from {}", cond_pred))); }
             }
         }
 
-        true
+        // Verify that body is not the only way out of a loop containing cond.
+        if (Self::is_only_loop_exit(cfg, cond, body, exit)) { return Err(reason(explain, format_args!("body {} is the only way out of the loop containing cond", body))); }
+
+        Ok(())
+    }
+
+
+    /// Returns `true` if cond can be reached again from exit, but the function can not return from there
+    ///   without going through body.
+    fn is_only_loop_exit(cfg : &ControlFlowGraph, cond : &CFGNode, body : &CFGNode, exit : &CFGNode) -> bool {
+        let mut in_loop    = false;
+        let mut can_return = false;
+        let mut visited    = vec![ body ];
+        let mut stack      = vec![ exit ];
+        while let Some(node) = stack.pop() {
+            if (visited.contains(&node)) { continue; }
+            visited.push(node);
+            if (node == cond) { in_loop = true; }
+            match (cfg.succs(node)) {
+                Some(succs) if (succs.len() > 0) => stack.extend(succs),
+                _ => { can_return = true; }
+            }
+        }
+        in_loop && ! can_return
     }
 
 
@@ -103,7 +133,7 @@ impl CFAOnewayReturnConditional {
     /// ```
     pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
         // If exit does not have one predecessors (cond), insert a temporary node.
-        if (cfg.preds(&self.exit).map(|preds| preds.len()).unwrap_or(0) != 1) {
+        if (cfg.pred_count(&self.exit) != 1) {
             let temporary = cfg.create_temporary_node();
             cfg.insert_node(&temporary, &self.cond, &self.exit);
             self.exit = (&temporary).into();
@@ -116,15 +146,23 @@ impl CFAOnewayReturnConditional {
 
 impl fmt::Display for CFAOnewayReturnConditional {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[95m\x1b[1mif\x1b[0m \x1b[37m\x1b[1m(\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.cond)?;
-        write!(f, " \x1b[37m\x1b[1m) {{\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.body)?;
-        write!(f, " \x1b[95m\x1b[1mreturn\x1b[0m\x1b[2m;\x1b[0m ")?;
-        write!(f, "\x1b[37m\x1b[1m}}\x1b[0m ")?;
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.exit)?;
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAOnewayReturnConditional {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, negation, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}if{reset} {punctuation}({reset} ")?;
+        if (self.negated) { write!(f, "{negation}!{reset} ")?; }
+        write!(f, "{block}{}{reset}", self.cond)?;
+        write!(f, " {punctuation}) {{{reset} ")?;
+        write!(f, "{block}{}{reset}", self.body)?;
+        write!(f, " {keyword}return{reset}{muted};{reset} ")?;
+        write!(f, "{punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
         Ok(())
     }
 }
@@ -2,11 +2,15 @@ use super::*;
 
 
 /// ```text
-/// loop {
-///     if (! COND) { break; }
-/// }
+/// PSEUDOCODE                   GRAPH
+/// 
+/// loop {                       A <-.
+///     if (! COND) { break; }   | \_/
+/// }                            B
 /// EXIT
 /// ```
+/// 
+/// Cond is a single block which is its own successor, along with exit.
 pub struct CFAPostconditionLoop {
     pub cond : CFGNode,
     pub exit : CFGNode
@@ -16,8 +20,17 @@ pub struct CFAPostconditionLoop {
 impl CFAPostconditionLoop {
 
 
-    /// Find the first precondition loop primitive in a `ControlFlowGraph`, or `None` if none could be found.
+    /// Find the first postcondition loop primitive in a `ControlFlowGraph`, or `None` if none could be found.
     pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first postcondition loop primitive in a `ControlFlowGraph`, recording why each candidate before it
+    ///   was rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |cond : &CFGNode, reason| RejectionReason::record(&mut rejected, "loop", cond, reason);
         for cond in cfg.nodes() {
 
             // Verify that cond has two successors (cond and exit).
@@ -28,12 +41,12 @@ impl CFAPostconditionLoop {
             let a = cond_succs.next().unwrap();
             let b = cond_succs.next().unwrap();
 
-            if (Self::is_valid(cfg, cond, a)) {
-                return Some(Self { cond : cond.clone(), exit : a.clone() });
-            }
+            // Cond loops back to itself, so the exit is the other successor.
+            let exit = if (a == cond) { b } else if (b == cond) { a } else { continue };
 
-            if (Self::is_valid(cfg, cond, b)) {
-                return Some(Self { cond : cond.clone(), exit : b.clone() });
+            match (Self::is_valid(cfg, cond, exit, explain)) {
+                Ok(())      => return Some(Self { cond : cond.clone(), exit : exit.clone() }),
+                Err(reason) => reject(cond, reason)
             }
 
         }
@@ -41,22 +54,29 @@ impl CFAPostconditionLoop {
     }
 
 
-    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, exit : &CFGNode) -> bool {
+    /// Checks that `cond` is a postcondition loop leaving to `exit`, or gets the reason it is not.
+    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, exit : &CFGNode, explain : bool) -> Result<(), String> {
 
         // Temporaries sanity check.
-        if (cfg.temps().contains(cond.to_succ())) { return false; }
-        if (cfg.temps().contains(exit.from_pred())) { return false; }
+        if (cfg.temps().contains(cond.to_succ())) { return Err(reason(explain, format_args!("cond ends in a temporary"))); }
+        if (cfg.temps().contains(exit.from_pred())) { return Err(reason(explain, format_args!("exit {} is a temporary", exit))); }
 
-        // Dominator sanity check.
-        if (! cfg.dominates(cond, exit)) { return false; }
+        // A single block loop can not exit to itself.
+        if (cond == exit) { return Err(reason(explain, format_args!("cond exits to itself"))); }
 
         // Verify that cond has two successors (cond and exit).
-        let Some(cond_succs) = cfg.succs(cond) else { return false };
-        if (cond_succs.len() != 2) { return false; }
-        if (! cond_succs.contains(cond)) { return false; }
-        if (! cond_succs.contains(exit)) { return false; }
+        let Some(cond_succs) = cfg.succs(cond) else { return Err(reason(explain, format_args!("cond has no successors"))) };
+        if (cond_succs.len() != 2) { return Err(reason(explain, format_args!("cond has {} successors, expected 2", cond_succs.len()))); }
+        if (! cond_succs.contains(cond)) { return Err(reason(explain, format_args!("cond does not loop back to itself"))); }
+        if (! cond_succs.contains(exit)) { return Err(reason(explain, format_args!("cond does not branch to exit {}", exit))); }
+
+        // Verify that the body dominates the end of the loop, so that it always runs before the condition is tested.
+        //   Both are cond itself, so this only fails if cond can not be reached from the entry. Loops whose
+        //   condition is tested first are left for `CFAPreconditionLoop`, which requires cond to strictly dominate
+        //   the body.
+        if (! cfg.dominators_of(cond).contains(cond)) { return Err(reason(explain, format_args!("cond can not be reached from the entry"))); }
 
-        true
+        Ok(())
     }
 
 
@@ -73,7 +93,7 @@ impl CFAPostconditionLoop {
     /// ```
     pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
         // If exit does not have one predecessor (cond), insert a temporary node.
-        if (cfg.preds(&self.exit).map(|preds| preds.len()).unwrap_or(0) != 1) {
+        if (cfg.pred_count(&self.exit) != 1) {
             let temporary = cfg.create_temporary_node();
             cfg.insert_node(&temporary, &self.cond, &self.exit);
             self.exit = (&temporary).into();
@@ -86,13 +106,71 @@ impl CFAPostconditionLoop {
 
 impl fmt::Display for CFAPostconditionLoop {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[95m\x1b[1mloop\x1b[0m \x1b[37m\x1b[1m{{\x1b[0m \x1b[95m\x1b[1mif\x1b[0m \x1b[37m\x1b[1m(\x1b[91m!\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.cond)?;
-        write!(f, " \x1b[37m\x1b[1m) {{\x1b[0m \x1b[95m\x1b[1mbreak\x1b[0m\x1b[2m;\x1b[0m")?;
-        write!(f, " \x1b[37m\x1b[1m}}\x1b[0m ")?;
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.exit)?;
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAPostconditionLoop {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, negation, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}loop{reset} {punctuation}{{{reset} {keyword}if{reset} {punctuation}({negation}!{reset} ")?;
+        write!(f, "{block}{}{reset}", self.cond)?;
+        write!(f, " {punctuation}) {{{reset} {keyword}break{reset}{muted};{reset}")?;
+        write!(f, " {punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
         Ok(())
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfa::tests::reduce;
+    use crate::cfg::tests::{
+        graph,
+        node
+    };
+
+    #[test]
+    fn self_loop_with_exit() -> () {
+        let cfg  = graph(&[ ("entry", "a"), ("a", "a"), ("a", "exit") ]);
+        let prim = CFAPostconditionLoop::find_first(&cfg).expect("no loop found");
+        assert!(prim.cond == node("a") && prim.exit == node("exit"));
+        assert!(matches!(CFAPrim::find_first(&cfg), Some(CFAPrim::PostconditionLoop(_))));
+    }
+
+    #[test]
+    fn self_loop_at_entry() -> () {
+        let cfg  = graph(&[ ("a", "a"), ("a", "exit") ]);
+        let prim = CFAPostconditionLoop::find_first(&cfg).expect("no loop found");
+        assert!(prim.cond == node("a") && prim.exit == node("exit"));
+    }
+
+    #[test]
+    fn do_while_lowering() -> () {
+        // `while (c) { b } x` rotated into `if (g) { do { b } while (c) } x`, so the body runs before the condition.
+        let prims = reduce(graph(&[ ("g", "b"), ("g", "x"), ("b", "c"), ("c", "b"), ("c", "x") ]));
+        let loops = prims.prims().iter().filter(|prim| matches!(prim, CFAPrim::PreconditionLoop(_) | CFAPrim::PostconditionLoop(_))).collect::<Vec<_>>();
+        assert!(matches!(loops.as_slice(), [ CFAPrim::PostconditionLoop(_) ]), "expected one postcondition loop");
+    }
+
+    #[test]
+    fn do_while_at_entry() -> () {
+        // `do { b } while (c) x` at the start of the function.
+        let prims = reduce(graph(&[ ("b", "c"), ("c", "b"), ("c", "x") ]));
+        let loops = prims.prims().iter().filter(|prim| matches!(prim, CFAPrim::PreconditionLoop(_) | CFAPrim::PostconditionLoop(_))).collect::<Vec<_>>();
+        assert!(matches!(loops.as_slice(), [ CFAPrim::PostconditionLoop(_) ]), "expected one postcondition loop");
+    }
+
+    #[test]
+    fn unreachable_self_loop() -> () {
+        let mut cfg = graph(&[ ("entry", "exit") ]);
+        cfg.add_edge(node("a"), node("a"));
+        cfg.add_edge(node("a"), node("exit"));
+        assert!(CFAPostconditionLoop::find_first(&cfg).is_none());
+    }
+
+}
@@ -8,9 +8,11 @@ use super::*;
 /// EXIT
 /// ```
 pub struct CFAPreconditionLoop {
-    pub cond : CFGNode,
-    pub body : CFGNode,
-    pub exit : CFGNode
+    pub cond    : CFGNode,
+    pub body    : CFGNode,
+    pub exit    : CFGNode,
+    /// Whether body is reached when the condition is false, meaning the condition should be negated.
+    pub negated : bool
 }
 
 
@@ -19,6 +21,15 @@ impl CFAPreconditionLoop {
 
     /// Find the first precondition loop primitive in a `ControlFlowGraph`, or `None` if none could be found.
     pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first precondition loop primitive in a `ControlFlowGraph`, recording why each candidate before it
+    ///   was rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |cond : &CFGNode, reason| RejectionReason::record(&mut rejected, "while", cond, reason);
         for cond in cfg.nodes() {
 
             // Verify that cond has two successors (body and exit).
@@ -29,12 +40,14 @@ impl CFAPreconditionLoop {
             let a = cond_succs.next().unwrap();
             let b = cond_succs.next().unwrap();
 
-            if (Self::is_valid(cfg, cond, a, b)) {
-                return Some(Self { cond : cond.clone(), body : a.clone(), exit : b.clone() });
+            match (Self::is_valid(cfg, cond, a, b, explain)) {
+                Ok(())      => return Some(Self { cond : cond.clone(), body : a.clone(), exit : b.clone(), negated : cfg.is_false_edge(cond, a) }),
+                Err(reason) => reject(cond, reason)
             }
 
-            if (Self::is_valid(cfg, cond, b, a)) {
-                return Some(Self { cond : cond.clone(), body : b.clone(), exit : a.clone() });
+            match (Self::is_valid(cfg, cond, b, a, explain)) {
+                Ok(())      => return Some(Self { cond : cond.clone(), body : b.clone(), exit : a.clone(), negated : cfg.is_false_edge(cond, b) }),
+                Err(reason) => reject(cond, reason)
             }
 
         }
@@ -42,32 +55,38 @@ impl CFAPreconditionLoop {
     }
 
 
-    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, body : &CFGNode, exit : &CFGNode) -> bool {
+    /// Checks that `body` and `exit` form a precondition loop with `cond`, or gets the reason they do not.
+    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, body : &CFGNode, exit : &CFGNode, explain : bool) -> Result<(), String> {
 
         // Temporaries sanity check.
-        if (cfg.temps().contains(cond.to_succ())) { return false; }
-        if (cfg.temps().contains(exit.from_pred())) { return false; }
+        if (cfg.temps().contains(cond.to_succ())) { return Err(reason(explain, format_args!("cond ends in a temporary"))); }
+        if (cfg.temps().contains(exit.from_pred())) { return Err(reason(explain, format_args!("exit {} is a temporary", exit))); }
 
         // Dominator sanity check.
-        if (! cfg.dominates(cond, body)) { return false; }
-        if (! cfg.dominates(cond, exit)) { return false; }
+        if (! cfg.dominates(cond, body)) { return Err(reason(explain, format_args!("cond does not dominate body {}", body))); }
 
         // Verify that cond has two successors (body and exit).
-        let Some(cond_succs) = cfg.succs(cond) else { return false };
-        if (cond_succs.len() != 2) { return false; }
-        if (! cond_succs.contains(body)) { return false; }
-        if (! cond_succs.contains(exit)) { return false; }
+        let Some(cond_succs) = cfg.succs(cond) else { return Err(reason(explain, format_args!("cond has no successors"))) };
+        if (cond_succs.len() != 2) { return Err(reason(explain, format_args!("cond has {} successors, expected 2", cond_succs.len()))); }
+        if (! cond_succs.contains(body)) { return Err(reason(explain, format_args!("cond does not branch to body {}", body))); }
+        if (! cond_succs.contains(exit)) { return Err(reason(explain, format_args!("cond does not branch to exit {}", exit))); }
+        // A cond which also branches to itself never leaves the loop, so it is `loop { if (cond) { body } }`.
+        if (exit == cond) { return Err(reason(explain, format_args!("cond branches to itself"))); }
 
         // Verify that body has one predecessor (cond).
-        let Some(body_preds) = cfg.preds(body) else { return false };
-        if (body_preds.len() != 1) { return false; }
+        let Some(body_preds) = cfg.preds(body) else { return Err(reason(explain, format_args!("body {} has no predecessors", body))) };
+        if (body_preds.len() != 1) { return Err(reason(explain, format_args!("body {} has {} predecessors, expected 1", body, body_preds.len()))); }
 
         // Verify that body has one successor (cond).
-        let Some(body_succs) = cfg.succs(body) else { return false };
-        if (body_succs.len() != 1) { return false; }
-        if (! body_succs.contains(cond)) { return false };
+        let Some(body_succs) = cfg.succs(body) else { return Err(reason(explain, format_args!("body {} has no successors", body))) };
+        if (body_succs.len() != 1) { return Err(reason(explain, format_args!("body {} has {} successors, expected 1", body, body_succs.len()))); }
+        if (! body_succs.contains(cond)) { return Err(reason(explain, format_args!("body {} does not loop back to cond", body))) };
 
-        true
+        // Verify that cond is always tested before body runs. If body can be reached without going through cond
+        //   (such as when body is the entry), the loop runs body first and is a postcondition loop instead.
+        if (! cfg.strictly_dominates(cond, body)) { return Err(reason(explain, format_args!("cond does not strictly dominate body {}", body))); }
+
+        Ok(())
     }
 
 
@@ -84,7 +103,7 @@ impl CFAPreconditionLoop {
     /// ```
     pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
         // If exit does not have one predecessor (cond), insert a temporary node.
-        if (cfg.preds(&self.exit).map(|preds| preds.len()).unwrap_or(0) != 1) {
+        if (cfg.pred_count(&self.exit) != 1) {
             let temporary = cfg.create_temporary_node();
             cfg.insert_node(&temporary, &self.cond, &self.exit);
             self.exit = (&temporary).into();
@@ -97,14 +116,65 @@ impl CFAPreconditionLoop {
 
 impl fmt::Display for CFAPreconditionLoop {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[95m\x1b[1mwhile\x1b[0m \x1b[37m\x1b[1m(\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.cond)?;
-        write!(f, " \x1b[37m\x1b[1m) {{\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.body)?;
-        write!(f, " \x1b[37m\x1b[1m}}\x1b[0m ")?;
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.exit)?;
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAPreconditionLoop {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, negation, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}while{reset} {punctuation}({reset} ")?;
+        if (self.negated) { write!(f, "{negation}!{reset} ")?; }
+        write!(f, "{block}{}{reset}", self.cond)?;
+        write!(f, " {punctuation}) {{{reset} ")?;
+        write!(f, "{block}{}{reset}", self.body)?;
+        write!(f, " {punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
         Ok(())
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::{
+        function,
+        graph,
+        node
+    };
+
+    #[test]
+    fn while_lowering() -> () {
+        // `while (h) { b } x`, where the condition is tested before the body first runs.
+        let cfg  = graph(&[ ("e", "h"), ("h", "b"), ("b", "h"), ("h", "x") ]);
+        let prim = CFAPreconditionLoop::find_first(&cfg).expect("no loop found");
+        assert!(prim.cond == node("h") && prim.body == node("b") && prim.exit == node("x"));
+    }
+
+    #[test]
+    fn body_at_entry() -> () {
+        // `do { b } while (c) x`, where the body is the entry, so it is not reached through the condition.
+        let cfg = graph(&[ ("b", "c"), ("c", "b"), ("c", "x") ]);
+        assert!(CFAPreconditionLoop::find_first(&cfg).is_none());
+    }
+
+    #[test]
+    fn guard_on_true_edge() -> () {
+        // `while (h) { b } x`, where the body is the true destination of the guard.
+        let cfg  = ControlFlowGraph::new(&function(&[ ("e", &[ "h" ]), ("h", &[ "b", "x" ]), ("b", &[ "h" ]), ("x", &[]) ]));
+        let prim = CFAPreconditionLoop::find_first(&cfg).expect("no loop found");
+        assert!(prim.body == node("b") && ! prim.negated);
+    }
+
+    #[test]
+    fn guard_on_false_edge() -> () {
+        // `while (! h) { b } x`, where the body is the false destination of the guard.
+        let cfg  = ControlFlowGraph::new(&function(&[ ("e", &[ "h" ]), ("h", &[ "x", "b" ]), ("b", &[ "h" ]), ("x", &[]) ]));
+        let prim = CFAPreconditionLoop::find_first(&cfg).expect("no loop found");
+        assert!(prim.body == node("b") && prim.negated);
+    }
+
+}
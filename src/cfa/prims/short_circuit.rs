@@ -0,0 +1,145 @@
+use super::*;
+
+
+/// ```text
+/// PSEUDOCODE                    GRAPH
+/// 
+/// if (FIRST || SECOND) {          A
+///     ...                        / |
+/// }                             B  |
+///                              / \ |
+///                             ..  C
+/// ```
+/// 
+/// Two conditions which branch to the same node, where the second is only reached from the first. They are merged
+///   into a single condition, which reaches the shared node when either condition does. This lets a chain of
+///   guards which all branch to the same node be reduced one condition at a time.
+/// 
+/// The merged condition branches the same way as `second`, so it is true exactly when `second` would be.
+pub struct CFAShortCircuit {
+    pub first       : CFGNode,
+    pub second      : CFGNode,
+    /// Whether the conditions are joined with `||` rather than `&&`.
+    pub disjunction : bool,
+    /// Whether the condition of `first` is negated.
+    pub negated     : bool
+}
+
+
+impl CFAShortCircuit {
+
+
+    /// Find the first short-circuit condition primitive in a `ControlFlowGraph`, or `None` if none could be found.
+    pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first short-circuit condition primitive in a `ControlFlowGraph`, recording why each candidate before it
+    ///   was rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |first : &CFGNode, reason| RejectionReason::record(&mut rejected, "short-circuit", first, reason);
+        for first in cfg.nodes() {
+
+            // Verify that first has two successors (second and shared).
+            let Some(first_succs) = cfg.succs(first) else { continue };
+            if (first_succs.len() != 2) { continue; }
+
+            let mut first_succs = first_succs.into_iter();
+            let a = first_succs.next().unwrap();
+            let b = first_succs.next().unwrap();
+
+            match (Self::is_valid(cfg, first, a, b, explain)) {
+                Ok(())      => return Some(Self::new(cfg, first, a, b)),
+                Err(reason) => reject(first, reason)
+            }
+
+            match (Self::is_valid(cfg, first, b, a, explain)) {
+                Ok(())      => return Some(Self::new(cfg, first, b, a)),
+                Err(reason) => reject(first, reason)
+            }
+
+        }
+        None
+    }
+
+
+    fn new(cfg : &ControlFlowGraph, first : &CFGNode, second : &CFGNode, shared : &CFGNode) -> Self {
+        // The merged condition is true when second is, so the operator depends on which way second reaches shared.
+        let first_to_shared  = ! cfg.is_false_edge(first, shared);
+        let second_to_shared = ! cfg.is_false_edge(second, shared);
+        Self {
+            first       : first.clone(),
+            second      : second.clone(),
+            disjunction : second_to_shared,
+            negated     : first_to_shared != second_to_shared
+        }
+    }
+
+
+    /// Checks that `first` and `second` form a short-circuit condition which both branch to `shared`, or gets the
+    ///   reason they do not.
+    fn is_valid(cfg : &ControlFlowGraph, first : &CFGNode, second : &CFGNode, shared : &CFGNode, explain : bool) -> Result<(), String> {
+
+        // Temporaries sanity check.
+        if (cfg.temps().contains(first.to_succ())) { return Err(reason(explain, format_args!("first ends in a temporary"))); }
+        if (cfg.temps().contains(second.from_pred())) { return Err(reason(explain, format_args!("second {} starts with a temporary", second))); }
+        if (cfg.temps().contains(second.to_succ())) { return Err(reason(explain, format_args!("second {} ends in a temporary", second))); }
+
+        // Only conditional branches can be joined, and the way they branch must be known.
+        if (! Self::is_cond_edge(cfg, first, second)) { return Err(reason(explain, format_args!("first does not conditionally branch to second {}", second))); }
+        if (! Self::is_cond_edge(cfg, first, shared)) { return Err(reason(explain, format_args!("first does not conditionally branch to shared {}", shared))); }
+        if (! Self::is_cond_edge(cfg, second, shared)) { return Err(reason(explain, format_args!("second {} does not conditionally branch to shared {}", second, shared))); }
+
+        // Verify that second has one predecessor (first).
+        let second_preds = cfg.pred_count(second);
+        if (second_preds != 1) { return Err(reason(explain, format_args!("second {} has {} predecessors, expected 1", second, second_preds))); }
+
+        // Verify that second has two successors, one of which is shared.
+        let Some(second_succs) = cfg.succs(second) else { return Err(reason(explain, format_args!("second {} has no successors", second))) };
+        if (second_succs.len() != 2) { return Err(reason(explain, format_args!("second {} has {} successors, expected 2", second, second_succs.len()))); }
+        if (! second_succs.contains(shared)) { return Err(reason(explain, format_args!("second {} does not branch to shared {}", second, shared))); }
+
+        // Loops are handled by the loop primitives.
+        if (first == shared || second_succs.contains(first)) { return Err(reason(explain, format_args!("first is in a loop with second"))); }
+
+        Ok(())
+    }
+
+
+    /// Returns `true` if `from` ends in a conditional branch, and `to` is one of its destinations.
+    fn is_cond_edge(cfg : &ControlFlowGraph, from : &CFGNode, to : &CFGNode) -> bool {
+        match (cfg.branch(from.to_succ())) {
+            Some(CFGBranch::Cond { true_dest, false_dest }) => true_dest == to.from_pred() || false_dest == to.from_pred(),
+            _ => false
+        }
+    }
+
+
+}
+
+
+impl fmt::Display for CFAShortCircuit {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAShortCircuit {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { punctuation, block, negation, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{punctuation}({reset} ")?;
+        if (self.negated) { write!(f, "{negation}!{reset} ")?; }
+        write!(f, "{block}{}{reset}", self.first)?;
+        if (self.disjunction) {
+            write!(f, " {punctuation}||{reset} ")?;
+        } else {
+            write!(f, " {punctuation}&&{reset} ")?;
+        }
+        write!(f, "{block}{}{reset}", self.second)?;
+        write!(f, " {punctuation}){reset}")?;
+        Ok(())
+    }
+}
@@ -2,14 +2,17 @@ use super::*;
 
 
 /// ```text
-/// if (COND) {
-///     BODY
-/// }
+/// ENTRY
+/// MIDDLE
 /// EXIT
 /// ```
+/// 
+/// A whole straight-line chain of nodes is found at once, rather than one edge at a time.
 pub struct CFAStatementSequence {
-    pub entry : CFGNode,
-    pub exit  : CFGNode
+    pub entry  : CFGNode,
+    /// The nodes between entry and exit, in order. Empty if entry branches directly to exit.
+    pub middle : Vec<CFGNode>,
+    pub exit   : CFGNode
 }
 
 
@@ -18,6 +21,15 @@ impl CFAStatementSequence {
 
     /// Find the first sequential statements primitive in a `ControlFlowGraph`, or `None` if none could be found.
     pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first sequential statements primitive in a `ControlFlowGraph`, recording why each candidate before it
+    ///   was rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |entry : &CFGNode, reason| RejectionReason::record(&mut rejected, "sequence", entry, reason);
         for entry in cfg.nodes() {
 
             // Verify that entry has one successor (exit).
@@ -27,8 +39,13 @@ impl CFAStatementSequence {
             let mut entry_succs = entry_succs.into_iter();
             let exit = entry_succs.next().unwrap();
 
-            if (Self::is_valid(cfg, entry, exit)) {
-                return Some(Self { entry : entry.clone(), exit : exit.clone() });
+            match (Self::is_valid(cfg, entry, exit, explain)) {
+                Ok(()) => {
+                    let mut prim = Self { entry : entry.clone(), middle : Vec::new(), exit : exit.clone() };
+                    prim.extend(cfg);
+                    return Some(prim);
+                },
+                Err(reason) => reject(entry, reason)
             }
 
         }
@@ -36,21 +53,41 @@ impl CFAStatementSequence {
     }
 
 
-    fn is_valid(cfg : &ControlFlowGraph, entry : &CFGNode, exit : &CFGNode) -> bool {
+    /// Checks that `entry` and `exit` can be joined into a sequence, or gets the reason they can not.
+    fn is_valid(cfg : &ControlFlowGraph, entry : &CFGNode, exit : &CFGNode, explain : bool) -> Result<(), String> {
 
-        // Temporaries sanity check.
-        if (cfg.temps().contains(entry.to_succ())) { return false; }
-        if (cfg.temps().contains(exit.from_pred())) { return false; }
+        // Temporaries sanity check. A temporary at the end of entry stands in for the header of the loop it is in,
+        //   so it must be kept unless exit is only reached from entry, outside of that loop.
+        if (cfg.temps().contains(entry.to_succ()) && (cfg.pred_count(exit) != 1 || cfg.dominates(exit, entry))) { return Err(reason(explain, format_args!("entry ends in a temporary"))); }
+        if (cfg.temps().contains(exit.from_pred())) { return Err(reason(explain, format_args!("exit {} is a temporary", exit))); }
 
         // Dominator sanity check.
-        if (! cfg.dominates(entry, exit)) { return false; }
+        if (! cfg.dominates(entry, exit)) { return Err(reason(explain, format_args!("entry does not dominate exit {}", exit))); }
 
         // Verify that entry has one successor (exit).
-        let Some(entry_succs) = cfg.succs(entry) else { return false };
-        if (entry_succs.len() != 1) { return false; }
-        if (! entry_succs.contains(exit)) { return false };
+        let Some(entry_succs) = cfg.succs(entry) else { return Err(reason(explain, format_args!("entry has no successors"))) };
+        if (entry_succs.len() != 1) { return Err(reason(explain, format_args!("entry has {} successors, expected 1", entry_succs.len()))); }
+        if (! entry_succs.contains(exit)) { return Err(reason(explain, format_args!("entry does not branch to exit {}", exit))) };
 
-        true
+        Ok(())
+    }
+
+
+    /// Extends the sequence along the chain of nodes after exit, for as long as each one is only reached from the
+    ///   one before it.
+    /// 
+    /// Nodes with other predecessors end the chain, so that a temporary node is only ever needed directly after
+    ///   entry.
+    fn extend(&mut self, cfg : &ControlFlowGraph) -> () {
+        while (cfg.pred_count(&self.exit) == 1) {
+            let Some(exit_succs) = cfg.succs(&self.exit) else { break };
+            if (exit_succs.len() != 1) { break; }
+            let next = exit_succs.into_iter().next().unwrap();
+            if (cfg.pred_count(next) != 1 || next == &self.entry || self.middle.contains(next)) { break; }
+            if (Self::is_valid(cfg, &self.exit, next, false).is_err()) { break; }
+            let exit = std::mem::replace(&mut self.exit, next.clone());
+            self.middle.push(exit);
+        }
     }
 
 
@@ -65,7 +102,7 @@ impl CFAStatementSequence {
     /// ```
     pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
         // If exit does not have one predecessor (entry), insert a temporary node.
-        if (cfg.preds(&self.exit).map(|preds| preds.len()).unwrap_or(0) != 1) {
+        if (cfg.pred_count(&self.exit) != 1) {
             let temporary = cfg.create_temporary_node();
             cfg.insert_node(&temporary, &self.entry, &self.exit);
             self.exit = (&temporary).into();
@@ -78,10 +115,21 @@ impl CFAStatementSequence {
 
 impl fmt::Display for CFAStatementSequence {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.entry)?;
-        write!(f, " \x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.exit)?;
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAStatementSequence {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { block, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.entry)?;
+        for node in &self.middle {
+            write!(f, " {muted}->{reset} ")?;
+            write!(f, "{block}{}{reset}", node)?;
+        }
+        write!(f, " {muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
         Ok(())
     }
 }
@@ -0,0 +1,296 @@
+use super::*;
+
+use crate::cfg::{
+    CFGBranch,
+    CFGCaseValue
+};
+
+
+/// ```text
+/// PSEUDOCODE           GRAPH
+/// 
+/// switch (COND) {         A
+///     case X:           / | \
+///         BODY_X       B  C  |
+///     default:          \ | /
+///         DEFAULT         D
+/// }
+/// EXIT
+/// ```
+/// 
+/// `default` is `None` when the default destination is the exit itself, meaning that there is no default body.
+/// 
+/// Each body must be a single node with one predecessor and one successor, so a switch is only found once every
+///   region inside of its arms has been merged. Loops and other switches in the arms, and switches in the body of
+///   a loop, are merged first, so nested regions always collapse from the inside out.
+/// 
+/// The exit has no limit on its predecessors, so every arm may break to it, however many there are. If the exit is
+///   also reached from outside of the switch, such as by the latch of a loop around it, the arms are joined by a
+///   single temporary node when the switch is merged.
+pub struct CFASwitch {
+    pub cond    : CFGNode,
+    pub cases   : Vec<CFASwitchCase>,
+    pub default : Option<CFGNode>,
+    pub exit    : CFGNode
+}
+
+/// A single arm of a `CFASwitch`.
+pub struct CFASwitchCase {
+    /// All of the values which branch to this arm.
+    pub values : Vec<CFGCaseValue>,
+    /// The body of the arm, or `None` if the values branch directly to the exit.
+    pub body   : Option<CFGNode>
+}
+
+
+impl CFASwitch {
+
+
+    /// Find the first switch primitive in a `ControlFlowGraph`, or `None` if none could be found.
+    /// 
+    /// A `switch` where every case branches to the default destination has only one successor, and is left
+    ///   to be found as a statement sequence instead.
+    pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first switch primitive in a `ControlFlowGraph`, recording why each candidate before it
+    ///   was rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |cond : &CFGNode, reason| RejectionReason::record(&mut rejected, "switch", cond, reason);
+        for cond in cfg.nodes() {
+
+            // Verify that cond ends in a switch.
+            let Some(CFGBranch::Switch { cases, default }) = cfg.branch(cond.to_succ()) else { continue };
+
+            // Verify that cond has multiple successors.
+            let Some(cond_succs) = cfg.succs(cond) else { continue };
+            if (cond_succs.len() < 2) { continue; }
+
+            // The exit is either a successor of cond, or the successor of one of the bodies.
+            for succ in cond_succs {
+                let mut exits = vec![ succ ];
+                if let Some(succ_succs) = cfg.succs(succ) { if (succ_succs.len() == 1) {
+                    exits.push(succ_succs.into_iter().next().unwrap());
+                } }
+
+                for exit in exits {
+                    match (Self::is_valid(cfg, cond, exit, explain).and_then(|()| Self::from_branch(cfg, cond, cases, default, exit, explain))) {
+                        Ok(prim)    => return Some(prim),
+                        Err(reason) => reject(cond, reason)
+                    }
+                }
+            }
+
+        }
+        None
+    }
+
+
+    /// Groups the case values of the switch by the successor of cond that they branch to, or gets the reason one of
+    ///   them could not be found.
+    fn from_branch(cfg : &ControlFlowGraph, cond : &CFGNode, cases : &Vec<(CFGCaseValue, Name)>, default : &Name, exit : &CFGNode, explain : bool) -> Result<Self, String> {
+        let find_succ = |dest : &Name| cfg.branch_succ(cond, dest).ok_or_else(|| reason(explain, format_args!("cond has no successor for {}", dest)));
+        let as_body   = |node : &CFGNode| if (node == exit) { None } else { Some(node.clone()) };
+
+        let mut out_cases : Vec<CFASwitchCase> = Vec::new();
+        for (value, dest) in cases {
+            // Cases which branch to the default destination are covered by the default arm.
+            if (dest == default) { continue; }
+            let body = as_body(find_succ(dest)?);
+            if let Some(case) = out_cases.iter_mut().find(|case| case.body == body) {
+                case.values.push(*value);
+            } else {
+                out_cases.push(CFASwitchCase { values : vec![ *value ], body });
+            }
+        }
+
+        Ok(Self {
+            cond    : cond.clone(),
+            cases   : out_cases,
+            default : as_body(find_succ(default)?),
+            exit    : exit.clone()
+        })
+    }
+
+
+    /// Checks that every successor of `cond` is either `exit` or a body leading to it, or gets the reason they are
+    ///   not.
+    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, exit : &CFGNode, explain : bool) -> Result<(), String> {
+
+        // Temporaries sanity check.
+        if (cfg.temps().contains(cond.to_succ())) { return Err(reason(explain, format_args!("cond ends in a temporary"))); }
+        if (cfg.temps().contains(exit.from_pred())) { return Err(reason(explain, format_args!("exit {} is a temporary", exit))); }
+
+        if (cond == exit) { return Err(reason(explain, format_args!("cond exits to itself"))); }
+
+        // Cond does not need to dominate exit, as an exit which is also reached from outside of the switch is given a
+        //   temporary join when merged.
+
+        let Some(cond_succs) = cfg.succs(cond) else { return Err(reason(explain, format_args!("cond has no successors"))) };
+        for body in cond_succs {
+            if (body == exit) { continue; }
+
+            // Dominator sanity check.
+            if (! cfg.dominates(cond, body)) { return Err(reason(explain, format_args!("cond does not dominate body {}", body))); }
+
+            // Verify that body has one predecessor (cond).
+            let Some(body_preds) = cfg.preds(body) else { return Err(reason(explain, format_args!("body {} has no predecessors", body))) };
+            if (body_preds.len() != 1) { return Err(reason(explain, format_args!("body {} has {} predecessors, expected 1", body, body_preds.len()))); }
+
+            // Verify that body has one successor (exit).
+            let Some(body_succs) = cfg.succs(body) else { return Err(reason(explain, format_args!("body {} has no successors", body))) };
+            if (body_succs.len() != 1) { return Err(reason(explain, format_args!("body {} has {} successors, expected 1", body, body_succs.len()))); }
+            if (! body_succs.contains(exit)) { return Err(reason(explain, format_args!("body {} does not branch to exit {}", body, exit))); }
+        }
+
+        Ok(())
+    }
+
+
+    /// Handles the special case where the node is directly at the end of a loop.
+    /// An additional temporary node will be added if needed.
+    /// 
+    /// ```text
+    /// while (EXIT) {     <- The exit is here, which makes analysis hard.
+    ///     switch (COND) {
+    ///         case X:
+    ///             BODY_X
+    ///     }
+    ///     TEMPORARY     <- This temporary node is added to make the analysis process easier.
+    /// }
+    /// ```
+    pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
+        // Every successor of cond either is exit, or branches to it, so the temporary node takes all of their edges.
+        let cond_succs = cfg.succs(&self.cond).map(|succs| succs.clone()).unwrap_or_else(|| UniqueVec::new());
+        // If exit has any other predecessors, insert a temporary node.
+        if (cfg.pred_count(&self.exit) != cond_succs.len()) {
+            let temporary = cfg.create_temporary_node();
+            for succ in &cond_succs {
+                if (succ == &self.exit) {
+                    cfg.insert_node(&temporary, &self.cond, &self.exit);
+                } else {
+                    cfg.insert_node(&temporary, succ, &self.exit);
+                }
+            }
+            self.exit = (&temporary).into();
+        }
+    }
+
+
+    /// Get all of the bodies in the primitive, including the default.
+    pub fn bodies(&self) -> Vec<&CFGNode> {
+        self.cases.iter().filter_map(|case| case.body.as_ref()).chain(self.default.as_ref()).collect()
+    }
+
+
+}
+
+
+impl fmt::Display for CFASwitch {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFASwitch {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}switch{reset} {punctuation}({reset} ")?;
+        write!(f, "{block}{}{reset}", self.cond)?;
+        write!(f, " {punctuation}) {{{reset} ")?;
+        for case in &self.cases {
+            write!(f, "{keyword}case{reset} ")?;
+            let mut first = true;
+            for value in &case.values {
+                if (first) { first = false; }
+                else { write!(f, "{muted},{reset} ")?; }
+                write!(f, "{}", value)?;
+            }
+            write!(f, "{muted}:{reset} ")?;
+            match (&case.body) {
+                Some(body) => write!(f, "{block}{}{reset} ", body)?,
+                None       => write!(f, "{keyword}break{reset}{muted};{reset} ")?
+            }
+        }
+        if let Some(default) = &self.default {
+            write!(f, "{keyword}default{reset}{muted}:{reset} ")?;
+            write!(f, "{block}{}{reset} ", default)?;
+        }
+        write!(f, "{punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfa::tests::reduce;
+    use crate::cfg::tests::{
+        function,
+        node
+    };
+
+    /// The blocks of `switch (s) { 0 => c0, ..., 7 => c7 }`, where every arm breaks to `x`.
+    const EIGHT_CASES : &[(&str, &[&str])] = &[
+        ("s",  &[ "c0", "c1", "c2", "c3", "c4", "c5", "c6", "c7", "x" ]),
+        ("c0", &[ "x" ]), ("c1", &[ "x" ]), ("c2", &[ "x" ]), ("c3", &[ "x" ]),
+        ("c4", &[ "x" ]), ("c5", &[ "x" ]), ("c6", &[ "x" ]), ("c7", &[ "x" ]),
+        ("x",  &[])
+    ];
+
+    #[test]
+    fn switch_in_while_in_switch() -> () {
+        // `switch (s) { 0 => a, 1 => while (h) { switch (b) { 0 => c, 1 => d } } }`
+        let function = function(&[
+            ("s", &[ "a", "h", "x" ]),
+            ("a", &[ "x" ]),
+            ("h", &[ "b", "l" ]),
+            ("b", &[ "c", "d", "j" ]),
+            ("c", &[ "j" ]),
+            ("d", &[ "j" ]),
+            ("j", &[ "h" ]),
+            ("l", &[ "x" ]),
+            ("x", &[])
+        ]);
+        let prims    = reduce(ControlFlowGraph::new(&function));
+        let position = |find : &dyn Fn(&CFAPrim) -> bool| prims.prims().iter().position(find).expect("primitive not found");
+        // The regions merge from the inside out.
+        let inner    = position(&|prim| matches!(prim, CFAPrim::Switch(switch) if switch.cond == node("b")));
+        let looped   = position(&|prim| matches!(prim, CFAPrim::PreconditionLoop(_)));
+        let outer    = position(&|prim| matches!(prim, CFAPrim::Switch(switch) if switch.cond == node("s")));
+        assert!(inner < looped && looped < outer);
+        let CFAPrim::Switch(outer) = &prims.prims()[outer] else { unreachable!() };
+        assert!(outer.cases.len() == 2 && outer.default.is_none());
+    }
+
+    #[test]
+    fn eight_cases_sharing_exit() -> () {
+        let cfg  = ControlFlowGraph::new(&function(EIGHT_CASES));
+        let prim = CFASwitch::find_first(&cfg).expect("no switch found");
+        assert!(prim.cond == node("s") && prim.exit == node("x") && prim.default.is_none());
+        assert!(prim.cases.len() == 8 && prim.cases.iter().all(|case| case.body.is_some()));
+        reduce(cfg);
+    }
+
+    #[test]
+    fn eight_cases_sharing_exit_with_others() -> () {
+        // `if (a) { switch (s) { ... } } x`, where the exit is also reached from outside of the switch.
+        let mut blocks = vec![ ("a", &[ "s", "x" ][..]) ];
+        blocks.extend_from_slice(EIGHT_CASES);
+        let mut cfg  = ControlFlowGraph::new(&function(&blocks));
+        let mut prim = CFAPrim::Switch(CFASwitch::find_first(&cfg).expect("no switch found"));
+        assert!(prim.merge(&mut cfg).is_empty());
+        // A single temporary node joins the arms.
+        assert!(cfg.temps().len() == 1);
+        assert!(cfg.pred_count(node("x")) == 2);
+        reduce(cfg);
+    }
+
+}
@@ -0,0 +1,335 @@
+use super::*;
+
+use crate::cfg::{
+    CFGBranch,
+    CFGCaseValue
+};
+
+
+/// ```text
+/// PSEUDOCODE                  GRAPH
+/// 
+/// loop {                        A <---.
+///     switch (COND) {          / \     |
+///         case X:             B   D    |
+///             BODY_X          |        |
+///             continue;       L -------'
+///         default:
+///             break;
+///     }
+/// } continue {
+///     LATCH
+/// }
+/// EXIT
+/// ```
+/// 
+/// Cond is the header of a loop and ends in a `switch`, as compiled from a state machine such as
+///   `loop { match state { .. } }`. Each arm either goes back around the loop, through the latch if there is one,
+///   or leaves it for the exit. The switch is on every way into the loop, so neither the loop primitives nor the
+///   switch primitive can find it on their own.
+/// 
+/// Each body must be a single node with one predecessor and one successor, as with a `CFASwitch`, and the latch
+///   must only be reached from the arms. If no arm leaves the loop, the exit is a temporary node with no
+///   successors, added when the primitive is merged, as with a `CFAInfiniteLoop`.
+pub struct CFASwitchLoop {
+    pub cond    : CFGNode,
+    pub cases   : Vec<CFASwitchLoopCase>,
+    /// The arm taken when no case matches, which has no values.
+    pub default : CFASwitchLoopCase,
+    /// Run after every arm which goes back around the loop, or `None` if they branch straight back to cond.
+    pub latch   : Option<CFGNode>,
+    pub exit    : CFGNode
+}
+
+/// A single arm of a `CFASwitchLoop`.
+pub struct CFASwitchLoopCase {
+    /// All of the values which branch to this arm.
+    pub values : Vec<CFGCaseValue>,
+    /// The body of the arm, or `None` if the values branch directly to the latch or the exit.
+    pub body   : Option<CFGNode>,
+    /// Whether the arm leaves the loop for the exit, rather than going back around it.
+    pub breaks : bool
+}
+
+
+impl CFASwitchLoop {
+
+
+    /// Find the first switch loop primitive in a `ControlFlowGraph`, or `None` if none could be found.
+    pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first switch loop primitive in a `ControlFlowGraph`, recording why each candidate before it was
+    ///   rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |cond : &CFGNode, reason| RejectionReason::record(&mut rejected, "switch loop", cond, reason);
+        for cond in cfg.nodes() {
+
+            // Verify that cond ends in a switch.
+            let Some(CFGBranch::Switch { cases, default }) = cfg.branch(cond.to_succ()) else { continue };
+
+            // Verify that cond has multiple successors.
+            let Some(cond_succs) = cfg.succs(cond) else { continue };
+            if (cond_succs.len() < 2) { continue; }
+
+            // The latch and the exit are each either a successor of cond, or the successor of one of the bodies.
+            let mut nearby : Vec<&CFGNode> = Vec::new();
+            for succ in cond_succs {
+                nearby.push(succ);
+                if let Some(succ_succs) = cfg.succs(succ) { if (succ_succs.len() == 1) {
+                    nearby.push(succ_succs.into_iter().next().unwrap());
+                } }
+            }
+            let mut latches = vec![ None ];
+            for &node in &nearby {
+                let leads_to_cond = cfg.succs(node).map(|succs| succs.len() == 1 && succs.contains(cond)).unwrap_or(false);
+                if (node != cond && leads_to_cond && ! latches.contains(&Some(node))) { latches.push(Some(node)); }
+            }
+            let mut exits = Vec::new();
+            for &node in &nearby {
+                if (node != cond && ! exits.contains(&Some(node))) { exits.push(Some(node)); }
+            }
+            exits.push(None);
+
+            for &latch in &latches {
+                for &exit in &exits {
+                    if (latch.is_some() && latch == exit) { continue; }
+                    match (Self::is_valid(cfg, cond, latch, exit, explain).and_then(|()| Self::from_branch(cfg, cond, cases, default, latch, exit, explain))) {
+                        Ok(prim)    => return Some(prim),
+                        Err(reason) => reject(cond, reason)
+                    }
+                }
+            }
+
+        }
+        None
+    }
+
+
+    /// Groups the case values of the switch by the successor of cond that they branch to, or gets the reason one of
+    ///   them could not be found.
+    fn from_branch(cfg : &ControlFlowGraph, cond : &CFGNode, cases : &Vec<(CFGCaseValue, Name)>, default : &Name, latch : Option<&CFGNode>, exit : Option<&CFGNode>, explain : bool) -> Result<Self, String> {
+        let next      = latch.unwrap_or(cond);
+        let find_succ = |dest : &Name| cfg.branch_succ(cond, dest).ok_or_else(|| reason(explain, format_args!("cond has no successor for {}", dest)));
+        let as_arm    = |values, node : &CFGNode| {
+            // Every body branches to one of the latch or the exit.
+            let breaks = if (node == next) { false }
+                else if (Some(node) == exit) { true }
+                else { ! cfg.succs(node).map(|succs| succs.contains(next)).unwrap_or(false) };
+            let body = if (node == next || Some(node) == exit) { None } else { Some(node.clone()) };
+            CFASwitchLoopCase { values, body, breaks }
+        };
+
+        let mut out_cases : Vec<CFASwitchLoopCase> = Vec::new();
+        for (value, dest) in cases {
+            // Cases which branch to the default destination are covered by the default arm.
+            if (dest == default) { continue; }
+            let arm = as_arm(vec![ *value ], find_succ(dest)?);
+            if let Some(case) = out_cases.iter_mut().find(|case| case.body == arm.body && case.breaks == arm.breaks) {
+                case.values.push(*value);
+            } else {
+                out_cases.push(arm);
+            }
+        }
+
+        Ok(Self {
+            cond    : cond.clone(),
+            cases   : out_cases,
+            default : as_arm(Vec::new(), find_succ(default)?),
+            latch   : latch.cloned(),
+            // Replaced by a temporary node when merged, if no arm leaves the loop.
+            exit    : exit.unwrap_or(cond).clone()
+        })
+    }
+
+
+    /// Checks that every successor of `cond` is the latch, the exit, or a body leading to one of them, or gets the
+    ///   reason they are not. Without a latch, the arms which go back around the loop branch straight to cond.
+    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, latch : Option<&CFGNode>, exit : Option<&CFGNode>, explain : bool) -> Result<(), String> {
+
+        // Temporaries sanity check.
+        if (cfg.temps().contains(cond.to_succ())) { return Err(reason(explain, format_args!("cond ends in a temporary"))); }
+        let next = latch.unwrap_or(cond);
+        if let Some(exit) = exit {
+            if (cfg.temps().contains(exit.from_pred())) { return Err(reason(explain, format_args!("exit {} is a temporary", exit))); }
+            // A node which branches straight back to the latch or cond is part of an arm which goes around the loop again.
+            if (cfg.succs(exit).map(|exit_succs| exit_succs.contains(cond) || exit_succs.contains(next)).unwrap_or(false)) { return Err(reason(explain, format_args!("exit {} loops back into the switch", exit))); }
+        }
+        let Some(cond_succs) = cfg.succs(cond) else { return Err(reason(explain, format_args!("cond has no successors"))) };
+        let mut loops = false;
+        for body in cond_succs {
+            if (body == next) { loops = true; continue; }
+            if (Some(body) == exit) { continue; }
+
+            // Dominator sanity check.
+            if (! cfg.strictly_dominates(cond, body)) { return Err(reason(explain, format_args!("cond does not strictly dominate body {}", body))); }
+
+            // Verify that body has one predecessor (cond).
+            let Some(body_preds) = cfg.preds(body) else { return Err(reason(explain, format_args!("body {} has no predecessors", body))) };
+            if (body_preds.len() != 1) { return Err(reason(explain, format_args!("body {} has {} predecessors, expected 1", body, body_preds.len()))); }
+
+            // Verify that body has one successor (the latch or exit).
+            let Some(body_succs) = cfg.succs(body) else { return Err(reason(explain, format_args!("body {} has no successors", body))) };
+            if (body_succs.len() != 1) { return Err(reason(explain, format_args!("body {} has {} successors, expected 1", body, body_succs.len()))); }
+            if (body_succs.contains(next)) {
+                loops = true;
+            } else if (! exit.map(|exit| body_succs.contains(exit)).unwrap_or(false)) {
+                return Err(reason(explain, format_args!("body {} does not branch to the latch or exit", body)));
+            }
+        }
+        if (! loops) { return Err(reason(explain, format_args!("no arm goes back around the loop"))); }
+
+        // Verify that the latch is only reached from the arms, and leads back to cond.
+        if let Some(latch) = latch {
+            if (! cfg.strictly_dominates(cond, latch)) { return Err(reason(explain, format_args!("cond does not strictly dominate latch {}", latch))); }
+            let Some(latch_preds) = cfg.preds(latch) else { return Err(reason(explain, format_args!("latch {} has no predecessors", latch))) };
+            if let Some(pred) = latch_preds.iter().find(|&pred| pred != cond && (! cond_succs.contains(pred) || Some(pred) == exit)) {
+                return Err(reason(explain, format_args!("latch {} is reached from {} outside of the switch", latch, pred)));
+            }
+            let Some(latch_succs) = cfg.succs(latch) else { return Err(reason(explain, format_args!("latch {} has no successors", latch))) };
+            if (latch_succs.len() != 1 || ! latch_succs.contains(cond)) { return Err(reason(explain, format_args!("latch {} does not loop back to cond", latch))); }
+        }
+
+        Ok(())
+    }
+
+
+    /// Handles the special cases where the exit is reached from outside of the loop, or there is no exit.
+    /// An additional temporary node will be added if needed.
+    /// 
+    /// ```text
+    /// loop {
+    ///     switch (COND) {
+    ///         case X:
+    ///             break;
+    ///     }
+    /// }
+    /// TEMPORARY     <- This temporary node takes every edge out of the loop.
+    /// EXIT
+    /// ```
+    pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
+        // If no arm leaves the loop, nothing follows it.
+        if (self.exit == self.cond) {
+            let temporary = cfg.create_temporary_node();
+            cfg.add_edge(&self.cond, &temporary);
+            self.exit = (&temporary).into();
+            return;
+        }
+        // The arms which leave the loop either branch to exit from cond, or from their bodies.
+        let mut breaks_from : Vec<&CFGNode> = Vec::new();
+        for case in self.cases.iter().chain([ &self.default ]).filter(|case| case.breaks) {
+            let from = case.body.as_ref().unwrap_or(&self.cond);
+            if (! breaks_from.contains(&from)) { breaks_from.push(from); }
+        }
+        let breaks_from = breaks_from.into_iter().cloned().collect::<Vec<_>>();
+        // If exit has any other predecessors, insert a temporary node.
+        if (cfg.pred_count(&self.exit) != breaks_from.len()) {
+            let temporary = cfg.create_temporary_node();
+            for from in &breaks_from {
+                cfg.insert_node(&temporary, from, &self.exit);
+            }
+            self.exit = (&temporary).into();
+        }
+    }
+
+
+    /// Get all of the bodies in the primitive, including the default.
+    pub fn bodies(&self) -> Vec<&CFGNode> {
+        self.cases.iter().chain([ &self.default ]).filter_map(|case| case.body.as_ref()).collect()
+    }
+
+
+}
+
+
+impl fmt::Display for CFASwitchLoop {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFASwitchLoop {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, muted, reset, .. } = theme;
+        let arm_end = |f : &mut fmt::Formatter<'_>, case : &CFASwitchLoopCase| {
+            if let Some(body) = &case.body {
+                write!(f, "{block}{}{reset} ", body)?;
+            }
+            let jump = if (case.breaks) { "break" } else { "continue" };
+            write!(f, "{keyword}{}{reset}{muted};{reset} ", jump)
+        };
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}loop{reset} {punctuation}{{{reset} ")?;
+        write!(f, "{keyword}switch{reset} {punctuation}({reset} ")?;
+        write!(f, "{block}{}{reset}", self.cond)?;
+        write!(f, " {punctuation}) {{{reset} ")?;
+        for case in &self.cases {
+            write!(f, "{keyword}case{reset} ")?;
+            let mut first = true;
+            for value in &case.values {
+                if (first) { first = false; }
+                else { write!(f, "{muted},{reset} ")?; }
+                write!(f, "{}", value)?;
+            }
+            write!(f, "{muted}:{reset} ")?;
+            arm_end(f, case)?;
+        }
+        write!(f, "{keyword}default{reset}{muted}:{reset} ")?;
+        arm_end(f, &self.default)?;
+        write!(f, "{punctuation}}} }}{reset} ")?;
+        if let Some(latch) = &self.latch {
+            write!(f, "{keyword}continue{reset} {punctuation}{{{reset} ")?;
+            write!(f, "{block}{}{reset}", latch)?;
+            write!(f, " {punctuation}}}{reset} ")?;
+        }
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfa::tests::reduce;
+    use crate::cfg::tests::{
+        function,
+        node
+    };
+
+    #[test]
+    fn state_machine() -> () {
+        // `loop { match (state) { 0 => a, 1 => b, 2 => break, _ => c } }`, where every arm but one loops through `l`.
+        let function = function(&[
+            ("s", &[ "a", "b", "x", "c" ]),
+            ("a", &[ "l" ]),
+            ("b", &[ "l" ]),
+            ("c", &[ "l" ]),
+            ("l", &[ "s" ]),
+            ("x", &[])
+        ]);
+        let cfg  = ControlFlowGraph::new(&function);
+        let prim = CFASwitchLoop::find_first(&cfg).expect("no switch loop found");
+        assert!(prim.cond == node("s") && prim.latch == Some(node("l")) && prim.exit == node("x"));
+        assert!(prim.cases.len() == 3 && prim.cases.iter().filter(|case| case.breaks).count() == 1);
+        assert!(! prim.default.breaks && prim.default.body == Some(node("c")));
+        let prims = reduce(cfg);
+        assert!(prims.prims().iter().any(|prim| matches!(prim, CFAPrim::SwitchLoop(_))));
+    }
+
+    #[test]
+    fn state_machine_without_exit() -> () {
+        // `loop { match (state) { 0 => a, 1 => b, _ => c } }`, where every arm branches straight back to the switch.
+        let function = function(&[ ("s", &[ "a", "b", "c" ]), ("a", &[ "s" ]), ("b", &[ "s" ]), ("c", &[ "s" ]) ]);
+        let cfg  = ControlFlowGraph::new(&function);
+        let prim = CFASwitchLoop::find_first(&cfg).expect("no switch loop found");
+        assert!(prim.latch.is_none() && prim.exit == node("s"));
+        assert!(prim.cases.iter().chain([ &prim.default ]).all(|case| ! case.breaks));
+        reduce(cfg);
+    }
+
+}
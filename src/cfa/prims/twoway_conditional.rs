@@ -11,7 +11,9 @@ use super::*;
 /// ```
 pub struct CFATwowayConditional {
     pub cond    : CFGNode,
+    /// Reached when the condition is true.
     pub body_a  : CFGNode,
+    /// Reached when the condition is false.
     pub body_b  : CFGNode,
     pub exit    : CFGNode
 }
@@ -22,25 +24,40 @@ impl CFATwowayConditional {
 
     /// Find the first two-way conditional primitive in a `ControlFlowGraph`, or `None` if none could be found.
     pub fn find_first(cfg : &ControlFlowGraph) -> Option<Self> {
+        Self::find_first_explained(cfg, None)
+    }
+
+
+    /// Find the first two-way conditional primitive in a `ControlFlowGraph`, recording why each candidate before it
+    ///   was rejected.
+    pub(crate) fn find_first_explained(cfg : &ControlFlowGraph, mut rejected : Option<&mut Vec<RejectionReason>>) -> Option<Self> {
+        let explain    = rejected.is_some();
+        let mut reject = |cond : &CFGNode, reason| RejectionReason::record(&mut rejected, "if-else", cond, reason);
         for cond in cfg.nodes() {
 
             // Verify that cond has two successors (body_a and body_b).
             let Some(cond_succs) = cfg.succs(cond) else { continue };
             if (cond_succs.len() != 2) { continue; }
+
             let mut cond_succs = cond_succs.into_iter();
 
             let body_a = cond_succs.next().unwrap();
             let body_b = cond_succs.next().unwrap();
 
             // Verify that body_a has one successor (exit).
-            let Some(body_a_succs) = cfg.succs(body_a) else { continue };
-            if (body_a_succs.len() != 1) { continue; }
+            let Some(body_a_succs) = cfg.succs(body_a) else { reject(cond, reason(explain, format_args!("body {} has no successors", body_a))); continue };
+            if (body_a_succs.len() != 1) { reject(cond, reason(explain, format_args!("body {} has {} successors, expected 1", body_a, body_a_succs.len()))); continue; }
 
             let mut body_a_succs = body_a_succs.into_iter();
             let exit = body_a_succs.next().unwrap();
 
-            if (Self::is_valid(cfg, cond, body_a, body_b, exit)) {
-                return Some(Self { cond : cond.clone(), body_a : body_a.clone(), body_b : body_b.clone(), exit : exit.clone() });
+            match (Self::is_valid(cfg, cond, body_a, body_b, exit, explain)) {
+                Ok(()) => {
+                    // Keep body_a on the true edge.
+                    let (body_a, body_b) = if (cfg.is_false_edge(cond, body_a)) { (body_b, body_a) } else { (body_a, body_b) };
+                    return Some(Self { cond : cond.clone(), body_a : body_a.clone(), body_b : body_b.clone(), exit : exit.clone() });
+                },
+                Err(reason) => reject(cond, reason)
             }
 
         }
@@ -48,49 +65,43 @@ impl CFATwowayConditional {
     }
 
 
-    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, body_a : &CFGNode, body_b : &CFGNode, exit : &CFGNode) -> bool {
+    /// Checks that `body_a`, `body_b`, and `exit` form a two-way conditional with `cond`, or gets the reason they
+    ///   do not.
+    fn is_valid(cfg : &ControlFlowGraph, cond : &CFGNode, body_a : &CFGNode, body_b : &CFGNode, exit : &CFGNode, explain : bool) -> Result<(), String> {
 
         // Temporaries sanity check.
-        if (cfg.temps().contains(cond.to_succ())) { return false; }
-        if (cfg.temps().contains(exit.from_pred())) { return false; }
+        if (cfg.temps().contains(cond.to_succ())) { return Err(reason(explain, format_args!("cond ends in a temporary"))); }
+        check_temporary_exit(cfg, cond, exit, explain)?;
 
         // Dominator sanity check.
-        if (! cfg.dominates(cond, body_a)) { return false; }
-        if (! cfg.dominates(cond, body_b)) { return false; }
-        if (! cfg.dominates(cond, exit)) { return false; }
-
-        // Verify that cond is dominated by its predecessors.
-        if let Some(cond_preds) = cfg.preds(cond) {
-            for cond_pred in cond_preds {
-                if (! cfg.dominates(cond_pred, cond)) { return false; }
-            }
-        }
+        if (! cfg.dominates(cond, body_a)) { return Err(reason(explain, format_args!("cond does not dominate body {}", body_a))); }
+        if (! cfg.dominates(cond, body_b)) { return Err(reason(explain, format_args!("cond does not dominate body {}", body_b))); }
 
         // Verify that cond has two successors (body_a and body_b).
-        let Some(cond_succs) = cfg.succs(cond) else { return false };
-        if (cond_succs.len() != 2) { return false; }
-        if (! cond_succs.contains(body_a)) { return false; }
-        if (! cond_succs.contains(body_b)) { return false; }
+        let Some(cond_succs) = cfg.succs(cond) else { return Err(reason(explain, format_args!("cond has no successors"))) };
+        if (cond_succs.len() != 2) { return Err(reason(explain, format_args!("cond has {} successors, expected 2", cond_succs.len()))); }
+        if (! cond_succs.contains(body_a)) { return Err(reason(explain, format_args!("cond does not branch to body {}", body_a))); }
+        if (! cond_succs.contains(body_b)) { return Err(reason(explain, format_args!("cond does not branch to body {}", body_b))); }
 
         // Verify that body_a has one predecessor (cond).
-        let Some(body_a_preds) = cfg.preds(body_a) else { return false };
-        if (body_a_preds.len() != 1) { return false; }
+        let Some(body_a_preds) = cfg.preds(body_a) else { return Err(reason(explain, format_args!("body {} has no predecessors", body_a))) };
+        if (body_a_preds.len() != 1) { return Err(reason(explain, format_args!("body {} has {} predecessors, expected 1", body_a, body_a_preds.len()))); }
 
         // Verify that body_a has one successor (exit).
-        let Some(body_a_succs) = cfg.succs(body_a) else { return false };
-        if (body_a_succs.len() != 1) { return false; }
-        if (! body_a_succs.contains(exit)) { return false; }
+        let Some(body_a_succs) = cfg.succs(body_a) else { return Err(reason(explain, format_args!("body {} has no successors", body_a))) };
+        if (body_a_succs.len() != 1) { return Err(reason(explain, format_args!("body {} has {} successors, expected 1", body_a, body_a_succs.len()))); }
+        if (! body_a_succs.contains(exit)) { return Err(reason(explain, format_args!("body {} does not branch to exit {}", body_a, exit))); }
 
         // Verify that body_b has one predecessor (cond).
-        let Some(body_b_preds) = cfg.preds(body_b) else { return false };
-        if (body_b_preds.len() != 1) { return false; }
+        let Some(body_b_preds) = cfg.preds(body_b) else { return Err(reason(explain, format_args!("body {} has no predecessors", body_b))) };
+        if (body_b_preds.len() != 1) { return Err(reason(explain, format_args!("body {} has {} predecessors, expected 1", body_b, body_b_preds.len()))); }
 
         // Verify that body_b has one successor (exit).
-        let Some(body_b_succs) = cfg.succs(body_b) else { return false };
-        if (body_b_succs.len() != 1) { return false; }
-        if (! body_b_succs.contains(exit)) { return false; }
+        let Some(body_b_succs) = cfg.succs(body_b) else { return Err(reason(explain, format_args!("body {} has no successors", body_b))) };
+        if (body_b_succs.len() != 1) { return Err(reason(explain, format_args!("body {} has {} successors, expected 1", body_b, body_b_succs.len()))); }
+        if (! body_b_succs.contains(exit)) { return Err(reason(explain, format_args!("body {} does not branch to exit {}", body_b, exit))); }
 
-        true
+        Ok(())
     }
 
 
@@ -109,7 +120,7 @@ impl CFATwowayConditional {
     /// ```
     pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
         // If exit does not have two predecessors (body_a and body_b), insert a temporary node.
-        if (cfg.preds(&self.exit).map(|preds| preds.len()).unwrap_or(0) != 2) {
+        if (cfg.pred_count(&self.exit) != 2) {
             let temporary = cfg.create_temporary_node();
             cfg.insert_node(&temporary, &self.body_a, &self.exit);
             cfg.insert_node(&temporary, &self.body_b, &self.exit);
@@ -123,16 +134,23 @@ impl CFATwowayConditional {
 
 impl fmt::Display for CFATwowayConditional {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[95m\x1b[1mif\x1b[0m \x1b[37m\x1b[1m(\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.cond)?;
-        write!(f, " \x1b[37m\x1b[1m) {{\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.body_a)?;
-        write!(f, " \x1b[37m\x1b[1m}}\x1b[0m \x1b[95m\x1b[1melse\x1b[0m \x1b[37m\x1b[1m{{\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.body_b)?;
-        write!(f, " \x1b[37m\x1b[1m}}\x1b[0m ")?;
-        write!(f, "\x1b[2m->\x1b[0m ")?;
-        write!(f, "\x1b[36m{}\x1b[0m", self.exit)?;
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFATwowayConditional {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { keyword, punctuation, block, muted, reset, .. } = theme;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{keyword}if{reset} {punctuation}({reset} ")?;
+        write!(f, "{block}{}{reset}", self.cond)?;
+        write!(f, " {punctuation}) {{{reset} ")?;
+        write!(f, "{block}{}{reset}", self.body_a)?;
+        write!(f, " {punctuation}}}{reset} {keyword}else{reset} {punctuation}{{{reset} ")?;
+        write!(f, "{block}{}{reset}", self.body_b)?;
+        write!(f, " {punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
         Ok(())
     }
 }
@@ -0,0 +1,333 @@
+use super::*;
+
+use std::collections::HashSet;
+
+
+/// ```text
+/// PSEUDOCODE                  GRAPH
+/// 
+/// ENTRY                         A
+/// goto B, C;                   / \
+/// B:                          B <-> C
+///     goto C, EXIT;            \   /
+/// C:                            EXIT
+///     goto B, EXIT;
+/// EXIT
+/// ```
+/// 
+/// A region which none of the other primitives can reduce, such as a loop entered through more than one of its
+///   nodes, kept as the blocks in it and the edges between them. Only found by `CFAPrim::find_all_partial`, once
+///   nothing else can be found.
+/// 
+/// Entry dominates the rest of the region, and is structured as usual, as is the exit. Every edge out of the region
+///   goes to the exit. If the region never leaves, or only leaves back to entry, the exit is a temporary node with
+///   no successors, added when the primitive is merged, as with a `CFAInfiniteLoop`.
+pub struct CFAUnstructured {
+    pub entry  : CFGNode,
+    /// The rest of the nodes of the region.
+    pub body   : Vec<CFGNode>,
+    /// Every block merged into the body, in the order of the original function.
+    pub blocks : Vec<Name>,
+    /// Every edge of the original function into or out of `blocks`.
+    pub edges  : Vec<(Name, Name)>,
+    pub exit   : CFGNode
+}
+
+
+impl CFAUnstructured {
+
+
+    /// Find the smallest region around a part of a `ControlFlowGraph` which no other primitive can reduce, or the
+    ///   whole graph if there is none.
+    /// 
+    /// Loops entered through more than one of their nodes are tried first, then any other loop, then any other
+    ///   node with more than one successor. `original` is the graph before anything was merged, and `prims` is
+    ///   every primitive merged since, which are needed to find the blocks of the region.
+    pub(crate) fn find_first(cfg : &ControlFlowGraph, original : &ControlFlowGraph, prims : &[CFAPrim]) -> Self {
+        let is_reachable = |node : &CFGNode| cfg.dominates(cfg.entry(), node);
+        let irreducible  = cfg.irreducible_regions();
+        let loops        = cfg.sccs().into_iter().filter(|component| component.len() > 1).collect::<Vec<_>>();
+        let branches     = cfg.nodes().iter()
+            .filter(|&node| cfg.succs(node).is_some_and(|succs| succs.len() > 1))
+            .map(|node| vec![ node.clone() ])
+            .collect::<Vec<_>>();
+        for seeds in [ irreducible, loops, branches ] {
+            let region = seeds.iter()
+                .filter(|seed| seed.iter().all(is_reachable))
+                .filter_map(|seed| Self::around(cfg, seed))
+                .min_by_key(|(_, body, _)| body.len());
+            if let Some((entry, body, exit)) = region {
+                return Self::new(original, prims, entry, body, exit);
+            }
+        }
+        Self::whole(cfg, original, prims)
+    }
+
+
+    /// Gets the whole of a `ControlFlowGraph` as a single region, for when nothing more may be merged.
+    pub(crate) fn whole(cfg : &ControlFlowGraph, original : &ControlFlowGraph, prims : &[CFAPrim]) -> Self {
+        let entry = cfg.entry().clone();
+        let body  = cfg.nodes().iter().filter(|&node| node != &entry).cloned().collect();
+        Self::new(original, prims, entry.clone(), body, entry)
+    }
+
+
+    /// Finds the smallest region around `seed` which is only entered through its entry and only left to its exit,
+    ///   as its entry, body and exit, or `None` if there is none.
+    /// 
+    /// The exit is the entry if the region only leaves back to it, or never leaves.
+    fn around(cfg : &ControlFlowGraph, seed : &[CFGNode]) -> Option<(CFGNode, Vec<CFGNode>, CFGNode)> {
+        // The region is entered through the closest node which dominates every way into the seed.
+        let entries = seed.iter()
+            .filter(|&node| node == cfg.entry() || cfg.preds(node).is_some_and(|preds| preds.iter().any(|pred| ! seed.contains(pred))))
+            .collect::<Vec<_>>();
+        let first     = entries.first()?;
+        let mut entry = cfg.dominators_of(*first).into_iter()
+            .filter(|dominator| entries.iter().all(|&node| cfg.dominates(dominator, node)))
+            .last()?;
+        loop {
+            if let Some(region) = Self::region(cfg, &entry, seed) { return Some(region); }
+            entry = cfg.immediate_dominator(&entry)?;
+        }
+    }
+
+
+    /// Grows a region entered through `entry` from `seed` until it only leaves to a single node, or `None` if it
+    ///   can not be without going above `entry`.
+    fn region(cfg : &ControlFlowGraph, entry : &CFGNode, seed : &[CFGNode]) -> Option<(CFGNode, Vec<CFGNode>, CFGNode)> {
+        // Nodes which can not be reached at all only lead into the region, so they are taken in with it.
+        let below     = |node : &CFGNode| cfg.dominates(entry, node) || ! cfg.dominates(cfg.entry(), node);
+        let mut nodes = HashSet::from([ entry.clone() ]);
+        let mut stack = seed.to_vec();
+        loop {
+            // Everything leading into the region from below entry is part of it, so that it is only entered
+            //   through entry.
+            while let Some(node) = stack.pop() {
+                if (! nodes.insert(node.clone())) { continue; }
+                for pred in cfg.preds(&node).into_iter().flatten() {
+                    if (! nodes.contains(pred) && below(pred)) { stack.push(pred.clone()); }
+                }
+            }
+
+            let mut exits : Vec<&CFGNode> = Vec::new();
+            for node in &nodes {
+                for succ in cfg.succs(node).into_iter().flatten() {
+                    if ((! nodes.contains(succ) || succ == entry) && ! exits.contains(&succ)) { exits.push(succ); }
+                }
+            }
+            if (exits.len() <= 1) {
+                let exit = exits.first().copied().unwrap_or(entry).clone();
+                let body = cfg.nodes().iter().filter(|&node| node != entry && nodes.contains(node)).cloned().collect();
+                return Some((entry.clone(), body, exit));
+            }
+
+            // Take in the exits below entry and try again, starting with those which never leave.
+            let inside = exits.into_iter().filter(|&exit| exit != entry && below(exit)).collect::<Vec<_>>();
+            let sinks  = inside.iter().filter(|&&exit| cfg.succs(exit).map(|succs| succs.is_empty()).unwrap_or(true)).copied().collect::<Vec<_>>();
+            let taken  = if (sinks.is_empty()) { inside } else { sinks };
+            if (taken.is_empty()) { return None; }
+            stack.extend(taken.into_iter().cloned());
+        }
+    }
+
+
+    /// Creates the primitive for a region, finding its blocks and edges in `original` through `prims`.
+    fn new(original : &ControlFlowGraph, prims : &[CFAPrim], entry : CFGNode, body : Vec<CFGNode>, exit : CFGNode) -> Self {
+        let mut merged = Vec::new();
+        for node in &body {
+            Self::blocks_of(node, prims, &mut merged);
+        }
+        let merged = merged.into_iter().collect::<HashSet<_>>();
+        let mut from_entry = Vec::new();
+        Self::blocks_of(&entry, prims, &mut from_entry);
+
+        let blocks = original.nodes().iter()
+            .map(|node| node.from_pred())
+            .filter(|&block| merged.contains(block))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut edges = Vec::new();
+        for block in from_entry.iter().chain(&blocks) {
+            for succ in original.succs(block).into_iter().flatten() {
+                if (merged.contains(block) || merged.contains(succ.from_pred())) {
+                    edges.push((block.clone(), succ.from_pred().clone()));
+                }
+            }
+        }
+        Self { entry, body, blocks, edges, exit }
+    }
+
+
+    /// Appends every block merged into `node` by `prims` to `out`, leaving out temporary nodes.
+    fn blocks_of(node : &CFGNode, prims : &[CFAPrim], out : &mut Vec<Name>) -> () {
+        let merged_from = prims.iter().find(|prim| prim.entry().from_pred() == node.from_pred() && prim.exit().to_succ() == node.to_succ());
+        match (merged_from) {
+            Some(prim) => for node in prim.nodes() {
+                Self::blocks_of(node, prims, out);
+            },
+            None => if (! ControlFlowGraph::is_temporary_name(node.from_pred())) {
+                out.push(node.from_pred().clone());
+            }
+        }
+    }
+
+
+    /// Handles the special cases where the exit is reached from outside of the region, the region only leaves back
+    ///   to its entry, or it never leaves. An additional temporary node will be added if needed.
+    /// 
+    /// The edges back to entry are written as `goto`s inside of the region, so a region which only leaves back to
+    ///   its entry is handled the same as one which never leaves.
+    /// 
+    /// ```text
+    /// ENTRY
+    /// goto B, C;
+    /// B:
+    ///     goto C, ENTRY;
+    /// C:
+    ///     goto B, ENTRY;
+    /// TEMPORARY     <- This temporary node follows the region, but is never reached.
+    /// ```
+    pub(crate) fn insert_needed_node(&mut self, cfg : &mut ControlFlowGraph) -> () {
+        let leaves_from = [ &self.entry ].into_iter().chain(&self.body)
+            .filter(|&node| cfg.succs(node).is_some_and(|succs| succs.contains(&self.exit)))
+            .cloned()
+            .collect::<Vec<_>>();
+        // If the region never leaves, nothing follows it.
+        if (self.exit == self.entry || leaves_from.is_empty()) {
+            let temporary = cfg.create_temporary_node();
+            cfg.add_edge(&self.entry, &temporary);
+            self.exit = (&temporary).into();
+            return;
+        }
+        // If exit has any other predecessors, insert a temporary node.
+        if (cfg.pred_count(&self.exit) != leaves_from.len()) {
+            let temporary = cfg.create_temporary_node();
+            for from in &leaves_from {
+                cfg.insert_node(&temporary, from, &self.exit);
+            }
+            self.exit = (&temporary).into();
+        }
+    }
+
+
+}
+
+
+impl fmt::Display for CFAUnstructured {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for CFAUnstructured {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { punctuation, block, muted, error, reset, .. } = theme;
+        write!(f, "{block}{}{reset} ", self.entry)?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{error}unstructured{reset} {punctuation}{{{reset} ")?;
+        for body in &self.body {
+            write!(f, "{block}{}{reset} ", body)?;
+        }
+        write!(f, "{punctuation}}}{reset} ")?;
+        write!(f, "{muted}->{reset} ")?;
+        write!(f, "{block}{}{reset}", self.exit)?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::{
+        CFRGroups,
+        CFRGroup
+    };
+    use crate::cfg::tests::{
+        asm_goto,
+        function,
+        graph
+    };
+
+    /// Recovers a function built from its blocks, keeping what can not be reduced as it is.
+    fn recover(blocks : &[(&str, &[&str])]) -> CFRGroups {
+        let prims = CFAPrim::find_all_partial(ControlFlowGraph::new(&function(blocks)), usize::MAX);
+        assert!(prims.lost_edges().is_empty(), "lost edges while reducing");
+        CFRGroups::new_partial(&prims).0
+    }
+
+    fn names(names : &[&str]) -> Vec<Name> {
+        names.iter().map(|&name| Name::from(name)).collect()
+    }
+
+    #[test]
+    fn irreducible_loop_only() -> () {
+        // `if (e) { p } a`, then a loop entered through both `b` and `c`, then `if (x) { y } r`.
+        let groups = recover(&[
+            ("e", &[ "p", "a" ]),
+            ("p", &[ "a" ]),
+            ("a", &[ "b", "c" ]),
+            ("b", &[ "c", "x" ]),
+            ("c", &[ "b", "x" ]),
+            ("x", &[ "y", "r" ]),
+            ("y", &[ "r" ]),
+            ("r", &[])
+        ]);
+        let [ CFRGroup::OnewayConditional { .. }, CFRGroup::Block(a), CFRGroup::Unstructured { blocks, edges }, CFRGroup::OnewayConditional { .. }, CFRGroup::Block(r) ] = groups.groups.as_slice()
+            else { panic!("expected the loop alone to be unstructured, found\n{}", groups) };
+        assert!(a == &Name::from("a") && r == &Name::from("r"));
+        assert!(blocks == &names(&[ "b", "c" ]));
+        for (from, to) in [ ("a", "b"), ("a", "c"), ("b", "c"), ("b", "x"), ("c", "b"), ("c", "x") ] {
+            assert!(edges.contains(&(Name::from(from), Name::from(to))), "missing edge {} -> {}", from, to);
+        }
+        assert!(edges.len() == 6);
+    }
+
+    #[test]
+    fn irreducible_loop_in_loop() -> () {
+        // `loop { h  <b and c entered from h>  l }`, where the irreducible loop leaves back to the outer loop.
+        let groups = recover(&[
+            ("s", &[ "h" ]),
+            ("h", &[ "b", "c" ]),
+            ("b", &[ "c", "l" ]),
+            ("c", &[ "b", "l" ]),
+            ("l", &[ "h" ])
+        ]);
+        let [ CFRGroup::Block(_), CFRGroup::InfiniteLoop { body } ] = groups.groups.as_slice()
+            else { panic!("expected the outer loop to be structured, found\n{}", groups) };
+        assert!(body.groups.iter().any(|group| matches!(group, CFRGroup::Unstructured { blocks, .. } if blocks == &names(&[ "b", "c" ]))),
+            "expected the inner loop to be unstructured, found\n{}", groups);
+    }
+
+    #[test]
+    fn strict_recovery_fails() -> () {
+        let function = function(&[ ("a", &[ "b", "c" ]), ("b", &[ "c" ]), ("c", &[ "b" ]) ]);
+        let prims    = CFAPrim::find_all_partial(ControlFlowGraph::new(&function), usize::MAX);
+        assert!(prims.prims().iter().any(|prim| matches!(prim, CFAPrim::Unstructured(_))));
+        assert!(CFRGroups::new(&prims).is_none());
+        assert!(! CFRGroups::new_partial(&prims).0.is_fully_structured());
+    }
+
+    #[test]
+    fn out_of_steps() -> () {
+        // `if (a) { b } c`, stopped before anything is merged.
+        let function = function(&[ ("a", &[ "b", "c" ]), ("b", &[ "c" ]), ("c", &[]) ]);
+        let prims    = CFAPrim::find_all_partial(ControlFlowGraph::new(&function), 0);
+        let [ CFAPrim::Unstructured(prim) ] = prims.prims().as_slice() else { panic!("expected a single unstructured region") };
+        assert!(prim.blocks == names(&[ "b", "c" ]));
+    }
+
+    #[test]
+    fn asm_goto_placeholder() -> () {
+        // `a` jumps to `b` or `c` from inline assembly, which both continue to `d`.
+        let mut cfg = graph(&[ ("a", "b"), ("a", "c"), ("b", "d"), ("c", "d") ]);
+        asm_goto(&mut cfg, "a");
+        let prims  = CFAPrim::find_all_partial(cfg, usize::MAX);
+        let groups = CFRGroups::new_partial(&prims).0;
+        let [ CFRGroup::AsmGoto { name, labels }, CFRGroup::Unstructured { blocks, .. }, CFRGroup::Block(d) ] = groups.groups.as_slice()
+            else { panic!("expected the asm goto to be left unstructured, found\n{}", groups) };
+        assert!(name == &Name::from("a") && labels == &names(&[ "b", "c" ]));
+        assert!(blocks == &names(&[ "b", "c" ]) && d == &Name::from("d"));
+    }
+
+}
@@ -0,0 +1,39 @@
+use super::*;
+
+
+/// Why a candidate for a primitive was not merged, as recorded by `CFAPrim::explain_first`.
+#[derive(Clone)]
+pub struct RejectionReason {
+    /// The kind of primitive which was tried, such as `if` or `while`.
+    pub prim   : &'static str,
+    /// The node the candidate was found from, such as its condition.
+    pub node   : CFGNode,
+    /// The check which failed, such as `body %bb3 has 2 predecessors, expected 1`.
+    pub reason : String
+}
+
+
+impl RejectionReason {
+
+    /// Records why a candidate was rejected, if `rejected` is being kept.
+    pub(crate) fn record(rejected : &mut Option<&mut Vec<RejectionReason>>, prim : &'static str, node : &CFGNode, reason : String) -> () {
+        if let Some(rejected) = rejected {
+            rejected.push(Self { prim, node : node.clone(), reason });
+        }
+    }
+
+}
+
+
+/// Formats the check which failed, or gets an empty string if `explain` is `false`, so that finding primitives
+///   normally does not build reasons which are never read.
+pub(crate) fn reason(explain : bool, args : fmt::Arguments<'_>) -> String {
+    if (explain) { args.to_string() } else { String::new() }
+}
+
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.prim, self.node, self.reason)
+    }
+}
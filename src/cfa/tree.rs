@@ -0,0 +1,57 @@
+use super::*;
+
+
+impl CFAPrims {
+
+    /// Formats the primitives as a tree, with the primitives merged into the nodes of each primitive nested below
+    ///   it.
+    /// 
+    /// A node contains a primitive if it has the same range of nodes as that primitive, the same way as the
+    ///   primitives are recovered by `CFRGroups::new`. Primitives which are not in any other primitive are written
+    ///   at the top level, which is only the last one found if the graph was fully reduced.
+    /// 
+    /// ```text
+    /// -> %start -> (%bb1...%bb13)
+    ///   -> while ( %bb1 ) { (%bb2...%@DECOMP_TEMPORARY_0) } -> (%bb7...%bb13)
+    ///     -> if ( (%bb2...%bb4) ) { %bb5 } else { %bb6 } -> %@DECOMP_TEMPORARY_0
+    ///       -> if ( %bb2 ) { %bb3 } -> %bb4
+    ///     -> if ( ! %bb7 ) { (%bb8...%bb10) } -> %bb13
+    ///       -> loop { if (! %bb8 ) { break; } -> %bb10
+    /// ```
+    /// 
+    /// `Display` uses this instead of listing the primitives in the order they were found when formatted with `{:#}`.
+    pub fn fmt_tree(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree_themed(f, &Theme::default())
+    }
+
+    /// Formats the primitives as a tree, using the colours of `theme`. See `CFAPrims::fmt_tree`.
+    pub(crate) fn fmt_tree_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let children = self.prims.iter().map(|prim| prim.nodes().into_iter().filter_map(|node| self.merged_from(node)).collect::<Vec<_>>()).collect::<Vec<_>>();
+        let mut is_root = vec![ true; self.prims.len() ];
+        for &child in children.iter().flatten() {
+            is_root[child] = false;
+        }
+        let mut first = true;
+        for root in (0..self.prims.len()).filter(|&i| is_root[i]) {
+            self.fmt_subtree(f, theme, &children, root, 0, &mut first)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_subtree(&self, f : &mut fmt::Formatter<'_>, theme : &Theme, children : &Vec<Vec<usize>>, index : usize, depth : usize, first : &mut bool) -> fmt::Result {
+        if (*first) { *first = false; }
+        else { writeln!(f)?; }
+        write!(f, "{}", "  ".repeat(depth))?;
+        self.prims[index].fmt_themed(f, theme)?;
+        for &child in &children[index] {
+            self.fmt_subtree(f, theme, children, child, depth + 1, first)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the index of the primitive which `node` was merged from, or `None` if it is a block.
+    fn merged_from(&self, node : &CFGNode) -> Option<usize> {
+        self.prims.iter().position(|prim| prim.entry().from_pred() == node.from_pred() && prim.exit().to_succ() == node.to_succ())
+    }
+
+}
@@ -0,0 +1,148 @@
+use super::*;
+
+use crate::util::json;
+
+
+impl CFAPrims {
+
+
+    /// Formats the primitives like the JSON output of the original [decomp project](https://github.com/decomp/decomp),
+    ///   so the two can be compared on the same input.
+    /// 
+    /// Each primitive is written as an object with the fields `prim`, `node`, `nodes`, `entry`, and `exit`. Block
+    ///   names are written without their `%` prefix, and merged nodes are written as `entry...exit`. The original
+    ///   project names merged nodes differently, so `node` is not expected to match exactly.
+    /// 
+    /// Infinite loop, switch, switch loop, short-circuit, loop break, and unstructured primitives are not part of
+    ///   the original project, and are written with the `prim` names `infinite_loop`, `switch`, `switch_loop`,
+    ///   `short_circuit`, `loop_break`, and `unstructured`.
+    pub fn to_upstream_format(&self) -> String {
+        let mut out = String::from("[");
+        let mut first = true;
+        for prim in &self.prims {
+            if (first) { first = false; }
+            else { out.push(','); }
+            out.push_str("\n\t{\n\t\t\"prim\": ");
+            json::write_string(&mut out, prim.upstream_name());
+            out.push_str(",\n\t\t\"node\": ");
+            json::write_string(&mut out, &upstream_node_name(&CFGNode::between(prim.entry(), prim.exit())));
+            out.push_str(",\n\t\t\"nodes\": {");
+            // Keys are sorted, to match the original project.
+            let mut nodes = prim.upstream_nodes();
+            nodes.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut first_node = true;
+            for (key, node) in nodes {
+                if (first_node) { first_node = false; }
+                else { out.push(','); }
+                out.push_str("\n\t\t\t");
+                json::write_string(&mut out, &key);
+                out.push_str(": ");
+                json::write_string(&mut out, &upstream_node_name(node));
+            }
+            out.push_str("\n\t\t},\n\t\t\"entry\": ");
+            json::write_string(&mut out, &upstream_node_name(prim.entry()));
+            out.push_str(",\n\t\t\"exit\": ");
+            json::write_string(&mut out, &upstream_node_name(prim.exit()));
+            out.push_str("\n\t}");
+        }
+        out.push_str("\n]");
+        out
+    }
+
+
+}
+
+
+impl CFAPrim {
+
+
+    /// The name of the primitive in the original project.
+    fn upstream_name(&self) -> &'static str {
+        match (self) {
+            Self::PreconditionLoop        (_) => "pre_loop",
+            Self::PostconditionLoop       (_) => "post_loop",
+            Self::InfiniteLoop            (_) => "infinite_loop",
+            Self::OnewayConditional       (_) => "if",
+            Self::OnewayReturnConditional (_) => "if_return",
+            Self::TwowayConditional       (_) => "if_else",
+            Self::Switch                  (_) => "switch",
+            Self::SwitchLoop              (_) => "switch_loop",
+            Self::StatementSequence       (_) => "seq",
+            Self::ShortCircuit            (_) => "short_circuit",
+            Self::LoopBreak               (_) => "loop_break",
+            Self::Unstructured            (_) => "unstructured"
+        }
+    }
+
+
+    /// The nodes of the primitive, named as in the original project.
+    fn upstream_nodes(&self) -> Vec<(String, &CFGNode)> {
+        match (self) {
+            Self::PreconditionLoop        (CFAPreconditionLoop        { cond, body, exit, .. })     => vec![ ("cond".into(), cond), ("body".into(), body), ("exit".into(), exit) ],
+            Self::PostconditionLoop       (CFAPostconditionLoop       { cond, exit })               => vec![ ("cond".into(), cond), ("exit".into(), exit) ],
+            Self::InfiniteLoop            (CFAInfiniteLoop            { body, exit })               => vec![ ("body".into(), body), ("exit".into(), exit) ],
+            Self::OnewayConditional       (CFAOnewayConditional       { cond, body, exit, .. })     => vec![ ("cond".into(), cond), ("body".into(), body), ("exit".into(), exit) ],
+            Self::OnewayReturnConditional (CFAOnewayReturnConditional { cond, body, exit, .. })     => vec![ ("cond".into(), cond), ("body".into(), body), ("exit".into(), exit) ],
+            Self::TwowayConditional       (CFATwowayConditional       { cond, body_a, body_b, exit }) => vec![ ("cond".into(), cond), ("body_true".into(), body_a), ("body_false".into(), body_b), ("exit".into(), exit) ],
+            Self::Switch                  (CFASwitch                  { cond, cases, default, exit }) => {
+                let mut nodes = vec![ ("cond".into(), cond), ("exit".into(), exit) ];
+                for (i, case) in cases.iter().enumerate() {
+                    if let Some(body) = &case.body {
+                        nodes.push((format!("case_{}", i), body));
+                    }
+                }
+                if let Some(default) = default {
+                    nodes.push(("default".into(), default));
+                }
+                nodes
+            },
+            Self::SwitchLoop              (CFASwitchLoop              { cond, cases, default, latch, exit }) => {
+                let mut nodes = vec![ ("cond".into(), cond), ("exit".into(), exit) ];
+                for (i, case) in cases.iter().enumerate() {
+                    if let Some(body) = &case.body {
+                        nodes.push((format!("case_{}", i), body));
+                    }
+                }
+                nodes.extend(default.body.iter().map(|body| ("default".into(), body)));
+                nodes.extend(latch.iter().map(|latch| ("latch".into(), latch)));
+                nodes
+            },
+            Self::StatementSequence       (CFAStatementSequence       { entry, middle, exit })      => {
+                let mut nodes = vec![ ("entry".into(), entry), ("exit".into(), exit) ];
+                for (i, node) in middle.iter().enumerate() {
+                    nodes.push((format!("middle_{}", i), node));
+                }
+                nodes
+            },
+            Self::ShortCircuit            (CFAShortCircuit            { first, second, .. })        => vec![ ("first".into(), first), ("second".into(), second) ],
+            Self::LoopBreak               (CFALoopBreak               { cond, body, exit, .. })     => {
+                let mut nodes = vec![ ("cond".into(), cond), ("exit".into(), exit) ];
+                nodes.extend(body.iter().map(|body| ("body".into(), body)));
+                nodes
+            },
+            Self::Unstructured            (CFAUnstructured            { entry, body, exit, .. })    => {
+                let mut nodes = vec![ ("entry".into(), entry), ("exit".into(), exit) ];
+                for (i, node) in body.iter().enumerate() {
+                    nodes.push((format!("body_{}", i), node));
+                }
+                nodes
+            }
+        }
+    }
+
+
+}
+
+
+/// Formats a node without the `%` prefix used by `Name`.
+fn upstream_node_name(node : &CFGNode) -> String {
+    let name = |name : &Name| match (name) {
+        Name::Name(name)   => name.to_string(),
+        Name::Number(name) => name.to_string()
+    };
+    if (node.from_pred() == node.to_succ()) {
+        name(node.from_pred())
+    } else {
+        format!("{}...{}", name(node.from_pred()), name(node.to_succ()))
+    }
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+
+/// How control leaves a block, as recorded from its LLVM terminator.
+/// 
+/// Only terminators whose successors need to be told apart during analysis are recorded. More kinds of
+///   terminator may be recorded in future versions, so matches outside of this crate need a wildcard arm.
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum CFGBranch {
+
+    /// A conditional `br` terminator.
+    Cond {
+        /// The block branched to when the condition is true.
+        true_dest  : Name,
+        /// The block branched to when the condition is false.
+        false_dest : Name
+    },
+
+    /// A `switch` terminator.
+    Switch {
+        /// Each case value, along with the block it branches to.
+        cases   : Vec<(CFGCaseValue, Name)>,
+        /// The block branched to when no case matches.
+        default : Name
+    },
+
+    /// A `callbr` terminator, as used by `asm goto`.
+    /// 
+    /// The destination is chosen by inline assembly rather than a condition, so `CFAPrim::find_first` never
+    ///   structures a block with more than one of them. Such a region is left for `CFRGroups::new_partial` to
+    ///   recover as a `CFRGroup::Unstructured`, after a `CFRGroup::AsmGoto` in place of the block.
+    /// 
+    /// llvm-ir does not read the blocks which the assembly can jump to, so only the one it falls through to is
+    ///   recorded. Any other successor of the block is taken to be one of them.
+    CallBr {
+        /// The block continued to when the assembly falls through.
+        default : Name
+    }
+
+}
+
+
+impl CFGBranch {
+
+    /// Gets a copy of the branch with every destination renamed by `rename`.
+    pub(crate) fn renamed<F : Fn(&Name) -> Name>(&self, rename : F) -> Self {
+        match (self) {
+            Self::Cond { true_dest, false_dest } => Self::Cond {
+                true_dest  : rename(true_dest),
+                false_dest : rename(false_dest)
+            },
+            Self::Switch { cases, default } => Self::Switch {
+                cases   : cases.iter().map(|(value, dest)| (*value, rename(dest))).collect(),
+                default : rename(default)
+            },
+            Self::CallBr { default } => Self::CallBr {
+                default : rename(default)
+            }
+        }
+    }
+
+}
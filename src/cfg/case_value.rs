@@ -0,0 +1,60 @@
+use super::*;
+
+
+/// An integer `switch` case value.
+/// 
+/// LLVM integers are bit patterns with no inherent sign, so the value can be read either way with `as_signed` and
+///   `as_unsigned`. `Display` uses the signed reading, except for single bit integers.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CFGCaseValue {
+    /// The bit pattern of the value, in the lowest `bits` bits.
+    value : u64,
+    /// The width of the integer type.
+    bits  : u32
+}
+
+
+impl CFGCaseValue {
+
+    /// Create a case value from a bit pattern of the given width. Bits above `bits` are ignored.
+    pub fn new(value : u64, bits : u32) -> Self { Self { value, bits } }
+
+    /// Create a case value from an integer constant, or `None` if `constant` is not an integer.
+    pub fn from_constant(constant : &Constant) -> Option<Self> {
+        match (constant) {
+            Constant::Int { bits, value } => Some(Self::new(*value, *bits)),
+            _ => None
+        }
+    }
+
+    /// Get the width of the integer type.
+    pub fn bits(&self) -> u32 { self.bits }
+
+    /// Read the value as an unsigned integer.
+    pub fn as_unsigned(&self) -> u64 {
+        if (self.bits >= 64) { self.value }
+        else { self.value & ((1 << self.bits) - 1) }
+    }
+
+    /// Read the value as a two's complement signed integer.
+    pub fn as_signed(&self) -> i64 {
+        if (self.bits == 0) { 0 }
+        else if (self.bits >= 64) { self.value as i64 }
+        else {
+            let shift = 64 - self.bits;
+            ((self.value << shift) as i64) >> shift
+        }
+    }
+
+}
+
+
+impl fmt::Display for CFGCaseValue {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        if (self.bits == 1) {
+            write!(f, "{}", self.as_unsigned())
+        } else {
+            write!(f, "{}", self.as_signed())
+        }
+    }
+}
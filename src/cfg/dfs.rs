@@ -0,0 +1,160 @@
+use super::*;
+
+
+/// A step of a depth first search.
+enum DfsStep<'l> {
+    /// The node was reached for the first time.
+    Enter(&'l CFGNode),
+    /// Every node reachable from the node has been visited.
+    Leave(&'l CFGNode)
+}
+
+
+/// A depth first search over the nodes of a `ControlFlowGraph`, which every traversal in this module is built on.
+struct Dfs<'l> {
+    cfg        : &'l ControlFlowGraph,
+    /// Nodes being visited, each with the index of the next successor to visit, or `None` if it has not been
+    ///   entered yet.
+    stack      : Vec<(&'l CFGNode, Option<usize>)>,
+    visited    : HashSet<&'l CFGNode>,
+    /// Edges which are never followed.
+    skip_edges : &'l [(CFGNode, CFGNode)]
+}
+
+impl<'l> Dfs<'l> {
+
+    fn new(cfg : &'l ControlFlowGraph, skip_edges : &'l [(CFGNode, CFGNode)]) -> Self {
+        Self { cfg, stack : Vec::new(), visited : HashSet::new(), skip_edges }
+    }
+
+    /// Carries on the search from `start`, unless it was already visited.
+    fn visit(&mut self, start : &'l CFGNode) -> () {
+        if (self.visited.insert(start)) {
+            self.stack.push((start, None));
+        }
+    }
+
+    /// Takes the next step of the search, or `None` if every node reachable from the starts was left.
+    fn step(&mut self) -> Option<DfsStep<'l>> {
+        while let Some((node, next)) = self.stack.pop() {
+            let Some(next) = next else {
+                self.stack.push((node, Some(0)));
+                return Some(DfsStep::Enter(node));
+            };
+            let succ = self.cfg.succs.get(node).and_then(|succs| succs.get(next));
+            let Some(succ) = succ else { return Some(DfsStep::Leave(node)) };
+            self.stack.push((node, Some(next + 1)));
+            let is_skipped = self.skip_edges.iter().any(|(from, to)| from == node && to == succ);
+            if (! is_skipped && self.visited.insert(succ)) {
+                self.stack.push((succ, None));
+            }
+        }
+        None
+    }
+
+}
+
+
+/// An iterator over the nodes of a `ControlFlowGraph` in depth first preorder.
+/// 
+/// Created by `ControlFlowGraph::dfs_preorder`.
+pub struct DfsPreorder<'l> {
+    dfs : Dfs<'l>
+}
+
+impl<'l> Iterator for DfsPreorder<'l> {
+    type Item = &'l CFGNode;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let DfsStep::Enter(node) = self.dfs.step()? { return Some(node); }
+        }
+    }
+}
+
+
+/// An iterator over the nodes of a `ControlFlowGraph` in depth first postorder.
+/// 
+/// Created by `ControlFlowGraph::dfs_postorder`.
+pub struct DfsPostorder<'l> {
+    dfs : Dfs<'l>
+}
+
+impl<'l> Iterator for DfsPostorder<'l> {
+    type Item = &'l CFGNode;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let DfsStep::Leave(node) = self.dfs.step()? { return Some(node); }
+        }
+    }
+}
+
+
+impl ControlFlowGraph {
+
+    /// Get an iterator over every node reachable from `start` in depth first preorder. Each node is only visited
+    ///   once, even if the graph has cycles.
+    /// 
+    /// The iterator is empty if `start` is not in the graph.
+    pub fn dfs_preorder<N : Into<CFGNode>>(&self, start : N) -> DfsPreorder<'_> {
+        let mut dfs = Dfs::new(self, &[]);
+        if let Some(start) = self.find_node(start) { dfs.visit(start); }
+        DfsPreorder { dfs }
+    }
+
+    /// Get an iterator over every node reachable from `start` in depth first postorder. Each node is only visited
+    ///   once, even if the graph has cycles.
+    /// 
+    /// The iterator is empty if `start` is not in the graph.
+    pub fn dfs_postorder<N : Into<CFGNode>>(&self, start : N) -> DfsPostorder<'_> {
+        let mut dfs = Dfs::new(self, &[]);
+        if let Some(start) = self.find_node(start) { dfs.visit(start); }
+        DfsPostorder { dfs }
+    }
+
+    /// Gets every node reachable from each of `starts` in turn in depth first postorder, without following any of
+    ///   `skip_edges`. Each node is only visited once, by the first start which reaches it.
+    /// 
+    /// The postorder from each start is returned separately, in the order of `starts`.
+    pub(crate) fn postorders<'l>(&'l self, starts : impl IntoIterator<Item = &'l CFGNode>, skip_edges : &'l [(CFGNode, CFGNode)]) -> Vec<Vec<CFGNode>> {
+        let mut dfs = Dfs::new(self, skip_edges);
+        starts.into_iter()
+            .map(|start| {
+                dfs.visit(start);
+                std::iter::from_fn(|| dfs.step())
+                    .filter_map(|step| match (step) {
+                        DfsStep::Leave(node) => Some(node.clone()),
+                        DfsStep::Enter(_)    => None
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Gets the node in the graph which is equal to `node`.
+    fn find_node<N : Into<CFGNode>>(&self, node : N) -> Option<&CFGNode> {
+        let node = node.into();
+        self.succs.get_key_value(&node).or_else(|| self.preds.get_key_value(&node)).map(|(node, _)| node)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::tests::{
+        graph,
+        node
+    };
+
+    #[test]
+    fn orders_of_diamond_then_loop() -> () {
+        // `a -> b | c -> d <-> e -> f`
+        let cfg = graph(&[ ("a", "b"), ("a", "c"), ("b", "d"), ("c", "d"), ("d", "e"), ("e", "d"), ("e", "f") ]);
+        let preorder  = cfg.dfs_preorder(node("a")).cloned().collect::<Vec<_>>();
+        let postorder = cfg.dfs_postorder(node("a")).cloned().collect::<Vec<_>>();
+        assert!(preorder  == [ "a", "b", "d", "e", "f", "c" ].map(node));
+        assert!(postorder == [ "f", "e", "d", "b", "c", "a" ].map(node));
+        assert!(cfg.dfs_preorder(node("x")).next().is_none());
+    }
+
+}
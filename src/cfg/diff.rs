@@ -0,0 +1,94 @@
+use super::*;
+
+
+/// The nodes and edges which differ between two `ControlFlowGraph`s.
+/// 
+/// Created by `ControlFlowGraph::diff`. Nodes are compared by the blocks they were merged from, so a temporary
+///   node made by one graph is only matched in the other if it was given the same name.
+/// 
+/// Displayed with a line for each change, nodes before edges and additions before removals, each sorted so that
+///   the output is the same every time. Collapsing an empty `%bb2` between `%bb1` and `%bb3` gives:
+/// 
+/// ```text
+/// - %bb2
+/// + %bb1 -> %bb3
+/// - %bb1 -> %bb2
+/// - %bb2 -> %bb3
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct CfgDiff {
+    /// Nodes in the other graph but not this one.
+    pub added_nodes   : HashSet<CFGNode>,
+    /// Nodes in this graph but not the other one.
+    pub removed_nodes : HashSet<CFGNode>,
+    /// Edges in the other graph but not this one, as `(from, to)`.
+    pub added_edges   : HashSet<(CFGNode, CFGNode)>,
+    /// Edges in this graph but not the other one, as `(from, to)`.
+    pub removed_edges : HashSet<(CFGNode, CFGNode)>
+}
+
+
+impl CfgDiff {
+
+    /// Returns `true` if the two graphs have the same nodes and edges.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty() && self.removed_nodes.is_empty() && self.added_edges.is_empty() && self.removed_edges.is_empty()
+    }
+
+}
+
+
+impl ControlFlowGraph {
+
+    /// Gets the nodes and edges which were added or removed to get from this graph to `other`.
+    /// 
+    /// Only the shape of the graphs is compared, so which node is the entry, and how control leaves each block,
+    ///   are not. Useful for checking that a pass such as `collapse_empty_blocks` only changed what it should have.
+    pub fn diff(&self, other : &ControlFlowGraph) -> CfgDiff {
+        let nodes       = self.nodes.iter().cloned().collect::<HashSet<_>>();
+        let other_nodes = other.nodes.iter().cloned().collect::<HashSet<_>>();
+        let edges       = self.edge_set();
+        let other_edges = other.edge_set();
+        CfgDiff {
+            added_nodes   : other_nodes.difference(&nodes).cloned().collect(),
+            removed_nodes : nodes.difference(&other_nodes).cloned().collect(),
+            added_edges   : other_edges.difference(&edges).cloned().collect(),
+            removed_edges : edges.difference(&other_edges).cloned().collect()
+        }
+    }
+
+    /// Gets every edge in the graph, as `(from, to)`.
+    fn edge_set(&self) -> HashSet<(CFGNode, CFGNode)> {
+        self.succs.iter()
+            .flat_map(|(from, succs)| succs.iter().map(move |to| (from.clone(), to.clone())))
+            .collect()
+    }
+
+}
+
+
+impl fmt::Display for CfgDiff {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nodes = |sign, nodes : &HashSet<CFGNode>| {
+            let mut lines = nodes.iter().map(|node| format!("{} {}", sign, node)).collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+        let edges = |sign, edges : &HashSet<(CFGNode, CFGNode)>| {
+            let mut lines = edges.iter().map(|(from, to)| format!("{} {} -> {}", sign, from, to)).collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+        let lines = [
+            nodes('+', &self.added_nodes),
+            nodes('-', &self.removed_nodes),
+            edges('+', &self.added_edges),
+            edges('-', &self.removed_edges)
+        ];
+        for (i, line) in lines.iter().flatten().enumerate() {
+            if (i > 0) { writeln!(f)?; }
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use super::*;
+
+
+/// Which way out of a block an edge is taken, as given by `ControlFlowGraph::branch_order`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum EdgeLabel {
+
+    /// Taken when the condition of a conditional `br` is true.
+    True,
+
+    /// Taken when the condition of a conditional `br` is false.
+    False,
+
+    /// Taken when the value of a `switch` is this case value.
+    Case(CFGCaseValue),
+
+    /// Taken when no case of a `switch` matches.
+    Default,
+
+    /// The only way out, or where a `callbr` continues when its assembly falls through.
+    Fallthrough,
+
+    /// Where the assembly of a `callbr` can jump to instead of falling through.
+    Indirect
+
+}
+
+
+impl ControlFlowGraph {
+
+    /// Gets the successors of `node` in the order of its terminator, each labelled with the way out it is.
+    /// 
+    /// A conditional `br` gives its true successor then its false one, and a `switch` gives each case in order,
+    ///   followed by its default. A successor reached in more than one way is listed once for each of them.
+    ///   Successors of a node without a recorded branch, such as one which ends in an unconditional `br`, are
+    ///   all labelled `EdgeLabel::Fallthrough`, in the order of `ControlFlowGraph::succs`.
+    /// 
+    /// Destinations which are no longer successors of `node`, such as after a temporary node is inserted in front
+    ///   of them, are left out.
+    pub fn branch_order<N : Into<CFGNode>>(&self, node : N) -> Vec<(CFGNode, EdgeLabel)> {
+        let node = node.into();
+        let Some(succs) = self.succs(&node) else { return Vec::new() };
+        let find_succ = |dest : &Name| succs.into_iter().find(|succ| succ.from_pred() == dest).cloned();
+        let dests = match (self.branch(node.to_succ())) {
+            Some(CFGBranch::Cond { true_dest, false_dest }) => vec![ (true_dest, EdgeLabel::True), (false_dest, EdgeLabel::False) ],
+            Some(CFGBranch::Switch { cases, default }) => cases.iter()
+                .map(|(value, dest)| (dest, EdgeLabel::Case(*value)))
+                .chain([ (default, EdgeLabel::Default) ])
+                .collect(),
+            Some(CFGBranch::CallBr { default }) => [ (default, EdgeLabel::Fallthrough) ].into_iter()
+                .chain(succs.into_iter().map(|succ| succ.from_pred()).filter(|&dest| dest != default).map(|dest| (dest, EdgeLabel::Indirect)))
+                .collect(),
+            None => return succs.into_iter().map(|succ| (succ.clone(), EdgeLabel::Fallthrough)).collect()
+        };
+        dests.into_iter().filter_map(|(dest, label)| Some((find_succ(dest)?, label))).collect()
+    }
+
+}
+
+
+impl fmt::Display for EdgeLabel {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::True        => write!(f, "true"),
+            Self::False       => write!(f, "false"),
+            Self::Case(value) => write!(f, "case {}", value),
+            Self::Default     => write!(f, "default"),
+            Self::Fallthrough => write!(f, "fallthrough"),
+            Self::Indirect    => write!(f, "indirect")
+        }
+    }
+}
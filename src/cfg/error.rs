@@ -0,0 +1,55 @@
+use super::*;
+
+
+/// An error encountered while building a `ControlFlowGraph`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum CfgError {
+
+    /// A block ends in a terminator which can not be represented in a control flow graph.
+    UnsupportedTerminator {
+        /// The block which the terminator ends.
+        block  : Name,
+        /// The opcode of the unsupported terminator, such as `invoke`.
+        opcode : &'static str
+    },
+
+    /// The function has no blocks, such as a function which is only declared.
+    NoBlocks
+
+}
+
+
+impl fmt::Display for CfgError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::UnsupportedTerminator { block, opcode } => write!(f, "Unsupported terminator in {}: {}", block, opcode),
+            Self::NoBlocks                                => write!(f, "Function has no blocks")
+        }
+    }
+}
+
+impl CfgError {
+
+    /// Gets the error for the terminator of `block`, which is not supported.
+    pub(crate) fn unsupported_terminator(block : &BasicBlock) -> Self {
+        let opcode = match (&block.term) {
+            Terminator::Ret         (_) => "ret",
+            Terminator::Br          (_) => "br",
+            Terminator::CondBr      (_) => "br",
+            Terminator::Switch      (_) => "switch",
+            Terminator::IndirectBr  (_) => "indirectbr",
+            Terminator::Invoke      (_) => "invoke",
+            Terminator::Resume      (_) => "resume",
+            Terminator::Unreachable (_) => "unreachable",
+            Terminator::CleanupRet  (_) => "cleanupret",
+            Terminator::CatchRet    (_) => "catchret",
+            Terminator::CatchSwitch (_) => "catchswitch",
+            Terminator::CallBr      (_) => "callbr"
+        };
+        Self::UnsupportedTerminator { block : block.name.clone(), opcode }
+    }
+
+}
+
+impl std::error::Error for CfgError { }
@@ -0,0 +1,69 @@
+use super::*;
+
+use crate::util::json;
+
+
+impl ControlFlowGraph {
+
+
+    /// Formats the graph as a JSON object, for use by other tools.
+    /// 
+    /// ```text
+    /// {
+    ///     "entry": 0,
+    ///     "nodes": [
+    ///         { "from_pred": "%start", "to_succ": "%start", "is_temp": false },
+    ///         { "from_pred": "%bb1", "to_succ": "%bb1", "is_temp": false }
+    ///     ],
+    ///     "edges": [
+    ///         [0, 1]
+    ///     ]
+    /// }
+    /// ```
+    /// 
+    /// Nodes are listed in the order of `nodes`. `entry` and both ends of each edge are indices into `nodes`.
+    ///   Block names are written the same way as their `Display` implementation.
+    pub fn to_json(&self) -> String {
+        let index = self.nodes.iter().enumerate().map(|(i, node)| (node, i)).collect::<HashMap<_, _>>();
+
+        let mut out = String::from("{\n\t\"entry\": ");
+        match (index.get(&self.entry)) {
+            Some(entry) => out.push_str(&entry.to_string()),
+            None        => out.push_str("null")
+        }
+
+        out.push_str(",\n\t\"nodes\": [");
+        let mut first = true;
+        for node in &self.nodes {
+            if (first) { first = false; }
+            else { out.push(','); }
+            out.push_str("\n\t\t{ \"from_pred\": ");
+            json::write_string(&mut out, &node.from_pred().to_string());
+            out.push_str(", \"to_succ\": ");
+            json::write_string(&mut out, &node.to_succ().to_string());
+            out.push_str(", \"is_temp\": ");
+            let is_temp = node.from_pred() == node.to_succ() && self.temps.contains(node.from_pred());
+            out.push_str(if (is_temp) { "true" } else { "false" });
+            out.push_str(" }");
+        }
+        out.push_str(if (first) { "]" } else { "\n\t]" });
+
+        out.push_str(",\n\t\"edges\": [");
+        let mut first = true;
+        for node in &self.nodes {
+            let Some(succs) = self.succs.get(node) else { continue };
+            for succ in succs {
+                let Some(succ) = index.get(succ) else { continue };
+                if (first) { first = false; }
+                else { out.push(','); }
+                out.push_str(&format!("\n\t\t[{}, {}]", index[node], succ));
+            }
+        }
+        out.push_str(if (first) { "]" } else { "\n\t]" });
+
+        out.push_str("\n}");
+        out
+    }
+
+
+}
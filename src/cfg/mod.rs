@@ -51,13 +51,58 @@
 mod node;
 pub use node::CFGNode;
 
+mod branch;
+pub use branch::CFGBranch;
+
+mod case_value;
+pub use case_value::CFGCaseValue;
+
+mod edge_label;
+pub use edge_label::EdgeLabel;
+
+mod diff;
+pub use diff::CfgDiff;
+
+mod error;
+pub use error::CfgError;
+
+mod json;
+
+#[cfg(feature = "petgraph")]
+mod petgraph;
+
+mod dfs;
+pub use dfs::{
+    DfsPreorder,
+    DfsPostorder
+};
+
+mod scc;
+
+mod stats;
+pub use stats::CfgStats;
+
+mod validate;
+
 use crate::util::unique_vec::UniqueVec;
+use crate::theme::{
+    Theme,
+    Themed
+};
 
 use std::fmt;
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet
+};
+use std::sync::OnceLock;
 use llvm_ir::{
+    BasicBlock,
+    Constant,
     Function,
+    Instruction,
     Name,
+    Operand,
     Terminator
 };
 
@@ -66,16 +111,22 @@ use llvm_ir::{
 #[derive(Clone)]
 pub struct ControlFlowGraph {
     /// The entry node in the graph.
-    entry     : CFGNode,
+    entry      : CFGNode,
     /// All of the nodes in the graph.
-    nodes     : UniqueVec<CFGNode>,
+    nodes      : UniqueVec<CFGNode>,
     /// Predecessors of nodes in the graph.
-    preds     : HashMap<CFGNode, UniqueVec<CFGNode>>,
+    preds      : HashMap<CFGNode, UniqueVec<CFGNode>>,
     /// Successors of nodes in the graph.
-    succs     : HashMap<CFGNode, UniqueVec<CFGNode>>,
+    succs      : HashMap<CFGNode, UniqueVec<CFGNode>>,
     /// Temporary inserted nodes.
-    temps     : UniqueVec<Name>,
-    next_temp : u128
+    temps      : UniqueVec<Name>,
+    next_temp  : u128,
+    /// The position of each block in the original function.
+    layout     : HashMap<Name, usize>,
+    /// How control leaves blocks with multiple successors.
+    branches   : HashMap<Name, CFGBranch>,
+    /// The dominators of each node, worked out when first needed and forgotten whenever the graph changes.
+    dominators : OnceLock<HashMap<CFGNode, HashSet<CFGNode>>>
 }
 
 
@@ -83,14 +134,83 @@ impl ControlFlowGraph {
 
 
     /// Create a control flow graph of an LLVM `Function`.
+    /// 
+    /// Panics if the function has no blocks or contains an unsupported terminator. See `try_new` for a
+    ///   non-panicking version.
     pub fn new(function : &Function) -> Self {
+        match (Self::try_new(function)) {
+            Ok(cfg)  => cfg,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    /// Create a control flow graph of an LLVM `Function`, or a `CfgError` if it has no blocks or contains an
+    ///   unsupported terminator.
+    /// 
+    /// A function with a single block gives a graph of just its entry, which is recovered as that one block.
+    pub fn try_new(function : &Function) -> Result<Self, CfgError> {
+        Self::build(function, false)
+    }
+
+    /// Create a control flow graph of an LLVM `Function`, treating conditional branches on a constant as
+    ///   unconditional branches to the taken destination. Blocks which can then no longer be reached from the
+    ///   entry are removed.
+    /// 
+    /// ```text
+    /// br i1 true, label %a, label %b    ->    br label %a
+    /// ```
+    /// 
+    /// Panics if the function has no blocks or contains an unsupported terminator.
+    pub fn new_simplified(function : &Function) -> Self {
+        match (Self::build(function, true)) {
+            Ok(cfg)  => cfg,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    /// Create a control flow graph containing only `entry`, with no backing `Function`.
+    /// 
+    /// The rest of the graph can be built with `add_edge`. Since there are no terminators to read, every edge is
+    ///   treated as a true edge, and nodes with more than two successors are not treated as a `switch`.
+    /// 
+    /// ```rust
+    /// use decomp::prelude::*;
+    /// let name = |name : &str| Name::Name(Box::new(name.to_string()));
+    /// let mut cfg = ControlFlowGraph::with_entry(&name("head"));
+    /// cfg.add_edge(&name("head"), &name("body"));
+    /// cfg.add_edge(&name("body"), &name("head"));
+    /// cfg.add_edge(&name("head"), &name("exit"));
+    /// ```
+    pub fn with_entry<N : Into<CFGNode>>(entry : N) -> Self {
+        let entry = entry.into();
+        let mut nodes = UniqueVec::new();
+        nodes.insert(entry.clone());
+        ControlFlowGraph {
+            entry,
+            nodes,
+            preds      : HashMap::new(),
+            succs      : HashMap::new(),
+            temps      : UniqueVec::new(),
+            next_temp  : 0,
+            layout     : HashMap::new(),
+            branches   : HashMap::new(),
+            dominators : OnceLock::new()
+        }
+    }
+
+    fn build(function : &Function, simplify : bool) -> Result<Self, CfgError> {
+        // A declaration has no blocks, so there is no entry.
+        let Some(entry) = function.basic_blocks.first() else { return Err(CfgError::NoBlocks) };
         let mut cfg = ControlFlowGraph {
-            entry     : (&function.basic_blocks[0].name).into(),
-            nodes     : UniqueVec::new(),
-            preds     : HashMap::new(),
-            succs     : HashMap::new(),
-            temps     : UniqueVec::new(),
-            next_temp : 0
+            entry      : (&entry.name).into(),
+            nodes      : UniqueVec::new(),
+            preds      : HashMap::new(),
+            succs      : HashMap::new(),
+            temps      : UniqueVec::new(),
+            next_temp  : 0,
+            layout     : function.basic_blocks.iter().enumerate().map(|(i, block)| (block.name.clone(), i)).collect(),
+            branches   : HashMap::new(),
+            dominators : OnceLock::new()
         };
 
         for block in &function.basic_blocks { match (&block.term) {
@@ -99,16 +219,34 @@ impl ControlFlowGraph {
                 cfg.add_edge(&block.name, &term.dest);
             },
 
+            Terminator::CondBr(term) if (simplify && Self::constant_condition(&term.condition).is_some()) => {
+                let dest = if (Self::constant_condition(&term.condition).unwrap()) { &term.true_dest } else { &term.false_dest };
+                cfg.add_edge(&block.name, dest);
+            },
+
             Terminator::CondBr(term) => {
                 cfg.add_edge(&block.name, &term.true_dest);
                 cfg.add_edge(&block.name, &term.false_dest);
+                cfg.branches.insert(block.name.clone(), CFGBranch::Cond {
+                    true_dest  : term.true_dest.clone(),
+                    false_dest : term.false_dest.clone()
+                });
             },
 
             Terminator::Switch(term) => {
-                for (_, dest) in &term.dests {
+                let mut cases = Vec::new();
+                for (value, dest) in &term.dests {
+                    let Some(value) = CFGCaseValue::from_constant(value) else {
+                        return Err(CfgError::unsupported_terminator(block));
+                    };
+                    cases.push((value, dest.clone()));
                     cfg.add_edge(&block.name, dest);
                 }
                 cfg.add_edge(&block.name, &term.default_dest);
+                cfg.branches.insert(block.name.clone(), CFGBranch::Switch {
+                    cases,
+                    default : term.default_dest.clone()
+                });
             },
 
             Terminator::IndirectBr(term) => {
@@ -117,55 +255,244 @@ impl ControlFlowGraph {
                 }
             },
 
+            // llvm-ir does not read the indirect labels of a `callbr`, so only the default destination is known.
+            Terminator::CallBr(term) => {
+                cfg.add_edge(&block.name, &term.return_label);
+                cfg.branches.insert(block.name.clone(), CFGBranch::CallBr {
+                    default : term.return_label.clone()
+                });
+            },
+
             Terminator::Ret(_) | Terminator::Unreachable(_) => { },
 
-            term @ Terminator::Invoke      (_) |
-            term @ Terminator::Resume      (_) |
-            term @ Terminator::CleanupRet  (_) |
-            term @ Terminator::CatchRet    (_) |
-            term @ Terminator::CatchSwitch (_) |
-            term @ Terminator::CallBr      (_)
-                => { panic!("Unsupported terminator: {}", term) }
+            Terminator::Invoke      (_) |
+            Terminator::Resume      (_) |
+            Terminator::CleanupRet  (_) |
+            Terminator::CatchRet    (_) |
+            Terminator::CatchSwitch (_)
+                => { return Err(CfgError::unsupported_terminator(block)); }
 
         } }
 
-        cfg
+        if (simplify) {
+            // Blocks only reached through a dead edge are now unreachable.
+            let reachable = cfg.dfs_preorder(&cfg.entry).cloned().collect::<HashSet<_>>();
+            let unreachable = cfg.nodes.iter().filter(|node| ! reachable.contains(node)).cloned().collect::<Vec<_>>();
+            for node in unreachable {
+                cfg.branches.remove(node.to_succ());
+                cfg.remove_node(&node);
+            }
+        }
+
+        cfg.sort_nodes_by_layout();
+        Ok(cfg)
+    }
+
+
+    /// Gets the value of a branch condition, or `None` if it is not a constant.
+    fn constant_condition(condition : &Operand) -> Option<bool> {
+        let Operand::ConstantOperand(constant) = condition else { return None };
+        match (&**constant) {
+            Constant::Int { bits : 1, value } => Some(*value != 0),
+            _ => None
+        }
     }
 
 
     /// Gets the entry node.
     pub fn entry(&self) -> &CFGNode { &self.entry }
 
-    pub(crate) fn set_entry<N : Into<CFGNode>>(&mut self, node : N) -> () { self.entry = node.into(); }
+    /// Sets the entry node, adding it to the graph if it is not in it yet.
+    /// 
+    /// A node which has just been merged from the whole graph has no edges, so it is only added here.
+    pub(crate) fn set_entry<N : Into<CFGNode>>(&mut self, node : N) -> () {
+        self.dominators.take();
+        self.entry = node.into();
+        self.nodes.insert(self.entry.clone());
+    }
+
+    /// Sets the entry node, or returns `Err` without changing anything if the node is not in the graph.
+    pub fn set_entry_checked<N : Into<CFGNode>>(&mut self, node : N) -> Result<(), ()> {
+        let node = node.into();
+        if (! self.nodes.contains(&node)) { return Err(()); }
+        self.dominators.take();
+        self.entry = node;
+        Ok(())
+    }
 
     /// Get all nodes available.
+    /// 
+    /// The nodes are kept in the order of the blocks in the original function, rather than the order they were
+    ///   discovered in.
     pub fn nodes(&self) -> &UniqueVec<CFGNode> { &self.nodes }
 
+    /// Returns `true` if the graph has been reduced to a single node.
+    /// 
+    /// A single node which loops back to itself is an infinite loop which has not been merged yet, so is not
+    ///   reduced.
+    pub fn is_reduced(&self) -> bool {
+        match (self.nodes.len()) {
+            0 => true,
+            1 => self.exits().len() == 1,
+            _ => false
+        }
+    }
+
     /// Get all nodes preceeding the given node.
     pub fn preds<N : Into<CFGNode>>(&self, node : N) -> Option<&UniqueVec<CFGNode>> { self.preds.get(&node.into()) }
 
+    /// Gets the number of nodes preceeding the given node.
+    /// 
+    /// Control enters the function at the entry node, so that counts as an extra predecessor of the entry node.
+    pub(crate) fn pred_count<N : Into<CFGNode>>(&self, node : N) -> usize {
+        let node  = node.into();
+        let preds = self.preds(&node).map(|preds| preds.len()).unwrap_or(0);
+        if (node == self.entry) { preds + 1 } else { preds }
+    }
+
     /// Get all nodes succeeding the given node.
+    /// 
+    /// The successors are in the order their edges were added, which says nothing about which way each is taken.
+    ///   Use `ControlFlowGraph::branch_order` to get them in the order of the terminator.
     pub fn succs<N : Into<CFGNode>>(&self, node : N) -> Option<&UniqueVec<CFGNode>> { self.succs.get(&node.into()) }
 
     /// Get all temporary nodes.
     pub fn temps(&self) -> &UniqueVec<Name> { &self.temps }
 
+    /// Gets how control leaves a block, or `None` if nothing is recorded for it.
+    pub fn branch(&self, name : &Name) -> Option<&CFGBranch> { self.branches.get(name) }
+
+    /// Gets the successor of `node` which its terminator reaches by branching to `dest`, or `None` if there is
+    ///   none.
+    /// 
+    /// Temporary nodes inserted on the edge are looked through, so the successor may be a temporary node.
+    pub(crate) fn branch_succ(&self, node : &CFGNode, dest : &Name) -> Option<&CFGNode> {
+        self.succs.get(node)?.iter().find(|succ| self.branch_target(succ) == dest)
+    }
+
+    /// Gets the block an edge into `node` originally went to, following any temporary nodes to the node after them.
+    fn branch_target<'l>(&'l self, mut node : &'l CFGNode) -> &'l Name {
+        while (self.temps.contains(node.from_pred())) {
+            let Some(next) = self.succs.get(node).filter(|succs| succs.len() == 1).and_then(|succs| succs.get(0)) else { break };
+            node = next;
+        }
+        node.from_pred()
+    }
+
+    /// Returns `true` if `from` ends in a conditional branch, and `to` is reached when the condition is false.
+    /// 
+    /// If `to` is a temporary node inserted on the edge, the block it leads to is checked instead.
+    pub fn is_false_edge<F : Into<CFGNode>, T : Into<CFGNode>>(&self, from : F, to : T) -> bool {
+        let from = from.into();
+        let to   = to.into();
+        let dest = self.branch_target(&to);
+        match (self.branches.get(from.to_succ())) {
+            Some(CFGBranch::Cond { true_dest, false_dest }) => false_dest == dest && true_dest != dest,
+            _ => false
+        }
+    }
+
+    /// Returns `true` if `node` ends in a `callbr`, whose destination is chosen by inline assembly.
+    pub fn is_asm_goto<N : Into<CFGNode>>(&self, node : N) -> bool {
+        matches!(self.branches.get(node.into().to_succ()), Some(CFGBranch::CallBr { .. }))
+    }
+
+    /// Gets every block which ends in a `callbr`, along with every block it can continue to, starting with the one
+    ///   it falls through to.
+    /// 
+    /// Every other successor of the block is one the assembly can jump to. A graph built from a `Function` has
+    ///   none of these, as llvm-ir does not read them, so they are only found if the edges are added by hand.
+    pub fn asm_gotos(&self) -> HashMap<Name, Vec<Name>> {
+        self.branches.iter()
+            .filter_map(|(name, branch)| match (branch) {
+                CFGBranch::CallBr { default } => {
+                    let indirect = self.succs(name).into_iter().flatten().map(|succ| succ.from_pred()).filter(|&dest| dest != default);
+                    Some((name.clone(), [ default ].into_iter().chain(indirect).cloned().collect()))
+                },
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Gets the position of a block in the original function's `basic_blocks`, or `None` if it is not an original block.
+    pub fn layout_index(&self, name : &Name) -> Option<usize> { self.layout.get(name).copied() }
+
+    /// Reorders the nodes to match the original block order, where possible.
+    /// 
+    /// Merged nodes are ordered by their entry block. Temporary nodes are placed last.
+    pub(crate) fn sort_nodes_by_layout(&mut self) -> () {
+        let layout = &self.layout;
+        self.nodes.sort_by_key(|node| layout.get(node.from_pred()).copied().unwrap_or(usize::MAX));
+    }
+
+    /// Get all exit (sink) nodes. These are the nodes with no successors.
+    pub fn exits(&self) -> Vec<&CFGNode> {
+        self.nodes.iter()
+            .filter(|node| self.succs.get(node).map(|succs| succs.is_empty()).unwrap_or(true))
+            .collect()
+    }
+
 
     /// Creates a unidirectional connection between two nodes.
     /// 
     /// This will create the nodes if they do not already exist.
     pub fn add_edge<F : Into<CFGNode>, T : Into<CFGNode>>(&mut self, from : F, to : T) -> () {
-        let from = from.into();
-        let to = to.into();
-        self.preds.entry(to.clone()).or_insert_with(|| UniqueVec::new()).insert(from.clone());
-        self.succs.entry(from.clone()).or_insert_with(|| UniqueVec::new()).insert(to.clone());
+        let from = self.existing_node(from.into());
+        let to = self.existing_node(to.into());
+        self.dominators.take();
+        self.preds.entry(to.clone()).or_insert_with(UniqueVec::new).insert(from.clone());
+        self.succs.entry(from.clone()).or_insert_with(UniqueVec::new).insert(to.clone());
         self.nodes.insert(from.clone());
         self.nodes.insert(to.clone());
     }
 
+    /// Gets the copy of `node` already stored in the graph, so that they share names, or `node` if there is none.
+    fn existing_node(&self, node : CFGNode) -> CFGNode {
+        match (self.succs.get_key_value(&node).or_else(|| self.preds.get_key_value(&node))) {
+            Some((existing, _)) => existing.clone(),
+            None                => node
+        }
+    }
+
+    /// Gives a node on the graph a readable label, which is shown instead of its names.
+    /// 
+    /// Every copy of the node stored in the graph is relabelled. The label does not change which node it is.
+    pub fn set_label<N : Into<CFGNode>, S : Into<String>>(&mut self, node : N, label : S) -> () {
+        let node = self.existing_node(node.into()).with_label(label);
+        self.dominators.take();
+        if (self.entry == node) { self.entry = node.clone(); }
+        self.nodes.replace(node.clone());
+        for edges in [ &mut self.preds, &mut self.succs ] {
+            if let Some(others) = edges.remove(&node) {
+                edges.insert(node.clone(), others);
+            }
+            for others in edges.values_mut() {
+                others.replace(node.clone());
+            }
+        }
+    }
+
+    /// Removes the unidirectional connection between two nodes, if it exists.
+    /// 
+    /// The nodes themselves are not removed.
+    pub fn remove_edge<F : Into<CFGNode>, T : Into<CFGNode>>(&mut self, from : F, to : T) -> () {
+        let from = from.into();
+        let to = to.into();
+        self.dominators.take();
+        if let Some(succs) = self.succs.get_mut(&from) {
+            succs.remove(&to);
+        }
+        if let Some(preds) = self.preds.get_mut(&to) {
+            preds.remove(&from);
+        }
+    }
+
     /// Removes a node, along with all connections to or from it.
+    /// 
+    /// No edge is left mentioning the node, but removing the entry leaves the graph without one. See `validate`.
     pub fn remove_node<N : Into<CFGNode>>(&mut self, node : N) -> () {
         let node = node.into();
+        self.dominators.take();
         self.nodes.remove(&node);
         self.preds.remove(&node);
         self.succs.remove(&node);
@@ -179,6 +506,8 @@ impl ControlFlowGraph {
 
     /// Inserts the given node between `after` and `before`, destroying the previous connection if needed.
     /// 
+    /// Any of the nodes which are not already in the graph are added to it.
+    /// 
     /// ```
     ///          A
     /// A        |
@@ -191,55 +520,467 @@ impl ControlFlowGraph {
         let after = after.into();
         let before = before.into();
         // Disconnect after and before.
-        if let Some(succs) = self.succs.get_mut(&after) {
-            succs.remove(&before);
-        }
-        if let Some(preds) = self.preds.get_mut(&before) {
-            preds.remove(&after);
-        }
+        self.remove_edge(&after, &before);
         // Insert the new node.
         self.add_edge(&after, &node);
         self.add_edge(&node, &before);
     }
 
+    /// Merges exit (sink) nodes whose blocks in `function` have identical instructions and terminators.
+    /// 
+    /// Optimizers often duplicate a trivial `return` block into every predecessor. Coalescing these before
+    ///   analysis simplifies the graph and avoids spurious conditionals.
+    /// 
+    /// Debug locations on the terminators are ignored when comparing blocks. Branches to a removed sink are
+    ///   pointed at the sink it was merged into.
+    pub fn coalesce_identical_sinks(&mut self, function : &Function) -> () {
+        let mut kept : Vec<&BasicBlock> = Vec::new();
+        let mut duplicates : Vec<(CFGNode, CFGNode)> = Vec::new();
+        for sink in self.exits() {
+            // Only plain blocks can be compared.
+            let name = sink.from_pred();
+            if (name != sink.to_succ() || sink == &self.entry || self.temps.contains(name)) { continue; }
+            let Some(block) = function.basic_blocks.iter().find(|block| &block.name == name) else { continue };
+            if let Some(original) = kept.iter().find(|original| Self::is_identical_sink(original, block)) {
+                duplicates.push((sink.clone(), (&original.name).into()));
+            } else {
+                kept.push(block);
+            }
+        }
+        for (duplicate, original) in duplicates {
+            if let Some(preds) = self.preds.get(&duplicate).cloned() {
+                for pred in &preds {
+                    self.add_edge(pred, &original);
+                    self.retarget_branch(pred, duplicate.from_pred(), original.from_pred());
+                }
+            }
+            self.remove_node(&duplicate);
+        }
+    }
+    fn is_identical_sink(a : &BasicBlock, b : &BasicBlock) -> bool {
+        if (a.instrs != b.instrs) { return false; }
+        match (&a.term, &b.term) {
+            (Terminator::Ret(a), Terminator::Ret(b)) => a.return_operand == b.return_operand,
+            (Terminator::Unreachable(_), Terminator::Unreachable(_)) => true,
+            _ => false
+        }
+    }
+
+    /// Removes blocks of `function` which contain nothing but an unconditional `br`, connecting their predecessors
+    ///   directly to their successor.
+    /// 
+    /// ```text
+    /// A        A
+    /// |        |
+    /// E   ->   |
+    /// |        |
+    /// B        B
+    /// ```
+    /// 
+    /// A block is kept if it is the entry, if it branches to itself, if its successor has `phi` instructions which
+    ///   depend on it, or if removing it would join both edges of a conditional branch.
+    pub fn collapse_empty_blocks(&mut self, function : &Function) -> () {
+        for block in &function.basic_blocks {
+            let Terminator::Br(term) = &block.term else { continue };
+            if (! block.instrs.is_empty()) { continue; }
+            let node : CFGNode = (&block.name).into();
+            let succ : CFGNode = (&term.dest).into();
+            if (node == self.entry || node == succ || ! self.nodes.contains(&node) || self.temps.contains(&block.name)) { continue; }
+
+            // The incoming values of a phi need to know which block they came from.
+            let succ_has_phis = function.basic_blocks.iter()
+                .find(|succ_block| succ_block.name == term.dest)
+                .map(|succ_block| succ_block.instrs.iter().any(|instr| matches!(instr, Instruction::Phi(_))))
+                .unwrap_or(false);
+            if (succ_has_phis) { continue; }
+
+            let preds = self.preds.get(&node).cloned().unwrap_or_else(UniqueVec::new);
+            if (preds.iter().any(|pred| self.succs.get(pred).map(|pred_succs| pred_succs.contains(&succ)).unwrap_or(false))) { continue; }
+
+            for pred in &preds {
+                self.add_edge(pred, &succ);
+                self.retarget_branch(pred, &block.name, &term.dest);
+            }
+            self.remove_node(&node);
+        }
+        self.sort_nodes_by_layout();
+    }
+
+    /// Points the recorded branch of `pred` at `to` wherever it went to `from`.
+    fn retarget_branch(&mut self, pred : &CFGNode, from : &Name, to : &Name) -> () {
+        match (self.branches.get_mut(pred.to_succ())) {
+            Some(CFGBranch::Cond { true_dest, false_dest }) => {
+                for dest in [ true_dest, false_dest ] {
+                    if (dest == from) { *dest = to.clone(); }
+                }
+            },
+            Some(CFGBranch::Switch { cases, default }) => {
+                for (_, dest) in cases.iter_mut() {
+                    if (dest == from) { *dest = to.clone(); }
+                }
+                if (default == from) { *default = to.clone(); }
+            },
+            Some(CFGBranch::CallBr { default }) if (default == from) => { *default = to.clone(); },
+            _ => { }
+        }
+    }
+
+    /// Adds an edge back to the entry from each block of `function` which ends in a tail call to `function`
+    ///   itself, so that a tail recursive function is recovered as a loop.
+    /// 
+    /// ```text
+    /// A                  A <-.
+    /// |                  |   |
+    /// B        ->        B   |
+    /// |                  |   |
+    /// C: return f()      C --'
+    /// ```
+    /// 
+    /// A block qualifies when its last instruction is a direct call to `function`, and it is followed by a `ret`
+    ///   of nothing or of the result of that call. The call is left in the block, and the block still returns, so
+    ///   this changes the shape of the graph without changing the blocks. The arguments of the call are not
+    ///   assigned back to the parameters, so the loop is only a guide to the structure of the function.
+    pub fn link_tail_self_calls(&mut self, function : &Function) -> () {
+        let entry = self.entry.clone();
+        for block in &function.basic_blocks {
+            let node : CFGNode = (&block.name).into();
+            if (! self.nodes.contains(&node) || self.temps.contains(&block.name)) { continue; }
+            if (Self::is_tail_self_call(function, block)) {
+                self.add_edge(&node, &entry);
+            }
+        }
+    }
+    fn is_tail_self_call(function : &Function, block : &BasicBlock) -> bool {
+        let Terminator::Ret(term) = &block.term else { return false };
+        let Some(Instruction::Call(call)) = block.instrs.last() else { return false };
+        let is_self = match (call.function.as_ref().right()) {
+            Some(Operand::ConstantOperand(constant)) => match (&**constant) {
+                Constant::GlobalReference { name : Name::Name(name), .. } => **name == function.name,
+                _ => false
+            },
+            _ => false
+        };
+        if (! is_self) { return false; }
+        match (&term.return_operand, &call.dest) {
+            (None, _)                                              => true,
+            (Some(Operand::LocalOperand { name, .. }), Some(dest)) => name == dest,
+            _                                                      => false
+        }
+    }
+
     /// Creates a temporary node which is treated as identical to `point_to`.
     /// 
     /// Used when collapsing the control flow graph down to the primitives.
     pub fn create_temporary_node(&mut self) -> Name {
+        let name = self.create_unique_name("TEMPORARY");
+        self.temps.insert(name.clone());
+        name
+    }
+
+    /// Returns `true` if `name` has the form of a name made by `create_temporary_node`.
+    pub(crate) fn is_temporary_name(name : &Name) -> bool {
+        let prefix = format!("@{}_TEMPORARY_", crate::MODULE_NAME.to_uppercase());
+        matches!(name, Name::Name(name) if name.starts_with(&prefix))
+    }
+
+    /// Creates a name which is not used by any node in the graph.
+    /// 
+    /// `next_temp` only ever increases, so a collision is only possible with a block that was given a name
+    ///   matching the pattern. A node with no edges, such as the entry of a graph made by `with_entry`, has no
+    ///   entry in `preds` or `succs`, so every node is checked for collisions.
+    fn create_unique_name(&mut self, kind : &str) -> Name {
         let mut name;
-        // Find a temporary name that is not used.
+        // Find a name that is not used.
         loop {
-            name = Name::Name(Box::new(format!("@{}_TEMPORARY_{}", crate::MODULE_NAME.to_uppercase(), self.next_temp)));
+            name = Name::Name(Box::new(format!("@{}_{}_{}", crate::MODULE_NAME.to_uppercase(), kind, self.next_temp)));
             self.next_temp += 1;
-            if (! self.nodes.contains(&(&name).into())) { break; }
+            if (! self.nodes.iter().any(|node| node.from_pred() == &name || node.to_succ() == &name)) { break; }
         }
-        self.temps.insert(name.clone());
         name
     }
 
 
-    /// Returns `true` if every path from entry to `to`, must go through `to`.
+    /// Splices a copy of `callee` into this graph, replacing the connection from `call_site` to `return_to`.
     /// 
-    /// By definition, a node will always dominate itself.
+    /// Every block of `callee` is renamed to avoid collisions with this graph. `call_site` is connected to the
+    ///   entry of the callee, and every exit of the callee is connected to `return_to`. Temporary nodes of the
+    ///   callee remain temporary, and its branches are kept. Its blocks are laid out after every block of this
+    ///   graph, in their order in the callee.
+    /// 
+    /// Returns a map from the names in `callee` to their new names in this graph.
+    /// 
+    /// ```text
+    ///            A
+    ///            |
+    /// A          E    <- Callee entry.
+    /// |   ->    / \
+    /// B        X   Y  <- Callee exits.
+    ///           \ /
+    ///            B
+    /// ```
+    pub fn merge_graphs<C : Into<CFGNode>, R : Into<CFGNode>>(&mut self, callee : &ControlFlowGraph, call_site : C, return_to : R) -> HashMap<Name, Name> {
+        let call_site = call_site.into();
+        let return_to = return_to.into();
+
+        // Find a new name for every block in the callee.
+        let mut renames = HashMap::new();
+        for node in callee.nodes().iter().chain([ callee.entry() ]) {
+            for name in [ node.from_pred(), node.to_succ() ] {
+                if (! renames.contains_key(name)) {
+                    let new_name = self.create_unique_name("INLINED");
+                    renames.insert(name.clone(), new_name);
+                }
+            }
+        }
+        let rename = |node : &CFGNode| CFGNode::new(renames[node.from_pred()].clone(), renames[node.to_succ()].clone());
+
+        for temp in callee.temps() {
+            if let Some(new_name) = renames.get(temp) {
+                self.temps.insert(new_name.clone());
+            }
+        }
+
+        // Copy how the callee branches, and where its blocks are.
+        let rename_name = |name : &Name| renames.get(name).cloned().unwrap_or_else(|| name.clone());
+        for (name, branch) in &callee.branches {
+            if let Some(new_name) = renames.get(name) {
+                self.branches.insert(new_name.clone(), branch.renamed(rename_name));
+            }
+        }
+        let layout_end = self.layout.values().max().map(|index| index + 1).unwrap_or(0);
+        for (name, index) in &callee.layout {
+            if let Some(new_name) = renames.get(name) {
+                self.layout.insert(new_name.clone(), layout_end + index);
+            }
+        }
+
+        // Copy the edges of the callee.
+        for from in callee.nodes() {
+            let Some(succs) = callee.succs(from) else { continue };
+            for to in succs {
+                self.add_edge(rename(from), rename(to));
+            }
+        }
+
+        // Connect the callee between the call site and the return node.
+        self.remove_edge(&call_site, &return_to);
+        self.add_edge(&call_site, rename(callee.entry()));
+        if (callee.nodes().is_empty()) {
+            self.add_edge(rename(callee.entry()), &return_to);
+        } else {
+            for exit in callee.exits() {
+                self.add_edge(rename(exit), &return_to);
+            }
+        }
+
+        renames
+    }
+
+
+    /// Creates a copy of the graph with every block renamed by `rename`.
+    /// 
+    /// `rename` is called once for each distinct name, and the result is used everywhere that name appears: the
+    ///   entry, nodes, edges, temporary nodes, layout, and branches. It should not give two names the same result.
+    /// 
+    /// ```rust,no_run
+    /// use decomp::prelude::*;
+    /// # let module   = Module::from_bc_path("/path/to/file.bc").unwrap();
+    /// # let function = &module.functions[0];
+    /// let cfg = ControlFlowGraph::new(function);
+    /// let copy = cfg.clone_with_renamed_nodes(|name| Name::Name(Box::new(format!("copy.{}", name))));
+    /// ```
+    pub fn clone_with_renamed_nodes<F : Fn(&Name) -> Name>(&self, rename : F) -> ControlFlowGraph {
+        // Rename each distinct name once, so that every part of the graph agrees.
+        let mut renames = HashMap::new();
+        let names = self.nodes.iter().chain([ &self.entry ])
+            .flat_map(|node| [ node.from_pred(), node.to_succ() ])
+            .chain(&self.temps)
+            .chain(self.layout.keys())
+            .chain(self.branches.keys());
+        for name in names {
+            if (! renames.contains_key(name)) {
+                renames.insert(name.clone(), rename(name));
+            }
+        }
+        let rename_name = |name : &Name| renames.get(name).cloned().unwrap_or_else(|| rename(name));
+        let rename_node = |node : &CFGNode| {
+            let renamed = CFGNode::new(rename_name(node.from_pred()), rename_name(node.to_succ()));
+            match (node.label()) {
+                Some(label) => renamed.with_label(label),
+                None        => renamed
+            }
+        };
+        let rename_edges = |edges : &HashMap<CFGNode, UniqueVec<CFGNode>>| edges.iter()
+            .map(|(node, others)| (rename_node(node), others.iter().map(rename_node).collect()))
+            .collect();
+
+        ControlFlowGraph {
+            entry      : rename_node(&self.entry),
+            nodes      : self.nodes.iter().map(rename_node).collect(),
+            preds      : rename_edges(&self.preds),
+            succs      : rename_edges(&self.succs),
+            temps      : self.temps.iter().map(rename_name).collect(),
+            next_temp  : self.next_temp,
+            layout     : self.layout.iter().map(|(name, &index)| (rename_name(name), index)).collect(),
+            branches   : self.branches.iter().map(|(name, branch)| (rename_name(name), branch.renamed(rename_name))).collect(),
+            dominators : OnceLock::new()
+        }
+    }
+
+
+    /// Returns `true` if every path from entry to `to`, must go through `through`.
+    /// 
+    /// By definition, a node will always dominate itself. Nodes which can not be reached from the entry are not
+    ///   dominated by anything else, the same as with `strictly_dominates`.
     /// 
     /// Relevant information: [Dominator (Graph theory)](https://en.wikipedia.org/wiki/Dominator_(graph_theory))
     pub fn dominates<H : Into<CFGNode>, O : Into<CFGNode>>(&self, through : H, to : O) -> bool {
         let through = through.into();
         let to = to.into();
-        if (through == to) { return true; }
-        self.dominates_inner(&self.entry, &through, &to, &mut Vec::new())
-    }
-    fn dominates_inner<'l>(&'l self, at : &'l CFGNode, through : &CFGNode, to : &CFGNode, already_checked : &mut Vec<&'l CFGNode>) -> bool {
-        already_checked.push(at);
-        let Some(succs) = self.succs.get(at) else { return true };
-        for succ in succs {
-            if (already_checked.contains(&succ)) { continue; }
-            if (succ == through) { continue; }
-            if (! self.dominates_inner(succ, through, to, already_checked)) {
-                return false;
+        through == to || self.strictly_dominates(through, to)
+    }
+
+    /// Returns `true` if every path from entry to `to` goes through `through`, and `through` is not `to`.
+    /// 
+    /// Nodes which can not be reached from the entry are not dominated by anything.
+    pub(crate) fn strictly_dominates<H : Into<CFGNode>, O : Into<CFGNode>>(&self, through : H, to : O) -> bool {
+        let through = through.into();
+        let to = to.into();
+        if (through == to) { return false; }
+        self.dominator_sets().get(&to).map(|dominators| dominators.contains(&through)).unwrap_or(false)
+    }
+
+    /// Get every node which dominates `node`, including the entry and `node` itself.
+    /// 
+    /// The dominators are ordered from the entry down to `node`. Nodes which can not be reached from the entry
+    ///   have no dominators.
+    pub fn dominators_of<N : Into<CFGNode>>(&self, node : N) -> UniqueVec<CFGNode> {
+        let dominators = self.dominator_sets();
+        let Some(node_dominators) = dominators.get(&node.into()) else { return UniqueVec::new() };
+        // Each dominator is dominated by all of the dominators before it.
+        let mut out = node_dominators.iter().cloned().collect::<Vec<_>>();
+        out.sort_by_key(|dominator| dominators[dominator].len());
+        out.into_iter().collect()
+    }
+
+    /// Get the closest node which strictly dominates `node`, which is its parent in the dominator tree.
+    /// 
+    /// Returns `None` for the entry, and for nodes which can not be reached from the entry.
+    pub fn immediate_dominator<N : Into<CFGNode>>(&self, node : N) -> Option<CFGNode> {
+        let dominators = self.dominators_of(node);
+        // The last dominator is the node itself.
+        dominators.len().checked_sub(2).and_then(|i| dominators.get(i)).cloned()
+    }
+
+
+    /// Get all back edges in the graph, as `(from, to)` pairs.
+    /// 
+    /// A back edge is an edge whose target dominates its source, such as the edge from the end of a loop back to
+    ///   its header.
+    pub fn back_edges(&self) -> Vec<(CFGNode, CFGNode)> {
+        let dominators = self.dominator_sets();
+        let mut out = Vec::new();
+        for from in &self.nodes {
+            let Some(from_dominators) = dominators.get(from) else { continue };
+            let Some(succs) = self.succs.get(from) else { continue };
+            for to in succs {
+                if (from_dominators.contains(to)) {
+                    out.push((from.clone(), to.clone()));
+                }
+            }
+        }
+        out
+    }
+
+    /// Returns `true` if `a` and `b` are in the same innermost loop.
+    /// 
+    /// Loops are the natural loops of the back edges, with loops that share a header counted as one. Nodes which
+    ///   are not in any loop are all in the same top level region.
+    pub fn same_loop<A : Into<CFGNode>, B : Into<CFGNode>>(&self, a : A, b : B) -> bool {
+        let loops = self.natural_loops();
+        // The innermost loop containing a node is the smallest one.
+        let innermost = |node : &CFGNode| loops.iter()
+            .filter(|(_, body)| body.contains(node))
+            .min_by_key(|(_, body)| body.len())
+            .map(|(header, _)| header);
+        innermost(&a.into()) == innermost(&b.into())
+    }
+
+    /// Get the nodes of the natural loop of every back edge, including the header, by header.
+    /// 
+    /// The natural loop of a back edge is its header, and every node which can reach the source of the edge
+    ///   without going through the header.
+    pub(crate) fn natural_loops(&self) -> HashMap<CFGNode, HashSet<CFGNode>> {
+        let mut loops : HashMap<CFGNode, HashSet<CFGNode>> = HashMap::new();
+        for (from, header) in self.back_edges() {
+            let body = loops.entry(header.clone()).or_insert_with(|| HashSet::from([ header.clone() ]));
+            let mut stack = vec![ from ];
+            while let Some(node) = stack.pop() {
+                if (! body.insert(node.clone())) { continue; }
+                if let Some(preds) = self.preds.get(&node) {
+                    stack.extend(preds.iter().cloned());
+                }
+            }
+        }
+        loops
+    }
+
+    /// Get all nodes in topological order, treating back edges as removed.
+    /// 
+    /// Nodes which can not be reached from the entry are placed last. In an irreducible graph, some cycles have
+    ///   no back edge, and the nodes of those cycles are ordered by a depth first search instead.
+    pub fn topo_order(&self) -> Vec<CFGNode> {
+        let back_edges = self.back_edges();
+        self.postorders([ &self.entry ].into_iter().chain(&self.nodes), &back_edges).into_iter()
+            .flat_map(|postorder| postorder.into_iter().rev())
+            .collect()
+    }
+
+    /// Gets the dominators of every node reachable from the entry.
+    /// 
+    /// They are only worked out again after the graph has changed, so checking dominance many times while
+    ///   searching for a primitive is cheap.
+    fn dominator_sets(&self) -> &HashMap<CFGNode, HashSet<CFGNode>> {
+        self.dominators.get_or_init(|| self.compute_dominator_sets())
+    }
+
+    /// Computes the dominators of every node reachable from the entry.
+    /// 
+    /// Relevant information: [Dominator (Graph theory)](https://en.wikipedia.org/wiki/Dominator_(graph_theory)#Algorithms)
+    fn compute_dominator_sets(&self) -> HashMap<CFGNode, HashSet<CFGNode>> {
+        let mut order = self.postorders([ &self.entry ], &[]).concat();
+        order.reverse();
+
+        // Start with every node dominating every other node, except at the entry.
+        let all = order.iter().cloned().collect::<HashSet<_>>();
+        let mut dominators = order.iter().map(|node| (node.clone(), all.clone())).collect::<HashMap<_, _>>();
+        dominators.insert(self.entry.clone(), HashSet::from([ self.entry.clone() ]));
+
+        // Narrow down the dominators until nothing changes.
+        let mut changed = true;
+        while (changed) {
+            changed = false;
+            for node in order.iter().skip(1) {
+                let mut new_dominators : Option<HashSet<CFGNode>> = None;
+                if let Some(preds) = self.preds.get(node) {
+                    for pred in preds {
+                        let Some(pred_dominators) = dominators.get(pred) else { continue };
+                        new_dominators = Some(match (new_dominators) {
+                            Some(new_dominators) => new_dominators.intersection(pred_dominators).cloned().collect(),
+                            None                 => pred_dominators.clone()
+                        });
+                    }
+                }
+                let mut new_dominators = new_dominators.unwrap_or_default();
+                new_dominators.insert(node.clone());
+                if (dominators[node] != new_dominators) {
+                    dominators.insert(node.clone(), new_dominators);
+                    changed = true;
+                }
             }
         }
-        true
+
+        dominators
     }
 
 
@@ -248,6 +989,12 @@ impl ControlFlowGraph {
 
 impl fmt::Display for ControlFlowGraph {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for ControlFlowGraph {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
         let mut first = true;
         for node in &self.nodes {
             if (first) { first = false; }
@@ -260,9 +1007,9 @@ impl fmt::Display for ControlFlowGraph {
                 writeln!(f)?;
             } }
             if (node == &self.entry) {
-                writeln!(f, "  \x1b[92m\x1b[1m{}\x1b[0m", node)?;
+                writeln!(f, "  {}{}{}", theme.entry, node, theme.reset)?;
             } else {
-                writeln!(f, "  \x1b[97m\x1b[1m{}\x1b[0m", node)?;
+                writeln!(f, "  {}{}{}", theme.node, node, theme.reset)?;
             }
             if let Some(succs) = self.succs.get(node) { if (succs.len() > 0) {
                 write!(f, "  ↘_")?;
@@ -275,3 +1022,177 @@ impl fmt::Display for ControlFlowGraph {
         Ok(())
     }
 }
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use llvm_ir::ConstantRef;
+    use llvm_ir::instruction::Phi;
+    use llvm_ir::types::Types;
+    use llvm_ir::terminator::{
+        Br,
+        CondBr,
+        Ret,
+        Switch
+    };
+
+    /// Builds a graph from its edges, entered at the source of the first edge.
+    pub(crate) fn graph(edges : &[(&str, &str)]) -> ControlFlowGraph {
+        let mut cfg = ControlFlowGraph::with_entry(Name::from(edges[0].0));
+        for (from, to) in edges {
+            cfg.add_edge(Name::from(*from), Name::from(*to));
+        }
+        cfg
+    }
+
+    /// Builds a function from its blocks and their successors, entered at the first block.
+    /// 
+    /// A block with no successors returns, one with two branches on a condition to its true then false successor,
+    ///   and one with more switches over its successors, with the last as the default.
+    pub(crate) fn function(blocks : &[(&str, &[&str])]) -> Function {
+        let operand = |bits| Operand::ConstantOperand(ConstantRef::new(Constant::Int { bits, value : 0 }));
+        let mut function = Function::new("test");
+        for (name, succs) in blocks {
+            let mut block = BasicBlock::new(Name::from(*name));
+            block.term = match (succs) {
+                []                      => Terminator::Ret(Ret { return_operand : None, debugloc : None }),
+                [ dest ]                => Terminator::Br(Br { dest : Name::from(*dest), debugloc : None }),
+                [ t, f ]                => Terminator::CondBr(CondBr { condition : operand(1), true_dest : Name::from(*t), false_dest : Name::from(*f), debugloc : None }),
+                [ cases @ .., default ] => Terminator::Switch(Switch {
+                    operand      : operand(32),
+                    dests        : cases.iter().enumerate().map(|(i, dest)| (ConstantRef::new(Constant::Int { bits : 32, value : i as u64 }), Name::from(*dest))).collect(),
+                    default_dest : Name::from(*default),
+                    debugloc     : None
+                })
+            };
+            function.basic_blocks.push(block);
+        }
+        function
+    }
+
+    /// Gives the block `name` of `function` a `phi` into `dest`, with a constant from each predecessor.
+    pub(crate) fn add_phi(function : &mut Function, name : &str, dest : &str, incoming : &[(u64, &str)]) -> () {
+        let block = function.basic_blocks.iter_mut().find(|block| block.name == Name::from(name)).expect("block is not in the function");
+        block.instrs.push(Instruction::Phi(Phi {
+            incoming_values : incoming.iter().map(|(value, pred)| (Operand::ConstantOperand(ConstantRef::new(Constant::Int { bits : 32, value : *value })), Name::from(*pred))).collect(),
+            dest            : Name::from(dest),
+            to_type         : Types::blank_for_testing().i32(),
+            debugloc        : None
+        }));
+    }
+
+    /// Marks the block `name` as ending in an `asm goto`, which falls through to its first successor.
+    pub(crate) fn asm_goto(cfg : &mut ControlFlowGraph, name : &str) -> () {
+        let name    = Name::from(name);
+        let default = cfg.succs(&name).and_then(|succs| succs.get(0)).expect("block has no successors").from_pred().clone();
+        cfg.branches.insert(name, CFGBranch::CallBr { default });
+    }
+
+    /// Gets the node of the block named `name`.
+    pub(crate) fn node(name : &str) -> CFGNode {
+        Name::from(name).into()
+    }
+
+    /// A diamond feeding a loop, `a -> b | c -> d <-> e -> f`.
+    fn diamond_then_loop() -> ControlFlowGraph {
+        graph(&[ ("a", "b"), ("a", "c"), ("b", "d"), ("c", "d"), ("d", "e"), ("e", "d"), ("e", "f") ])
+    }
+
+    #[test]
+    fn dominates_diamond_then_loop() -> () {
+        let cfg = diamond_then_loop();
+        // Neither side of the diamond is on every path into the loop.
+        assert!(! cfg.dominates(node("b"), node("d")));
+        assert!(! cfg.dominates(node("c"), node("d")));
+        assert!(! cfg.dominates(node("b"), node("f")));
+        assert!(cfg.dominates(node("a"), node("d")));
+        assert!(cfg.dominates(node("a"), node("f")));
+        // The back edge does not make the end of the loop dominate its header.
+        assert!(cfg.dominates(node("d"), node("e")));
+        assert!(! cfg.dominates(node("e"), node("d")));
+        assert!(cfg.dominates(node("d"), node("f")));
+        assert!(cfg.dominates(node("e"), node("f")));
+    }
+
+    #[test]
+    fn dominates_self() -> () {
+        let cfg = diamond_then_loop();
+        for node in cfg.nodes() {
+            assert!(cfg.dominates(node, node));
+            assert!(! cfg.strictly_dominates(node, node));
+        }
+    }
+
+    #[test]
+    fn dominates_agrees_with_dominators_of() -> () {
+        let mut cfg = diamond_then_loop();
+        // An unreachable node branching into the loop.
+        cfg.add_edge(node("u"), node("d"));
+        for to in cfg.nodes() {
+            let dominators = cfg.dominators_of(to);
+            for through in cfg.nodes() {
+                assert_eq!(cfg.dominates(through, to), through == to || dominators.contains(through), "{} dominates {}", through, to);
+            }
+        }
+        assert!(! cfg.dominates(node("a"), node("u")));
+        assert!(cfg.immediate_dominator(node("f")) == Some(node("e")));
+        assert!(cfg.immediate_dominator(node("d")) == Some(node("a")));
+    }
+
+    #[test]
+    fn false_edge_through_temporary() -> () {
+        // `a -> b | c`, where `a` branches to `c` when false, and a temporary is then inserted before `c`.
+        let mut cfg = ControlFlowGraph::new(&function(&[ ("a", &[ "b", "c" ]), ("b", &[]), ("c", &[]) ]));
+        let temp    = CFGNode::from(cfg.create_temporary_node());
+        cfg.remove_edge(node("a"), node("c"));
+        cfg.add_edge(node("a"), temp.clone());
+        cfg.add_edge(temp.clone(), node("c"));
+        assert!(cfg.is_false_edge(node("a"), temp.clone()));
+        assert!(! cfg.is_false_edge(node("a"), node("b")));
+    }
+
+    #[test]
+    fn temporary_avoids_lone_entry() -> () {
+        // A graph with only its entry, named like the first temporary.
+        let taken   = Name::Name(Box::new(format!("@{}_TEMPORARY_0", crate::MODULE_NAME.to_uppercase())));
+        let mut cfg = ControlFlowGraph::with_entry(taken.clone());
+        assert!(cfg.create_temporary_node() != taken);
+    }
+
+    #[test]
+    fn merged_graph_keeps_branches() -> () {
+        // `a -> b`, with `x -> y | z` inlined between them.
+        let mut cfg = ControlFlowGraph::new(&function(&[ ("a", &[ "b" ]), ("b", &[]) ]));
+        let callee  = ControlFlowGraph::new(&function(&[ ("x", &[ "y", "z" ]), ("y", &[]), ("z", &[]) ]));
+        let renames = cfg.merge_graphs(&callee, node("a"), node("b"));
+        let [ x, y, z ] = [ "x", "y", "z" ].map(|name| renames[&Name::from(name)].clone());
+        let Some(CFGBranch::Cond { true_dest, false_dest }) = cfg.branch(&x) else { panic!("expected a conditional branch") };
+        assert!(true_dest == &y && false_dest == &z);
+        assert!(cfg.is_false_edge(x.clone(), z.clone()));
+        assert!(cfg.layout_index(&Name::from("b")) < cfg.layout_index(&x) && cfg.layout_index(&x) < cfg.layout_index(&z));
+    }
+
+    #[test]
+    fn coalesced_sinks_keep_branches() -> () {
+        // `a -> b | r2`, `b -> r1`, where `r1` and `r2` both only return.
+        let function = function(&[ ("a", &[ "b", "r2" ]), ("b", &[ "r1" ]), ("r1", &[]), ("r2", &[]) ]);
+        let mut cfg  = ControlFlowGraph::new(&function);
+        cfg.coalesce_identical_sinks(&function);
+        let exits    = cfg.exits();
+        let [ sink ] = exits.as_slice() else { panic!("expected the sinks to be coalesced") };
+        assert!(cfg.is_false_edge(node("a"), (*sink).clone()));
+        assert!(! cfg.is_false_edge(node("a"), node("b")));
+        let Some(CFGBranch::Cond { true_dest, false_dest }) = cfg.branch(&Name::from("a")) else { panic!("expected a conditional branch") };
+        assert!(true_dest == &Name::from("b") && false_dest == sink.from_pred());
+    }
+
+    #[test]
+    fn unreachable_branches_removed() -> () {
+        // `a -> b | c`, where `a` always branches to `c`, so the switch in `b` is never reached.
+        let cfg = ControlFlowGraph::new_simplified(&function(&[ ("a", &[ "b", "c" ]), ("b", &[ "c", "d", "e" ]), ("c", &[]), ("d", &[]), ("e", &[]) ]));
+        assert!(cfg.nodes().iter().all(|node| node != &self::node("b")));
+        assert!(cfg.branch(&Name::from("b")).is_none());
+    }
+
+}
@@ -1,13 +1,23 @@
 use super::*;
 
+use std::hash::{ Hash, Hasher };
+use std::sync::Arc;
+
 
 /// A single node on a `ControlFlowGraph`
-#[derive(Hash, PartialEq, Eq, Clone)]
+/// 
+/// The names are shared between clones of a node, so cloning a node does not allocate.
+/// 
+/// Nodes are identified by their names alone, so two nodes with the same names are equal even if their labels
+///   differ.
+#[derive(Clone)]
 pub struct CFGNode {
     /// When nodes are merged during CFA, this is the name of the entry node of the primitive.
-    from_pred : Name,
+    from_pred : Arc<Name>,
     /// When nodes are merged during CFA, this is the name of the exit node of the primitive.
-    to_succ : Name
+    to_succ : Arc<Name>,
+    /// A readable name shown by `Display` instead of the names, such as one taken from debug info.
+    label : Option<Arc<str>>
 }
 
 
@@ -17,11 +27,45 @@ impl CFGNode {
     /// 
     /// Generally, this constructor is unneeded as `ControlFlowGraph` automatically creates
     /// them in its `new` constructor.
-    pub fn new(from_pred : Name, to_succ : Name) -> Self { Self {
-        from_pred,
-        to_succ
+    pub fn new(from_pred : Name, to_succ : Name) -> Self {
+        if (from_pred == to_succ) {
+            let name = Arc::new(from_pred);
+            Self { from_pred : name.clone(), to_succ : name, label : None }
+        } else {
+            Self { from_pred : Arc::new(from_pred), to_succ : Arc::new(to_succ), label : None }
+        }
+    }
+
+    /// Create the node which a primitive from `entry` to `exit` is merged into, sharing their names.
+    pub(crate) fn between(entry : &CFGNode, exit : &CFGNode) -> Self { Self {
+        from_pred : entry.from_pred.clone(),
+        to_succ   : exit.to_succ.clone(),
+        label     : None
     } }
 
+    /// Create a node for a single block from its name, with or without the `%` prefix.
+    /// 
+    /// A name made only of digits is a numbered name, so `"%3"` is `Name::Number(3)` and `"%bb3"` is
+    ///   `Name::Name("bb3")`.
+    pub fn from_name_str(name : &str) -> Self {
+        (&name_from_str(name)).into()
+    }
+
+    /// Create a node merged from the block `from_pred` to the block `to_succ`, named the same way as
+    ///   `CFGNode::from_name_str`.
+    pub fn from_range_str(from_pred : &str, to_succ : &str) -> Self {
+        Self::new(name_from_str(from_pred), name_from_str(to_succ))
+    }
+
+    /// Gives the node a readable label, which `Display` shows instead of its names.
+    /// 
+    /// The label is only for display. The node is still identified by its names, so it stays equal to the
+    ///   unlabelled node. Use `ControlFlowGraph::set_label` to label a node which is already on a graph.
+    pub fn with_label<S : Into<String>>(mut self, label : S) -> Self {
+        self.label = Some(Arc::from(label.into()));
+        self
+    }
+
 
     /// When nodes are merged during CFA, this is the name of the entry node of the primitive.
     pub fn from_pred(&self) -> &Name { &self.from_pred }
@@ -29,23 +73,115 @@ impl CFGNode {
     /// When nodes are merged during CFA, this is the name of the exit node of the primitive.
     pub fn to_succ(&self) -> &Name { &self.to_succ }
 
+    /// The readable label of the node, if it has one.
+    pub fn label(&self) -> Option<&str> { self.label.as_deref() }
+
+
+    /// Formats the node as an identifier, containing only ASCII letters, digits, and underscores.
+    /// 
+    /// Names are written without their `%` prefix, and merged nodes are written as `entry__exit`. Any other
+    ///   characters are replaced with `_`, and the identifier is prefixed with `_` if it would start with a digit.
+    /// 
+    /// ```text
+    /// %bb1             ->    bb1
+    /// (%bb2...%bb4)    ->    bb2__bb4
+    /// %0               ->    _0
+    /// ```
+    pub fn to_ident(&self) -> String {
+        self.display_name("")
+    }
+
+    /// Formats the node as an identifier in the same way as `CFGNode::to_ident`, but with each numbered name
+    ///   written as `prefix` followed by its number.
+    /// 
+    /// Numbered names are common in LLVM IR, so this gives them a consistent prefix which makes them legal labels.
+    /// 
+    /// ```text
+    /// %3               ->    bb3
+    /// (%2...%entry)    ->    bb2__entry
+    /// %exit            ->    exit
+    /// ```
+    pub fn display_name(&self, prefix : &str) -> String {
+        let name = |name : &Name| match (name) {
+            Name::Name(name)   => name.chars().map(|c| if (c.is_ascii_alphanumeric()) { c } else { '_' }).collect::<String>(),
+            Name::Number(name) => format!("{}{}", prefix, name)
+        };
+        let ident = if (self.from_pred == self.to_succ) {
+            name(&self.from_pred)
+        } else {
+            format!("{}__{}", name(&self.from_pred), name(&self.to_succ))
+        };
+        if (ident.starts_with(|c : char| c.is_ascii_digit())) {
+            format!("_{}", ident)
+        } else { ident }
+    }
+
+}
+
+
+/// Parses a block name, as written by the `Display` implementation of `Name`.
+fn name_from_str(name : &str) -> Name {
+    let name = name.strip_prefix('%').unwrap_or(name);
+    match (name.parse::<usize>()) {
+        Ok(number) if (name.bytes().all(|c| c.is_ascii_digit())) => Name::Number(number),
+        _                                                       => Name::Name(Box::new(name.to_string()))
+    }
 }
 
 
 impl Into<CFGNode> for &Name {
-    fn into(self) -> CFGNode { CFGNode {
-        from_pred : self.clone(),
-        to_succ : self.clone()
-    } }
+    fn into(self) -> CFGNode {
+        let name = Arc::new(self.clone());
+        CFGNode {
+            from_pred : name.clone(),
+            to_succ   : name,
+            label     : None
+        }
+    }
 }
 
 impl Into<CFGNode> for &CFGNode {
     fn into(self) -> CFGNode { self.clone() }
 }
 
+/// Creates a node for a single block, taking its name rather than cloning it.
+impl From<Name> for CFGNode {
+    fn from(name : Name) -> Self {
+        let name = Arc::new(name);
+        Self {
+            from_pred : name.clone(),
+            to_succ   : name,
+            label     : None
+        }
+    }
+}
+
+/// Creates a node merged from the block `from_pred` to the block `to_succ`, as by `CFGNode::new`.
+impl From<(Name, Name)> for CFGNode {
+    fn from((from_pred, to_succ) : (Name, Name)) -> Self { Self::new(from_pred, to_succ) }
+}
+
+
+impl PartialEq for CFGNode {
+    fn eq(&self, other : &Self) -> bool {
+        self.from_pred == other.from_pred && self.to_succ == other.to_succ
+    }
+}
+impl Eq for CFGNode { }
+
+impl Hash for CFGNode {
+    fn hash<H : Hasher>(&self, state : &mut H) -> () {
+        self.from_pred.hash(state);
+        self.to_succ.hash(state);
+    }
+}
+
 
 impl fmt::Display for CFGNode {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(label) = &self.label {
+            return write!(f, "{}", label);
+        }
         let from_pred = self.from_pred();
         let to_succ   = self.to_succ();
         if (from_pred == to_succ) {
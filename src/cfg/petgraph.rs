@@ -0,0 +1,50 @@
+use super::*;
+
+use ::petgraph::graph::{
+    Graph,
+    NodeIndex
+};
+
+
+impl ControlFlowGraph {
+
+
+    /// Converts the graph to a `petgraph::Graph`, along with the index of each node in it.
+    /// 
+    /// Nodes are added in the order of `nodes`, and the edges of each node in the order of its successors. The
+    ///   entry node can be found in the returned indices by looking up `entry`.
+    pub fn to_petgraph(&self) -> (Graph<CFGNode, ()>, HashMap<CFGNode, NodeIndex>) {
+        let mut graph   = Graph::with_capacity(self.nodes.len(), 0);
+        let mut indices = HashMap::new();
+        for node in &self.nodes {
+            indices.insert(node.clone(), graph.add_node(node.clone()));
+        }
+        for from in &self.nodes {
+            let Some(succs) = self.succs.get(from) else { continue };
+            for to in succs {
+                graph.add_edge(indices[from], indices[to], ());
+            }
+        }
+        (graph, indices)
+    }
+
+    /// Creates a graph from a `petgraph::Graph`, with the node at `entry` as its entry node.
+    /// 
+    /// Nodes are kept in the order of their indices. A `petgraph::Graph` does not know which edge of a conditional
+    ///   is taken when it is true, or which nodes are temporary, so the graph is built like
+    ///   `ControlFlowGraph::with_entry` and none of them are kept. Duplicate edges are merged.
+    /// 
+    /// Panics if `entry` is not in `graph`.
+    pub fn from_petgraph(graph : &Graph<CFGNode, ()>, entry : NodeIndex) -> Self {
+        let mut cfg = Self::with_entry(&graph[entry]);
+        for index in graph.node_indices() {
+            cfg.nodes.insert(graph[index].clone());
+        }
+        for edge in graph.raw_edges() {
+            cfg.add_edge(&graph[edge.source()], &graph[edge.target()]);
+        }
+        cfg
+    }
+
+
+}
@@ -0,0 +1,113 @@
+use super::*;
+
+
+impl ControlFlowGraph {
+
+    /// Get the strongly connected components of the graph, in topological order.
+    /// 
+    /// Every node is in exactly one component, and a node which is not in any cycle is a component on its own.
+    ///   Nodes in each component are in the same order as `nodes`.
+    /// 
+    /// Relevant information: [Tarjan's strongly connected components algorithm](https://en.wikipedia.org/wiki/Tarjan%27s_strongly_connected_components_algorithm)
+    pub fn sccs(&self) -> Vec<Vec<CFGNode>> {
+        let position = self.nodes.iter().enumerate().map(|(i, node)| (node, i)).collect::<HashMap<_, _>>();
+        // The order each node was first visited in, and the lowest of those reachable from it on the stack.
+        let mut index    : HashMap<&CFGNode, usize> = HashMap::new();
+        let mut lowlink  : HashMap<&CFGNode, usize> = HashMap::new();
+        let mut stack    = Vec::new();
+        let mut on_stack = HashSet::new();
+        let mut out      = Vec::new();
+
+        for start in &self.nodes {
+            if (index.contains_key(start)) { continue; }
+            // Each entry is a node, and the index of the next successor to visit.
+            let mut work = vec![ (start, 0) ];
+            while let Some((node, next)) = work.pop() {
+                if (next == 0) {
+                    index.insert(node, index.len());
+                    lowlink.insert(node, lowlink.len());
+                    stack.push(node);
+                    on_stack.insert(node);
+                }
+                let succ = self.succs.get(node).and_then(|succs| succs.get(next));
+                if let Some(succ) = succ {
+                    work.push((node, next + 1));
+                    if (! index.contains_key(succ)) {
+                        work.push((succ, 0));
+                    } else if (on_stack.contains(succ)) {
+                        let low = lowlink[node].min(index[succ]);
+                        lowlink.insert(node, low);
+                    }
+                    continue;
+                }
+                // Every successor has been visited, so pass the lowlink up to the parent.
+                if let Some((parent, _)) = work.last() {
+                    let low = lowlink[parent].min(lowlink[node]);
+                    lowlink.insert(parent, low);
+                }
+                if (lowlink[node] == index[node]) {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(member);
+                        component.push(member.clone());
+                        if (member == node) { break; }
+                    }
+                    component.sort_by_key(|member| position.get(member).copied().unwrap_or(usize::MAX));
+                    out.push(component);
+                }
+            }
+        }
+
+        // Components are completed after everything they lead to.
+        out.reverse();
+        out
+    }
+
+    /// Get the condensation of the graph, where each strongly connected component is collapsed into a single node,
+    ///   along with the members of each of those nodes.
+    /// 
+    /// The condensation has no cycles, so it gives an overview of the loop regions of the graph. A component of
+    ///   one node is kept as that node, and a larger component becomes a node merged from its first member to its
+    ///   last, the same way as a primitive is merged during CFA. Edges within a component are dropped.
+    /// 
+    /// ```text
+    /// %start                  %start
+    /// ↘_ %bb1                 ↘_ (%bb1...%bb6)
+    ///                  ->
+    /// ↙‾ %start %bb6 %bb5     ↙‾ %start
+    /// %bb1                    (%bb1...%bb6)
+    /// ↘_ %bb2 %bb7            ↘_ %bb7
+    /// ...                     ...
+    /// ```
+    pub fn condensation(&self) -> (ControlFlowGraph, HashMap<CFGNode, Vec<CFGNode>>) {
+        let sccs = self.sccs();
+
+        let mut supers = HashMap::new();
+        let mut members = HashMap::new();
+        for component in sccs {
+            let first = &component[0];
+            let last = &component[component.len() - 1];
+            let node = if (component.len() == 1) { first.clone() } else { CFGNode::between(first, last) };
+            for member in &component {
+                supers.insert(member.clone(), node.clone());
+            }
+            members.insert(node, component);
+        }
+
+        let mut cfg = ControlFlowGraph::with_entry(&supers[&self.entry]);
+        for node in &self.nodes {
+            cfg.nodes.insert(supers[node].clone());
+        }
+        for from in &self.nodes {
+            let Some(succs) = self.succs.get(from) else { continue };
+            for to in succs {
+                let (from, to) = (&supers[from], &supers[to]);
+                if (from != to) {
+                    cfg.add_edge(from, to);
+                }
+            }
+        }
+        (cfg, members)
+    }
+
+}
@@ -0,0 +1,92 @@
+use super::*;
+
+
+/// A summary of the shape of a `ControlFlowGraph`.
+/// 
+/// Created by `ControlFlowGraph::stats`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CfgStats {
+    pub nodes        : usize,
+    pub edges        : usize,
+    /// The number of nodes with exactly two successors.
+    pub conditionals : usize,
+    /// The number of nodes with more than two successors.
+    pub switches     : usize,
+    pub back_edges   : usize,
+    /// The number of nodes with no successors.
+    pub exits        : usize,
+    pub reducible    : bool
+}
+
+
+impl ControlFlowGraph {
+
+    /// Get a summary of the shape of the graph, such as for logging each function in a batch.
+    pub fn stats(&self) -> CfgStats {
+        let out_degree = |node : &CFGNode| self.succs.get(node).map(|succs| succs.len()).unwrap_or(0);
+        CfgStats {
+            nodes        : self.nodes.len(),
+            edges        : self.nodes.iter().map(out_degree).sum(),
+            conditionals : self.nodes.iter().filter(|node| out_degree(node) == 2).count(),
+            switches     : self.nodes.iter().filter(|node| out_degree(node) > 2).count(),
+            back_edges   : self.back_edges().len(),
+            exits        : self.exits().len(),
+            reducible    : self.is_reducible()
+        }
+    }
+
+    /// Returns `true` if the graph is reducible, meaning every cycle is a loop entered only through its header.
+    /// 
+    /// A graph is reducible if removing its back edges leaves no cycles. Back edges are found from the dominators,
+    ///   as by `ControlFlowGraph::back_edges`, so a cycle entered other than through a node which dominates the
+    ///   rest of it has none. Nodes which can not be reached from the entry are never run, so they are ignored.
+    /// 
+    /// This is much cheaper than finding out from `CFAPrim::find_all` failing, which is what an irreducible graph
+    ///   does, so it can be used to pick which functions to run through `CFAPrim::find_all_partial` instead.
+    pub fn is_reducible(&self) -> bool {
+        self.irreducible_regions().is_empty()
+    }
+
+    /// Get every cycle of the graph which is not a loop, as it is entered through more than one of its nodes.
+    /// 
+    /// Each region is a strongly connected component left after removing the back edges, as by
+    ///   `ControlFlowGraph::is_reducible`, in topological order. Only regions which can be reached from the entry
+    ///   are included, as unreachable nodes have no dominators to find back edges from.
+    pub(crate) fn irreducible_regions(&self) -> Vec<Vec<CFGNode>> {
+        let mut forward = self.clone();
+        for (from, to) in self.back_edges() {
+            forward.remove_edge(from, to);
+        }
+        forward.sccs().into_iter()
+            .filter(|component| component.len() > 1 && component.iter().all(|node| self.dominates(&self.entry, node)))
+            .collect()
+    }
+
+}
+
+
+impl fmt::Display for CfgStats {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} nodes, {} edges, {} conditionals, {} switches, {} back edges, {} exits, {}",
+            self.nodes, self.edges, self.conditionals, self.switches, self.back_edges, self.exits,
+            if (self.reducible) { "reducible" } else { "irreducible" }
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::tests::graph;
+
+    #[test]
+    fn reducible_ignores_unreachable() -> () {
+        // `a -> b | c`, where `b` and `c` loop into each other, entered through both.
+        let cfg = graph(&[ ("a", "b"), ("a", "c"), ("b", "c"), ("c", "b") ]);
+        assert!(! cfg.is_reducible());
+        // `a -> b`, with the same cycle between `u` and `v`, which are never reached.
+        let cfg = graph(&[ ("a", "b"), ("u", "v"), ("v", "u") ]);
+        assert!(cfg.is_reducible());
+    }
+
+}
@@ -0,0 +1,31 @@
+use super::*;
+
+
+impl ControlFlowGraph {
+
+    /// Checks that the graph is consistent, or gets a description of the first problem found.
+    /// 
+    /// A consistent graph has its entry in `nodes`, every node mentioned by an edge in `nodes`, and every edge
+    ///   recorded as both a successor of its source and a predecessor of its target. `add_edge`, `remove_edge`,
+    ///   `remove_node`, and `insert_node` all keep the edges consistent, so a problem with them means a bug in
+    ///   whatever edited the graph. Removing the entry with `remove_node` leaves the entry missing, until another
+    ///   is set with `set_entry_checked`.
+    /// 
+    /// Useful after editing a graph by hand, since an inconsistent graph otherwise only shows up later as a
+    ///   failure to reduce or recover it.
+    pub fn validate(&self) -> Result<(), String> {
+        if (! self.nodes.contains(&self.entry)) { return Err(format!("entry {} is not in the graph", self.entry)); }
+        for (edges, other_edges, other) in [ (&self.succs, &self.preds, "predecessor"), (&self.preds, &self.succs, "successor") ] {
+            for (node, others) in edges {
+                if (! self.nodes.contains(node)) { return Err(format!("{} has edges but is not in the graph", node)); }
+                for other_node in others {
+                    if (! self.nodes.contains(other_node)) { return Err(format!("{} is connected to {} which is not in the graph", node, other_node)); }
+                    let mirrored = other_edges.get(other_node).map(|other_others| other_others.contains(node)).unwrap_or(false);
+                    if (! mirrored) { return Err(format!("{} is not recorded as a {} of {}", node, other, other_node)); }
+                }
+            }
+        }
+        Ok(())
+    }
+
+}
@@ -0,0 +1,105 @@
+use super::*;
+
+
+impl CFRGroups {
+
+
+    /// Lists every block in the tree, in the order they are printed by `Display`.
+    /// 
+    /// Only blocks are listed, including those which end in a call that never returns or in `unreachable`. Useful
+    ///   for writing out the instructions of each block alongside the recovered structure.
+    pub fn blocks_in_order(&self) -> Vec<Name> {
+        let mut out = Vec::new();
+        self.for_each_block(&mut Vec::new(), &mut |name, _| out.push(name.clone()));
+        out
+    }
+
+
+    /// Lists every block in the tree, in the order they appear, with the path to the group which contains it.
+    /// 
+    /// The path is written the same way as `StructuralDiff::path`, joined with arrows. Blocks which are not nested
+    ///   in any group have the path `top-level`. Useful for spotting a block which was recovered into the wrong
+    ///   place.
+    /// 
+    /// ```text
+    /// %start    top-level
+    /// %bb1      #1 while → condition
+    /// %bb2      #1 while → body → #0 if → condition
+    /// %bb3      #1 while → body → #0 if → body
+    /// %bb4      #1 while → body → #1 if-else → condition
+    /// %bb5      #1 while → body → #1 if-else → then
+    /// %bb6      #1 while → body → #1 if-else → else
+    /// ...
+    /// ```
+    pub fn block_map(&self) -> Vec<(Name, String)> {
+        let mut out = Vec::new();
+        self.for_each_block(&mut Vec::new(), &mut |name, path| {
+            let breadcrumb = if (path.is_empty()) { "top-level".to_string() } else { path.join(" → ") };
+            out.push((name.clone(), breadcrumb));
+        });
+        out
+    }
+
+
+    /// Calls `f` with every block in the tree, in the order they are printed by `Display`, along with the path to
+    ///   the group which contains it, starting from `path`.
+    pub(crate) fn for_each_block<F : FnMut(&Name, &Vec<String>)>(&self, path : &mut Vec<String>, f : &mut F) -> () {
+        for (i, group) in self.groups.iter().enumerate() {
+            group.for_each_block(i, path, f);
+        }
+    }
+
+
+}
+
+
+impl CFRGroup {
+
+    /// Calls `f` with every block in this group, the same as `CFRGroups::for_each_block`, where `index` is the
+    ///   position of this group among its siblings.
+    pub(crate) fn for_each_block<F : FnMut(&Name, &Vec<String>)>(&self, index : usize, path : &mut Vec<String>, f : &mut F) -> () {
+        match (self) {
+            Self::Block(name) | Self::NoReturnCall { name, .. } | Self::Unreachable(name) | Self::AsmGoto { name, .. } => f(name, path),
+            Self::Unstructured { blocks, .. } => {
+                path.push(format!("#{} {}", index, self.kind()));
+                for name in blocks {
+                    f(name, path);
+                }
+                path.pop();
+            },
+            _ => for (role, child) in self.children() {
+                path.push(format!("#{} {}", index, self.kind()));
+                path.push(role.to_string());
+                child.for_each_block(path, f);
+                path.pop();
+                path.pop();
+            }
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(name : &str) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Block(Name::from(name)) ] }
+    }
+
+    #[test]
+    fn for_loop_step_before_body() -> () {
+        // `a for (i; c; s) { b } x`, printed with its step before its body.
+        let groups = CFRGroups { groups : vec![
+            CFRGroup::Block(Name::from("a")),
+            CFRGroup::ForLoop { init : block("i"), cond : block("c"), step : block("s"), body : block("b"), negated : false },
+            CFRGroup::Block(Name::from("x"))
+        ] };
+        let order = [ "a", "i", "c", "s", "b", "x" ].map(Name::from);
+        assert!(groups.blocks_in_order() == order);
+        assert!(groups.block_map().into_iter().map(|(name, _)| name).collect::<Vec<_>>() == order);
+        assert!(groups.block_map()[3].1 == "#1 for → step");
+    }
+
+}
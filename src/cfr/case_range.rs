@@ -0,0 +1,80 @@
+use super::*;
+
+
+/// A run of consecutive `switch` case values, from `start` to `end` inclusive.
+/// 
+/// A single value is a range where `start` and `end` are equal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CFRCaseRange {
+    pub start : CFGCaseValue,
+    pub end   : CFGCaseValue
+}
+
+
+impl CFRCaseRange {
+
+    /// Returns `true` if the range holds only one value.
+    pub fn is_single(&self) -> bool { self.start == self.end }
+
+}
+
+
+impl CFRSwitchCase {
+
+    /// Gets the values of this arm in order, with each run of three or more consecutive values collapsed into a
+    ///   single range.
+    /// 
+    /// Values are ordered the same way they are displayed, so signed except for single bit integers. Runs of two
+    ///   are left as two values, as `case 1, 2:` is easier to read than `case 1 ... 2:`.
+    pub fn ranges(&self) -> Vec<CFRCaseRange> { collapse(&self.values) }
+
+}
+
+
+impl CFRSwitchLoopCase {
+
+    /// Gets the values of this arm in order, with each run of three or more consecutive values collapsed into a
+    ///   single range, as by `CFRSwitchCase::ranges`.
+    pub fn ranges(&self) -> Vec<CFRCaseRange> { collapse(&self.values) }
+
+}
+
+
+/// Sorts `values` into display order, and collapses each run of three or more consecutive values into a range.
+fn collapse(values : &[CFGCaseValue]) -> Vec<CFRCaseRange> {
+    let mut values = values.to_vec();
+    values.sort_by_key(ordinal);
+
+    let mut out : Vec<CFRCaseRange> = Vec::new();
+    let mut i = 0;
+    while (i < values.len()) {
+        let mut j = i;
+        while (j + 1 < values.len() && values[j + 1].bits() == values[i].bits() && ordinal(&values[j + 1]) == ordinal(&values[j]) + 1) {
+            j += 1;
+        }
+        if (j - i >= 2) {
+            out.push(CFRCaseRange { start : values[i], end : values[j] });
+        } else {
+            out.extend(values[i..=j].iter().map(|&value| CFRCaseRange { start : value, end : value }));
+        }
+        i = j + 1;
+    }
+    out
+}
+
+
+/// Gets the position of `value` in display order.
+fn ordinal(value : &CFGCaseValue) -> i128 {
+    if (value.bits() == 1) { value.as_unsigned() as i128 } else { value.as_signed() as i128 }
+}
+
+
+impl fmt::Display for CFRCaseRange {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        if (self.is_single()) {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{} ... {}", self.start, self.end)
+        }
+    }
+}
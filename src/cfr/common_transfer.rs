@@ -0,0 +1,170 @@
+use super::*;
+
+
+impl CFRGroups {
+
+
+    /// Factors the `return` or `break` out of both branches of a conditional, where both of them leave the same
+    ///   way.
+    /// 
+    /// The body of a `CFRGroup::OnewayReturnConditional` ends in a `return`, so a
+    ///   `CFRGroup::OnewayReturnConditional` inside of it returns the same way as the groups after it. It is
+    ///   replaced with a `CFRGroup::TwowayConditional` holding the groups after it, or a
+    ///   `CFRGroup::OnewayConditional` if there are none, leaving a single `return` at the end.
+    /// 
+    /// ```text
+    /// if (                     if (
+    ///   %bb1                     %bb1
+    /// ) {                      ) {
+    ///   if (                     if (
+    ///     %bb2                     %bb2
+    ///   ) {           ->         ) {
+    ///     %bb3                     %bb3
+    ///     return;                } else {
+    ///   }                          %bb4
+    ///   %bb4                     }
+    ///   return;                  return;
+    /// }                        }
+    /// ```
+    /// 
+    /// A `break` is factored out the same way, from a `CFRGroup::BreakConditional` inside the body of another
+    ///   one, or inside an arm of a `CFRGroup::SwitchLoop` which leaves the loop. Only the groups directly in the
+    ///   body are checked, as a `break` inside a nested loop leaves that loop instead. A `continue` is only ever
+    ///   written once, at the end of an arm of a `CFRGroup::SwitchLoop`, so there is none to factor out.
+    /// 
+    /// Bodies which end in a `CFRGroup::NoReturnCall` or `CFRGroup::Unreachable` do not return, so their
+    ///   `return`s are left alone.
+    pub fn factor_common_transfers(&mut self) -> () {
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut CommonTransfers);
+    }
+
+
+}
+
+
+/// A way of leaving the groups after a conditional, which both of its branches can share.
+#[derive(Clone, Copy, PartialEq)]
+enum Transfer {
+    Return,
+    Break
+}
+
+
+/// Factors out `return`s and `break`s repeated in both branches of a conditional.
+struct CommonTransfers;
+
+impl CommonTransfers {
+
+    /// Factors the `transfer`s out of `body`, which is followed by a `transfer`.
+    fn factor(mut body : CFRGroups, transfer : Transfer) -> CFRGroups {
+        // A body which does not return has no `return` at the end to share.
+        if (transfer == Transfer::Return && body.diverges()) { return body; }
+        let at = body.groups.iter().position(|group| match (group) {
+            CFRGroup::OnewayReturnConditional { body, .. } => transfer == Transfer::Return && ! body.diverges(),
+            CFRGroup::BreakConditional { .. }              => transfer == Transfer::Break,
+            _                                              => false
+        });
+        let Some(at) = at else { return body };
+
+        let rest = Self::factor(CFRGroups { groups : body.groups.split_off(at + 1) }, transfer);
+        let (cond, inner, negated) = match (body.groups.pop()) {
+            Some(CFRGroup::OnewayReturnConditional { cond, body, negated } | CFRGroup::BreakConditional { cond, body, negated }) => (cond, body, negated),
+            group => {
+                body.groups.extend(group);
+                body.groups.extend(rest.groups);
+                return body;
+            }
+        };
+        body.groups.push(if (rest.groups.is_empty()) {
+            CFRGroup::OnewayConditional { cond, body : inner, negated }
+        } else if (negated) {
+            CFRGroup::TwowayConditional { cond, body_true : rest, body_false : inner }
+        } else {
+            CFRGroup::TwowayConditional { cond, body_true : inner, body_false : rest }
+        });
+        body
+    }
+
+}
+
+impl CfrVisitor for CommonTransfers {
+
+    fn visit_oneway_return_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        let body = Self::factor(body, Transfer::Return);
+        CFRGroups { groups : vec![ CFRGroup::OnewayReturnConditional { cond, body, negated } ] }
+    }
+
+    fn visit_break_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        let body = Self::factor(body, Transfer::Break);
+        CFRGroups { groups : vec![ CFRGroup::BreakConditional { cond, body, negated } ] }
+    }
+
+    fn visit_switch_loop(&mut self, cond : CFRGroups, cases : Vec<CFRSwitchLoopCase>, default : CFRSwitchLoopCase, step : CFRGroups) -> CFRGroups {
+        let mut factor_arm = |case : CFRSwitchLoopCase| {
+            let body = self.visit_groups(case.body);
+            let body = if (case.breaks) { Self::factor(body, Transfer::Break) } else { body };
+            CFRSwitchLoopCase { body, ..case }
+        };
+        let cases   = cases.into_iter().map(&mut factor_arm).collect();
+        let default = factor_arm(default);
+        let cond    = self.visit_groups(cond);
+        let step    = self.visit_groups(step);
+        CFRGroups { groups : vec![ CFRGroup::SwitchLoop { cond, cases, default, step } ] }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(name : &str) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Block(Name::from(name)) ] }
+    }
+
+    #[test]
+    fn returns_factored() -> () {
+        // `if (a) { if (b) { c return; } d return; }`
+        let inner      = CFRGroup::OnewayReturnConditional { cond : block("b"), body : block("c"), negated : false };
+        let mut groups = CFRGroups { groups : vec![ CFRGroup::OnewayReturnConditional {
+            cond    : block("a"),
+            body    : CFRGroups { groups : vec![ inner, CFRGroup::Block(Name::from("d")) ] },
+            negated : false
+        } ] };
+        groups.factor_common_transfers();
+        let [ CFRGroup::OnewayReturnConditional { body, .. } ] = groups.groups.as_slice() else { panic!("expected an if-return, found\n{}", groups) };
+        assert!(body.groups == vec![ CFRGroup::TwowayConditional { cond : block("b"), body_true : block("c"), body_false : block("d") } ]);
+    }
+
+    #[test]
+    fn breaks_factored() -> () {
+        // `loop { if (a) { if (! b) { c break; } d break; } }`
+        let inner      = CFRGroup::BreakConditional { cond : block("b"), body : block("c"), negated : true };
+        let mut groups = CFRGroups { groups : vec![ CFRGroup::InfiniteLoop { body : CFRGroups { groups : vec![ CFRGroup::BreakConditional {
+            cond    : block("a"),
+            body    : CFRGroups { groups : vec![ inner, CFRGroup::Block(Name::from("d")) ] },
+            negated : false
+        } ] } } ] };
+        groups.factor_common_transfers();
+        let [ CFRGroup::InfiniteLoop { body } ] = groups.groups.as_slice() else { panic!("expected a loop, found\n{}", groups) };
+        let [ CFRGroup::BreakConditional { body, .. } ] = body.groups.as_slice() else { panic!("expected an if-break, found\n{}", groups) };
+        assert!(body.groups == vec![ CFRGroup::TwowayConditional { cond : block("b"), body_true : block("d"), body_false : block("c") } ]);
+    }
+
+    #[test]
+    fn breaks_in_nested_loop_kept() -> () {
+        // `loop { if (a) { loop { if (b) { c break; } } d break; } }`, where the inner break leaves the inner loop.
+        let nested     = CFRGroup::InfiniteLoop { body : CFRGroups { groups : vec![ CFRGroup::BreakConditional { cond : block("b"), body : block("c"), negated : false } ] } };
+        let body       = CFRGroups { groups : vec![ nested, CFRGroup::Block(Name::from("d")) ] };
+        let mut groups = CFRGroups { groups : vec![ CFRGroup::BreakConditional { cond : block("a"), body : body.clone(), negated : false } ] };
+        groups.factor_common_transfers();
+        assert!(groups.groups == vec![ CFRGroup::BreakConditional { cond : block("a"), body, negated : false } ]);
+    }
+
+}
@@ -0,0 +1,247 @@
+use super::*;
+
+use crate::induction::inverted;
+
+use llvm_ir::{
+    Constant,
+    ConstantRef,
+    FPPredicate,
+    Function,
+    Instruction,
+    IntPredicate,
+    Terminator
+};
+
+
+/// The condition of a recovered group, as an expression which can be negated.
+/// 
+/// Built from the operands of the branches in the condition of a group, by `CFRGroup::condition` or
+///   `CFRGroups::to_condition`. Comparisons are kept as leaves, so that negating one inverts its predicate rather
+///   than wrapping it in `Condition::Not`.
+/// 
+/// ```text
+/// %x slt %n && !%flag
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub enum Condition {
+
+    /// A boolean value which is not a comparison, such as an argument or the result of a call.
+    Value(Operand),
+
+    /// An `icmp` between two values.
+    IntCompare {
+        predicate : IntPredicate,
+        lhs       : Operand,
+        rhs       : Operand
+    },
+
+    /// An `fcmp` between two values.
+    FloatCompare {
+        predicate : FPPredicate,
+        lhs       : Operand,
+        rhs       : Operand
+    },
+
+    Not(Box<Condition>),
+
+    /// True when both conditions are, where the second is only checked if the first is true.
+    And(Box<Condition>, Box<Condition>),
+
+    /// True when either condition is, where the second is only checked if the first is false.
+    Or(Box<Condition>, Box<Condition>)
+
+}
+
+
+impl Condition {
+
+    /// Gets the condition which is true exactly when this one is false.
+    /// 
+    /// Comparisons have their predicate inverted, double negations are removed, and `And` and `Or` are swapped
+    ///   by De Morgan's laws, so `Condition::Not` is only ever left around a `Condition::Value`.
+    pub fn negate(self) -> Self {
+        match (self) {
+            Self::Value(value)                         => Self::Not(Box::new(Self::Value(value))),
+            Self::IntCompare { predicate, lhs, rhs }   => Self::IntCompare { predicate : inverted(predicate), lhs, rhs },
+            Self::FloatCompare { predicate, lhs, rhs } => Self::FloatCompare { predicate : inverted_fp(predicate), lhs, rhs },
+            Self::Not(inner)                           => *inner,
+            Self::And(lhs, rhs)                        => Self::Or(Box::new(lhs.negate()), Box::new(rhs.negate())),
+            Self::Or(lhs, rhs)                         => Self::And(Box::new(lhs.negate()), Box::new(rhs.negate()))
+        }
+    }
+
+    /// Gets the condition the block `name` of `function` branches on, or `None` if it does not end in a
+    ///   conditional `br`.
+    fn from_block(function : &Function, name : &Name) -> Option<Self> {
+        let block = function.basic_blocks.iter().find(|block| &block.name == name)?;
+        let Terminator::CondBr(term) = &block.term else { return None };
+        let Operand::LocalOperand { name : condition, .. } = &term.condition else {
+            return Some(Self::Value(term.condition.clone()));
+        };
+        let compare = block.instrs.iter().find_map(|instr| match (instr) {
+            Instruction::ICmp(icmp) if (&icmp.dest == condition) => Some(Self::IntCompare {
+                predicate : icmp.predicate,
+                lhs       : icmp.operand0.clone(),
+                rhs       : icmp.operand1.clone()
+            }),
+            Instruction::FCmp(fcmp) if (&fcmp.dest == condition) => Some(Self::FloatCompare {
+                predicate : fcmp.predicate,
+                lhs       : fcmp.operand0.clone(),
+                rhs       : fcmp.operand1.clone()
+            }),
+            _ => None
+        });
+        Some(compare.unwrap_or_else(|| Self::Value(term.condition.clone())))
+    }
+
+    /// Formats the condition, wrapping it in parentheses if it is an `Or` inside of an `And`, or anything but a
+    ///   value inside of a `Not`.
+    fn fmt_nested(&self, f : &mut fmt::Formatter<'_>, parenthesize : bool) -> fmt::Result {
+        if (parenthesize) { write!(f, "({})", self) } else { write!(f, "{}", self) }
+    }
+
+}
+
+
+impl CFRGroups {
+
+    /// Gets the condition these groups branch on, when they are the condition of another group.
+    /// 
+    /// Returns `None` unless the groups are a single block which ends in a conditional `br`, a lone
+    ///   `CFRGroup::CaseTest`, or a `CFRGroup::ShortCircuit` made of them. A condition which runs other blocks first is written as a block
+    ///   expression instead, so has no `Condition`.
+    pub fn to_condition(&self, function : &Function) -> Option<Condition> {
+        match (self.groups.as_slice()) {
+            [ CFRGroup::Block(name) ] => Condition::from_block(function, name),
+            [ CFRGroup::CaseTest { value, values } ] => values.iter()
+                .map(|case| Condition::IntCompare {
+                    predicate : IntPredicate::EQ,
+                    lhs       : value.clone(),
+                    rhs       : Operand::ConstantOperand(ConstantRef::new(Constant::Int { bits : case.bits(), value : case.as_unsigned() }))
+                })
+                .reduce(|lhs, rhs| Condition::Or(Box::new(lhs), Box::new(rhs))),
+            [ CFRGroup::ShortCircuit { lhs, rhs, disjunction, negated } ] => {
+                let lhs = lhs.to_condition(function)?;
+                let lhs = if (*negated) { lhs.negate() } else { lhs };
+                let rhs = rhs.to_condition(function)?;
+                Some(if (*disjunction) {
+                    Condition::Or(Box::new(lhs), Box::new(rhs))
+                } else {
+                    Condition::And(Box::new(lhs), Box::new(rhs))
+                })
+            },
+            _ => None
+        }
+    }
+
+}
+
+
+impl CFRGroup {
+
+    /// Gets the condition which decides whether the body of this group is run, with the negation of the group
+    ///   applied.
+    /// 
+    /// For a `CFRGroup::TwowayConditional` or `CFRGroup::Select` this is the condition of the `then` side, and
+    ///   for a `CFRGroup::PostconditionLoop` it is the condition to keep looping. Returns `None` for groups
+    ///   without a condition, and as by `CFRGroups::to_condition`.
+    pub fn condition(&self, function : &Function) -> Option<Condition> {
+        match (self) {
+            Self::PreconditionLoop        { cond, negated, .. }
+            | Self::ForLoop                 { cond, negated, .. }
+            | Self::OnewayConditional       { cond, negated, .. }
+            | Self::OnewayReturnConditional { cond, negated, .. }
+            | Self::BreakConditional        { cond, negated, .. } => {
+                let condition = cond.to_condition(function)?;
+                Some(if (*negated) { condition.negate() } else { condition })
+            },
+            Self::PostconditionLoop   { cond, .. }
+            | Self::TwowayConditional { cond, .. }
+            | Self::Select            { cond, .. } => cond.to_condition(function),
+            _ => None
+        }
+    }
+
+}
+
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::Value(value)                         => write!(f, "{}", value),
+            Self::IntCompare { predicate, lhs, rhs }   => write!(f, "{} {} {}", lhs, int_mnemonic(*predicate), rhs),
+            Self::FloatCompare { predicate, lhs, rhs } => write!(f, "{} {} {}", lhs, fp_mnemonic(*predicate), rhs),
+            Self::Not(inner) => {
+                write!(f, "!")?;
+                inner.fmt_nested(f, ! matches!(**inner, Self::Value(_)))
+            },
+            Self::And(lhs, rhs) => {
+                lhs.fmt_nested(f, matches!(**lhs, Self::Or(..)))?;
+                write!(f, " && ")?;
+                rhs.fmt_nested(f, matches!(**rhs, Self::Or(..)))
+            },
+            Self::Or(lhs, rhs) => write!(f, "{} || {}", lhs, rhs)
+        }
+    }
+}
+
+
+/// Gets the predicate which gives the opposite result, including for unordered values such as NaN.
+fn inverted_fp(predicate : FPPredicate) -> FPPredicate {
+    match (predicate) {
+        FPPredicate::False => FPPredicate::True,
+        FPPredicate::OEQ   => FPPredicate::UNE,
+        FPPredicate::OGT   => FPPredicate::ULE,
+        FPPredicate::OGE   => FPPredicate::ULT,
+        FPPredicate::OLT   => FPPredicate::UGE,
+        FPPredicate::OLE   => FPPredicate::UGT,
+        FPPredicate::ONE   => FPPredicate::UEQ,
+        FPPredicate::ORD   => FPPredicate::UNO,
+        FPPredicate::UNO   => FPPredicate::ORD,
+        FPPredicate::UEQ   => FPPredicate::ONE,
+        FPPredicate::UGT   => FPPredicate::OLE,
+        FPPredicate::UGE   => FPPredicate::OLT,
+        FPPredicate::ULT   => FPPredicate::OGE,
+        FPPredicate::ULE   => FPPredicate::OGT,
+        FPPredicate::UNE   => FPPredicate::OEQ,
+        FPPredicate::True  => FPPredicate::False
+    }
+}
+
+/// Gets the name of an `icmp` predicate, as written in LLVM IR.
+fn int_mnemonic(predicate : IntPredicate) -> &'static str {
+    match (predicate) {
+        IntPredicate::EQ  => "eq",
+        IntPredicate::NE  => "ne",
+        IntPredicate::UGT => "ugt",
+        IntPredicate::UGE => "uge",
+        IntPredicate::ULT => "ult",
+        IntPredicate::ULE => "ule",
+        IntPredicate::SGT => "sgt",
+        IntPredicate::SGE => "sge",
+        IntPredicate::SLT => "slt",
+        IntPredicate::SLE => "sle"
+    }
+}
+
+/// Gets the name of an `fcmp` predicate, as written in LLVM IR.
+fn fp_mnemonic(predicate : FPPredicate) -> &'static str {
+    match (predicate) {
+        FPPredicate::False => "false",
+        FPPredicate::OEQ   => "oeq",
+        FPPredicate::OGT   => "ogt",
+        FPPredicate::OGE   => "oge",
+        FPPredicate::OLT   => "olt",
+        FPPredicate::OLE   => "ole",
+        FPPredicate::ONE   => "one",
+        FPPredicate::ORD   => "ord",
+        FPPredicate::UNO   => "uno",
+        FPPredicate::UEQ   => "ueq",
+        FPPredicate::UGT   => "ugt",
+        FPPredicate::UGE   => "uge",
+        FPPredicate::ULT   => "ult",
+        FPPredicate::ULE   => "ule",
+        FPPredicate::UNE   => "une",
+        FPPredicate::True  => "true"
+    }
+}
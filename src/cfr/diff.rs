@@ -0,0 +1,172 @@
+use super::*;
+
+
+/// A single difference between two `CFRGroups` trees.
+/// 
+/// The path leads from the root of the trees to the sequence containing the difference. Each step names a
+///   group by its position and kind, followed by the role of the nested sequence, such as
+///   `#1 while → body → #0 if-else → else`.
+#[derive(Clone)]
+pub enum StructuralDiff {
+    /// A group is present in the other tree, but not in this one.
+    Added {
+        path  : Vec<String>,
+        group : CFRGroup
+    },
+    /// A group is present in this tree, but not in the other one.
+    Removed {
+        path  : Vec<String>,
+        group : CFRGroup
+    },
+    /// A group was replaced with a group of a different kind or shape.
+    Changed {
+        path : Vec<String>,
+        from : CFRGroup,
+        to   : CFRGroup
+    }
+}
+
+
+impl StructuralDiff {
+
+    /// Get the path to the sequence containing the difference.
+    pub fn path(&self) -> &Vec<String> {
+        match (self) {
+            Self::Added   { path, .. } => path,
+            Self::Removed { path, .. } => path,
+            Self::Changed { path, .. } => path
+        }
+    }
+
+}
+
+
+impl CFRGroups {
+
+
+    /// Compares this tree against `other`, returning every group which was added, removed, or changed.
+    /// 
+    /// Both trees are walked in parallel. Sequences are aligned on their longest common subsequence of equal
+    ///   groups, and unaligned groups of the same kind are compared recursively.
+    pub fn diff(&self, other : &CFRGroups) -> Vec<StructuralDiff> {
+        let mut out = Vec::new();
+        Self::diff_inner(self, other, &mut Vec::new(), &mut out);
+        out
+    }
+
+    fn diff_inner(a : &CFRGroups, b : &CFRGroups, path : &mut Vec<String>, out : &mut Vec<StructuralDiff>) -> () {
+        let a = &a.groups;
+        let b = &b.groups;
+
+        // Longest common subsequence table of equal groups.
+        let mut lcs = vec![ vec![ 0usize; b.len() + 1 ]; a.len() + 1 ];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if (a[i] == b[j]) { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+
+        // Walk the table, collecting runs of unaligned groups.
+        let mut i = 0;
+        let mut j = 0;
+        let mut removed = Vec::new();
+        let mut added   = Vec::new();
+        while (i < a.len() || j < b.len()) {
+            if (i < a.len() && j < b.len() && a[i] == b[j]) {
+                Self::diff_run(a, b, &removed, &added, path, out);
+                removed.clear();
+                added.clear();
+                i += 1;
+                j += 1;
+            } else if (j >= b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1])) {
+                removed.push(i);
+                i += 1;
+            } else {
+                added.push(j);
+                j += 1;
+            }
+        }
+        Self::diff_run(a, b, &removed, &added, path, out);
+    }
+
+    /// Compares a run of unaligned groups, pairing up groups of the same kind in order.
+    fn diff_run(a : &[CFRGroup], b : &[CFRGroup], removed : &[usize], added : &[usize], path : &mut Vec<String>, out : &mut Vec<StructuralDiff>) -> () {
+        let mut added = added.to_vec();
+        for &i in removed {
+            let from = &a[i];
+            let paired = added.iter().position(|&j| b[j].kind() == from.kind());
+            let Some(paired) = paired else {
+                out.push(StructuralDiff::Removed { path : path.clone(), group : from.clone() });
+                continue;
+            };
+            // Report any additions before the paired group first, to keep the output in order.
+            for j in added.drain(0..paired) {
+                out.push(StructuralDiff::Added { path : path.clone(), group : b[j].clone() });
+            }
+            let j  = added.remove(0);
+            let to = &b[j];
+            let from_children = from.children();
+            let to_children   = to.children();
+            if (from_children.is_empty()) {
+                out.push(StructuralDiff::Changed { path : path.clone(), from : from.clone(), to : to.clone() });
+                continue;
+            }
+            for ((role, from_child), (_, to_child)) in from_children.into_iter().zip(to_children) {
+                path.push(format!("#{} {}", j, from.kind()));
+                path.push(role.to_string());
+                Self::diff_inner(from_child, to_child, path, out);
+                path.pop();
+                path.pop();
+            }
+        }
+        for j in added {
+            out.push(StructuralDiff::Added { path : path.clone(), group : b[j].clone() });
+        }
+    }
+
+
+}
+
+
+impl fmt::Display for StructuralDiff {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_themed(f, &Theme::default())
+    }
+}
+
+impl Themed for StructuralDiff {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        let Theme { added, removed, changed, muted, reset, .. } = theme;
+        let path = self.path();
+        let path = if (path.is_empty()) { String::from("root") } else { path.join(" → ") };
+        match (self) {
+            Self::Added   { group, .. }    => write!(f, "{added}+{reset} {} {muted}{}{reset}", path, group.kind())?,
+            Self::Removed { group, .. }    => write!(f, "{removed}-{reset} {} {muted}{}{reset}", path, group.kind())?,
+            Self::Changed { from, to, .. } => write!(f, "{changed}~{reset} {} {muted}{} → {}{reset}", path, from.kind(), to.kind())?
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(name : &str) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Block(Name::from(name)) ] }
+    }
+
+    #[test]
+    fn diff_uses_theme() -> () {
+        // `a loop { b }` against `a loop { b c }`.
+        let before = CFRGroups { groups : vec![ CFRGroup::Block(Name::from("a")), CFRGroup::InfiniteLoop { body : block("b") } ] };
+        let after  = CFRGroups { groups : vec![ CFRGroup::Block(Name::from("a")), CFRGroup::InfiniteLoop { body : CFRGroups { groups : vec![ CFRGroup::Block(Name::from("b")), CFRGroup::Block(Name::from("c")) ] } } ] };
+        let diffs  = before.diff(&after);
+        let [ diff ] = diffs.as_slice() else { panic!("expected one difference, found {}", diffs.len()) };
+        assert_eq!(diff.with_theme(Theme::PLAIN).to_string(), "+ #1 infinite-loop → body block");
+        let theme = Theme::default();
+        assert_eq!(diff.to_string(), format!("{}+{} #1 infinite-loop → body {}block{}", theme.added, theme.reset, theme.muted, theme.reset));
+    }
+
+}
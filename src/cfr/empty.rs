@@ -0,0 +1,167 @@
+use super::*;
+
+use std::collections::HashSet;
+
+use llvm_ir::{
+    BasicBlock,
+    Function,
+    Instruction,
+    Terminator
+};
+
+
+impl CFRGroups {
+
+
+    /// Removes the blocks of `function` which do nothing but branch from the bodies of conditionals, then
+    ///   simplifies the conditionals left with empty bodies.
+    /// 
+    /// A `CFRGroup::OnewayConditional` with an empty body only runs its condition, so it is replaced with the
+    ///   groups of its condition. A `CFRGroup::TwowayConditional` with one empty body becomes a
+    ///   `CFRGroup::OnewayConditional`, negated if the `then` body is the empty one. A condition ending in a
+    ///   `CFRGroup::ShortCircuit` or `CFRGroup::CaseTest` can not stand on its own, so is kept as a conditional
+    ///   with an empty body.
+    /// 
+    /// ```text
+    /// if (                     %bb1
+    ///   %bb1          ->       %bb3
+    /// ) {
+    ///   %bb2
+    /// }
+    /// %bb3
+    /// ```
+    /// 
+    /// CFA can not see the contents of blocks, so a conditional whose `else` body is an empty block is found as a
+    ///   twoway conditional. This is where it becomes a oneway conditional.
+    /// 
+    /// An empty block is kept if its successor has a `phi` which takes a different value depending on which way it
+    ///   was entered, as removing the block would lose which value is chosen.
+    /// 
+    /// ```text
+    /// if (                     if (
+    ///   %bb1                     %bb1
+    /// ) {             ->       ) {
+    ///   %bb2                     %bb2
+    /// } else {                 }
+    ///   %bb3
+    /// }
+    /// ```
+    pub fn simplify_empty_conditionals(&mut self, function : &Function) -> () {
+        let empty = function.basic_blocks.iter()
+            .filter(|block| block.instrs.is_empty() && ! EmptyConditionals::chooses_phi(function, block))
+            .map(|block| block.name.clone())
+            .collect::<HashSet<_>>();
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut EmptyConditionals { empty });
+    }
+
+
+}
+
+
+/// Simplifies conditionals whose bodies do nothing.
+struct EmptyConditionals {
+    /// The blocks which have no instructions.
+    empty : HashSet<Name>
+}
+
+impl EmptyConditionals {
+
+    /// Returns `true` if `block` branches to a block with a `phi` whose incoming values are not all the same.
+    fn chooses_phi(function : &Function, block : &BasicBlock) -> bool {
+        let Terminator::Br(term) = &block.term else { return false };
+        let Some(dest) = function.basic_blocks.iter().find(|dest| dest.name == term.dest) else { return false };
+        dest.instrs.iter().any(|instr| match (instr) {
+            Instruction::Phi(phi) => phi.incoming_values.iter().any(|(value, _)| value != &phi.incoming_values[0].0),
+            _                     => false
+        })
+    }
+
+    /// Removes the empty blocks from the top level of `body`.
+    fn strip(&self, mut body : CFRGroups) -> CFRGroups {
+        body.groups.retain(|group| match (group) {
+            CFRGroup::Block(name) => ! self.empty.contains(name),
+            _                     => true
+        });
+        body
+    }
+
+    /// Gets the groups of a conditional with nothing in its body.
+    fn hoist(cond : CFRGroups, negated : bool) -> CFRGroups {
+        if (matches!(cond.groups.last(), Some(CFRGroup::ShortCircuit { .. } | CFRGroup::CaseTest { .. }))) {
+            CFRGroups { groups : vec![ CFRGroup::OnewayConditional { cond, body : CFRGroups { groups : Vec::new() }, negated } ] }
+        } else { cond }
+    }
+
+}
+
+impl CfrVisitor for EmptyConditionals {
+
+    fn visit_oneway_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        let body = self.strip(body);
+        if (body.groups.is_empty()) {
+            return Self::hoist(cond, negated);
+        }
+        CFRGroups { groups : vec![ CFRGroup::OnewayConditional { cond, body, negated } ] }
+    }
+
+    fn visit_twoway_conditional(&mut self, cond : CFRGroups, body_true : CFRGroups, body_false : CFRGroups) -> CFRGroups {
+        let cond       = self.visit_groups(cond);
+        let body_true  = self.visit_groups(body_true);
+        let body_false = self.visit_groups(body_false);
+        let body_true  = self.strip(body_true);
+        let body_false = self.strip(body_false);
+        let group = match (body_true.groups.is_empty(), body_false.groups.is_empty()) {
+            (true,  true)  => return Self::hoist(cond, false),
+            (false, true)  => CFRGroup::OnewayConditional { cond, body : body_true, negated : false },
+            (true,  false) => CFRGroup::OnewayConditional { cond, body : body_false, negated : true },
+            (false, false) => CFRGroup::TwowayConditional { cond, body_true, body_false }
+        };
+        CFRGroups { groups : vec![ group ] }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::{
+        add_phi,
+        function
+    };
+
+    /// Recovers a function, then simplifies its empty conditionals.
+    fn recover(function : &Function) -> CFRGroups {
+        let prims      = CFAPrim::find_all(ControlFlowGraph::new(function)).expect("graph was not reduced");
+        let mut groups = CFRGroups::new(&prims).expect("groups were not recovered");
+        groups.simplify_empty_conditionals(function);
+        groups
+    }
+
+    #[test]
+    fn empty_body_removed() -> () {
+        // `if (a) { b } c`, where `b` is empty.
+        let groups = recover(&function(&[ ("a", &[ "b", "c" ]), ("b", &[ "c" ]), ("c", &[]) ]));
+        assert!(groups.groups == vec![ CFRGroup::Block(Name::from("a")), CFRGroup::Block(Name::from("c")) ], "expected the conditional to be removed, found\n{}", groups);
+    }
+
+    #[test]
+    fn phi_choice_kept() -> () {
+        // `if (a) { b } else { c } d`, where `b` and `c` are empty, but `d` has a `phi` taking `1` from `b` and `2`
+        //   from `c`.
+        let mut function = function(&[ ("a", &[ "b", "c" ]), ("b", &[ "d" ]), ("c", &[ "d" ]), ("d", &[]) ]);
+        add_phi(&mut function, "d", "x", &[ (1, "b"), (2, "c") ]);
+        let groups = recover(&function);
+        assert!(matches!(groups.groups.first(), Some(CFRGroup::TwowayConditional { .. })), "expected the conditional to be kept, found\n{}", groups);
+        // The same `phi` with one value either way does not depend on the branch.
+        let mut function = function.clone();
+        function.basic_blocks[3].instrs.clear();
+        add_phi(&mut function, "d", "x", &[ (1, "b"), (1, "c") ]);
+        let groups = recover(&function);
+        assert!(matches!(groups.groups.first(), Some(CFRGroup::Block(_))), "expected the conditional to be removed, found\n{}", groups);
+    }
+
+}
@@ -0,0 +1,91 @@
+use super::*;
+
+use crate::induction::analyze_induction;
+
+use llvm_ir::Function;
+
+
+impl CFRGroups {
+
+
+    /// Rewrites each `CFRGroup::PreconditionLoop` with a counter into a `CFRGroup::ForLoop`.
+    /// 
+    /// Latches are first folded into the `step` of their loop, as by `CFRGroups::fold_loop_latches`. A loop is
+    ///   then rewritten if `analyze_induction` finds a counter in its header, and the block which steps the
+    ///   counter was folded into the `step`. The `CFRGroup::Assign` directly before the loop which starts the
+    ///   counter is moved into the `init`, so this should be run after `CFRGroups::resolve_phis`.
+    /// 
+    /// Loops without a counter are left as they are.
+    /// 
+    /// ```text
+    /// %start                   %start
+    /// %i = 0;                  for (
+    /// while (                    %i = 0;
+    ///   %bb1                   ;
+    /// ) {                        %bb1
+    ///   %bb2          ->       ;
+    ///   %bb3                     %bb3
+    ///   %i = %n;                 %i = %n;
+    /// }                        ) {
+    ///                            %bb2
+    ///                          }
+    /// ```
+    pub fn recover_for_loops(&mut self, function : &Function) -> () {
+        self.fold_loop_latches(function);
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut ForLoops { function });
+    }
+
+
+}
+
+
+/// Rewrites loops with counters into `for` loops.
+struct ForLoops<'l> {
+    function : &'l Function
+}
+
+impl<'l> ForLoops<'l> {
+
+    /// Rewrites a single loop, taking its `init` from the end of `before` if it is a `for` loop.
+    fn for_loop(&mut self, before : &mut CFRGroups, cond : CFRGroups, body : CFRGroups, negated : bool, step : CFRGroups) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        let step = self.visit_groups(step);
+
+        // The loop is entered through the first block of its condition.
+        let info = match (cond.groups.first()) {
+            Some(CFRGroup::Block(header)) => analyze_induction(self.function, header),
+            _                             => None
+        };
+        let Some(info) = info.filter(|info| step.groups.contains(&CFRGroup::Block(info.latch.clone()))) else {
+            return CFRGroups { groups : vec![ CFRGroup::PreconditionLoop { cond, body, negated, step } ] };
+        };
+
+        let mut init = CFRGroups { groups : Vec::new() };
+        if let Some(CFRGroup::Assign(assigns)) = before.groups.last() {
+            if (assigns.iter().any(|(dest, _)| dest == &info.variable)) {
+                init.groups.extend(before.groups.pop());
+            }
+        }
+
+        CFRGroups { groups : vec![ CFRGroup::ForLoop { init, cond, step, body, negated } ] }
+    }
+
+}
+
+impl<'l> CfrVisitor for ForLoops<'l> {
+    fn visit_groups(&mut self, groups : CFRGroups) -> CFRGroups {
+        // The init of a loop comes from the groups before it, so the loops are rewritten here instead of in
+        //   `visit_precondition_loop`.
+        let mut out = CFRGroups { groups : Vec::new() };
+        for group in groups.groups {
+            let groups = match (group) {
+                CFRGroup::PreconditionLoop { cond, body, negated, step } => self.for_loop(&mut out, cond, body, negated, step),
+                group                                                    => self.visit_group(group)
+            };
+            out.groups.extend(groups.groups);
+        }
+        out
+    }
+}
@@ -0,0 +1,130 @@
+use super::*;
+
+use std::collections::HashSet;
+
+use llvm_ir::{
+    Function,
+    Terminator
+};
+
+
+impl CFRGroups {
+
+
+    /// Rewrites conditionals which skip to the shared return of `function` as guard clauses, so that the rest of
+    ///   the function is not nested inside of them.
+    /// 
+    /// Compilers merge the returns of a function into a single block, so a chain of guard clauses is found as a
+    ///   chain of nested `CFRGroup::OnewayConditional`s, each followed by the return block. Where the return
+    ///   block has no instructions, it is copied into a negated `CFRGroup::OnewayReturnConditional` instead, and
+    ///   the body of the conditional is moved after it.
+    /// 
+    /// ```text
+    /// if (                     if (!
+    ///   %bb1                     %bb1
+    /// ) {                      ) {
+    ///   if (                     %bb4
+    ///     %bb2                   return;
+    ///   ) {           ->       }
+    ///     %bb3                 if (!
+    ///   }                        %bb2
+    /// }                        ) {
+    /// %bb4                       %bb4
+    ///                            return;
+    ///                          }
+    ///                          %bb3
+    ///                          %bb4
+    /// ```
+    /// 
+    /// Only the groups at the top of the function and in the bodies of `CFRGroup::OnewayReturnConditional`s are
+    ///   rewritten, as nothing else is followed by a `return`.
+    pub fn flatten_guard_clauses(&mut self, function : &Function) -> () {
+        let returns = function.basic_blocks.iter()
+            .filter(|block| block.instrs.is_empty() && matches!(block.term, Terminator::Ret(_)))
+            .map(|block| block.name.clone())
+            .collect::<HashSet<_>>();
+        let mut guards = GuardClauses { returns };
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        let groups = groups.visit(&mut guards);
+        *self = guards.flatten(groups);
+    }
+
+
+}
+
+
+/// Flattens the chains of conditionals which skip to the return of the function.
+struct GuardClauses {
+    /// The blocks which only return.
+    returns : HashSet<Name>
+}
+
+impl GuardClauses {
+
+    /// Flattens the conditionals at the end of `groups`, which is followed by a `return`.
+    fn flatten(&self, mut groups : CFRGroups) -> CFRGroups {
+        let tail = groups.groups.split_off(groups.groups.len().saturating_sub(2));
+        match (<[CFRGroup; 2]>::try_from(tail)) {
+            Ok([ CFRGroup::OnewayConditional { cond, mut body, negated }, CFRGroup::Block(ret) ]) if (self.returns.contains(&ret) && ! body.diverges()) => {
+                groups.groups.push(CFRGroup::OnewayReturnConditional {
+                    cond,
+                    body    : CFRGroups { groups : vec![ CFRGroup::Block(ret.clone()) ] },
+                    negated : ! negated
+                });
+                body.groups.push(CFRGroup::Block(ret));
+                groups.groups.extend(self.flatten(body).groups);
+            },
+            Ok(tail)  => groups.groups.extend(tail),
+            Err(tail) => groups.groups.extend(tail)
+        }
+        groups
+    }
+
+}
+
+impl CfrVisitor for GuardClauses {
+    fn visit_oneway_return_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        let body = self.flatten(body);
+        CFRGroups { groups : vec![ CFRGroup::OnewayReturnConditional { cond, body, negated } ] }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::function;
+
+    /// Recovers a function built from its blocks, then flattens its guard clauses.
+    fn recover(blocks : &[(&str, &[&str])]) -> CFRGroups {
+        let function   = function(blocks);
+        let prims      = CFAPrim::find_all(ControlFlowGraph::new(&function)).expect("graph was not reduced");
+        let mut groups = CFRGroups::new(&prims).expect("groups were not recovered");
+        groups.flatten_guard_clauses(&function);
+        groups
+    }
+
+    fn is_guard(group : &CFRGroup, cond : &str) -> bool {
+        matches!(group, CFRGroup::OnewayReturnConditional { cond : guard, body, negated : true }
+            if guard.groups == vec![ CFRGroup::Block(Name::from(cond)) ] && body.groups == vec![ CFRGroup::Block(Name::from("r")) ])
+    }
+
+    #[test]
+    fn shared_return_flattened() -> () {
+        // `if (a) { if (b) { if (c) { d } } } r`, where every guard skips to the shared return `r`.
+        let groups = recover(&[ ("a", &[ "b", "r" ]), ("b", &[ "c", "r" ]), ("c", &[ "d", "r" ]), ("d", &[ "r" ]), ("r", &[]) ]);
+        let [ a, b, c, CFRGroup::Block(d), CFRGroup::Block(r) ] = groups.groups.as_slice() else { panic!("expected flat guards, found\n{}", groups) };
+        assert!(is_guard(a, "a") && is_guard(b, "b") && is_guard(c, "c"), "expected flat guards, found\n{}", groups);
+        assert!(d == &Name::from("d") && r == &Name::from("r"));
+    }
+
+    #[test]
+    fn nested_body_kept() -> () {
+        // `if (a) { b } c r`, where the conditional is not followed by the return.
+        let groups = recover(&[ ("a", &[ "b", "c" ]), ("b", &[ "c" ]), ("c", &[ "r" ]), ("r", &[]) ]);
+        assert!(matches!(groups.groups.first(), Some(CFRGroup::OnewayConditional { .. })), "expected the conditional to be kept, found\n{}", groups);
+    }
+
+}
@@ -0,0 +1,82 @@
+use super::*;
+
+use std::collections::HashMap;
+
+use llvm_ir::{
+    BasicBlock,
+    Function,
+    Terminator
+};
+
+
+impl CFRGroups {
+
+
+    /// Moves the latch of each `CFRGroup::PreconditionLoop` out of its body and into its `step`.
+    /// 
+    /// The latch is the block at the end of the body which does nothing but jump back to the loop condition, after
+    ///   running its instructions, such as an increment. Any `CFRGroup::Assign` after it moves with it. A latch
+    ///   without any instructions is only there for the back edge, so it is removed instead.
+    /// 
+    /// ```text
+    /// while (                  while (
+    ///   %bb1                     %bb1
+    /// ) {                      ) {
+    ///   %bb2          ->         %bb2
+    ///   %bb3                   } continue {
+    /// }                          %bb3
+    ///                          }
+    /// ```
+    pub fn fold_loop_latches(&mut self, function : &Function) -> () {
+        let blocks = function.basic_blocks.iter()
+            .map(|block| (block.name.clone(), block))
+            .collect::<HashMap<_, _>>();
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut LoopLatches { blocks });
+    }
+
+
+}
+
+
+/// Moves the latches of loops into their `step`.
+struct LoopLatches<'l> {
+    blocks : HashMap<Name, &'l BasicBlock>
+}
+
+impl<'l> LoopLatches<'l> {
+
+    /// Gets the index of the latch in `body`, and the block it is made of, or `None` if the body does not end in
+    ///   one.
+    fn latch(&self, cond : &CFRGroups, body : &CFRGroups) -> Option<(usize, &'l BasicBlock)> {
+        // The loop is entered through the first block of its condition.
+        let Some(CFRGroup::Block(header)) = cond.groups.first() else { return None };
+        // Skip any assignments lifted from the `phi`s of the header.
+        let latch_at = body.groups.iter().rposition(|group| ! matches!(group, CFRGroup::Assign(_)))?;
+        let CFRGroup::Block(latch) = &body.groups[latch_at] else { return None };
+        let latch_block = *self.blocks.get(latch)?;
+        let Terminator::Br(term) = &latch_block.term else { return None };
+        if (&term.dest != header) { return None; }
+        Some((latch_at, latch_block))
+    }
+
+}
+
+impl<'l> CfrVisitor for LoopLatches<'l> {
+    fn visit_precondition_loop(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool, step : CFRGroups) -> CFRGroups {
+        let     cond = self.visit_groups(cond);
+        let mut body = self.visit_groups(body);
+        let mut step = self.visit_groups(step);
+
+        if let Some((latch_at, latch_block)) = self.latch(&cond, &body) {
+            let mut latch = body.groups.split_off(latch_at);
+            if (latch_block.instrs.is_empty()) {
+                latch.remove(0);
+            }
+            latch.extend(step.groups);
+            step.groups = latch;
+        }
+
+        CFRGroups { groups : vec![ CFRGroup::PreconditionLoop { cond, body, negated, step } ] }
+    }
+}
@@ -0,0 +1,171 @@
+use super::*;
+
+use crate::induction::swapped;
+
+use std::collections::HashMap;
+
+use llvm_ir::{
+    Function,
+    Instruction
+};
+
+
+impl CFRGroups {
+
+
+    /// Removes the conditional around each `CFRGroup::PreconditionLoop` which only checks the condition the loop
+    ///   checks first anyway.
+    /// 
+    /// Compilers often guard a loop with a copy of its condition, so that the checks in the loop can assume it
+    ///   runs at least once. The guard is redundant when its condition is the same as the condition of the loop
+    ///   on entry, with each `phi` in the loop header replaced by the value it has when coming from the guard.
+    ///   A comparison with its operands swapped counts as the same. The block of the guard is kept before the
+    ///   loop, as it may do more than branch.
+    /// 
+    /// ```text
+    /// if (                     %bb1
+    ///   %bb1                   while (
+    /// ) {                        %bb2
+    ///   while (       ->       ) {
+    ///     %bb2                   %bb3
+    ///   ) {                    }
+    ///     %bb3
+    ///   }
+    /// }
+    /// ```
+    /// 
+    /// Only guards made of a single block, whose body is nothing but the loop, are removed. Should be run before
+    ///   `CFRGroups::recover_for_loops`.
+    pub fn merge_loop_guards(&mut self, function : &Function) -> () {
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut LoopGuards { function });
+    }
+
+
+}
+
+
+/// Removes the redundant guards of loops.
+struct LoopGuards<'l> {
+    function : &'l Function
+}
+
+impl<'l> LoopGuards<'l> {
+
+    /// Returns `true` if the guard `guard` branches to `header` on the same condition the loop checks on entry.
+    fn is_redundant(&self, guard : &Name, guard_cond : Condition, header : &Name, loop_cond : Condition) -> bool {
+        let Some(header_block) = self.function.basic_blocks.iter().find(|block| &block.name == header) else { return false };
+
+        // The value each `phi` in the header has when entered from the guard.
+        let entry_values = header_block.instrs.iter().filter_map(|instr| match (instr) {
+            Instruction::Phi(phi) => phi.incoming_values.iter()
+                .find(|(_, from)| from == guard)
+                .map(|(value, _)| (phi.dest.clone(), value.clone())),
+            _ => None
+        }).collect::<HashMap<_, _>>();
+        let loop_cond = substitute(loop_cond, &entry_values);
+
+        match (guard_cond, loop_cond) {
+            (
+                Condition::IntCompare { predicate : guard_predicate, lhs : guard_lhs, rhs : guard_rhs },
+                Condition::IntCompare { predicate : loop_predicate, lhs : loop_lhs, rhs : loop_rhs }
+            ) => (guard_predicate == loop_predicate && guard_lhs == loop_lhs && guard_rhs == loop_rhs)
+                || (guard_predicate == swapped(loop_predicate) && guard_lhs == loop_rhs && guard_rhs == loop_lhs),
+            (guard_cond, loop_cond) => guard_cond == loop_cond
+        }
+    }
+
+}
+
+impl<'l> CfrVisitor for LoopGuards<'l> {
+    fn visit_oneway_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+
+        if let ([ CFRGroup::Block(guard) ], [ group @ CFRGroup::PreconditionLoop { cond : loop_cond, .. } ]) = (cond.groups.as_slice(), body.groups.as_slice()) {
+            let header     = loop_cond.groups.first().and_then(|group| match (group) { CFRGroup::Block(header) => Some(header), _ => None });
+            let guard_cond = cond.to_condition(self.function).map(|guard_cond| if (negated) { guard_cond.negate() } else { guard_cond });
+            if let (Some(header), Some(guard_cond), Some(loop_cond)) = (header, guard_cond, group.condition(self.function)) {
+                if (self.is_redundant(guard, guard_cond, header, loop_cond)) {
+                    return CFRGroups { groups : vec![ CFRGroup::Block(guard.clone()), group.clone() ] };
+                }
+            }
+        }
+
+        CFRGroups { groups : vec![ CFRGroup::OnewayConditional { cond, body, negated } ] }
+    }
+}
+
+
+/// Replaces each value in `condition` which is a key of `values` with its value.
+fn substitute(condition : Condition, values : &HashMap<Name, Operand>) -> Condition {
+    let operand = |operand : Operand| match (&operand) {
+        Operand::LocalOperand { name, .. } => values.get(name).cloned().unwrap_or(operand),
+        _                                  => operand
+    };
+    match (condition) {
+        Condition::Value(value)                         => Condition::Value(operand(value)),
+        Condition::IntCompare { predicate, lhs, rhs }   => Condition::IntCompare { predicate, lhs : operand(lhs), rhs : operand(rhs) },
+        Condition::FloatCompare { predicate, lhs, rhs } => Condition::FloatCompare { predicate, lhs : operand(lhs), rhs : operand(rhs) },
+        Condition::Not(inner)                           => Condition::Not(Box::new(substitute(*inner, values))),
+        Condition::And(lhs, rhs)                        => Condition::And(Box::new(substitute(*lhs, values)), Box::new(substitute(*rhs, values))),
+        Condition::Or(lhs, rhs)                         => Condition::Or(Box::new(substitute(*lhs, values)), Box::new(substitute(*rhs, values)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::function;
+    use llvm_ir::{
+        Constant,
+        ConstantRef,
+        Terminator
+    };
+
+    /// `if (g) { while (h) { b } } x`, where the loop checks `i1 0` and the guard checks `i1 guard_value`.
+    fn guarded_loop(guard_value : u64) -> (Function, CFRGroups) {
+        let mut function = function(&[ ("g", &[ "h", "x" ]), ("h", &[ "b", "x" ]), ("b", &[ "h" ]), ("x", &[]) ]);
+        if let Terminator::CondBr(guard) = &mut function.basic_blocks[0].term {
+            guard.condition = Operand::ConstantOperand(ConstantRef::new(Constant::Int { bits : 1, value : guard_value }));
+        }
+        let prims  = CFAPrim::find_all(ControlFlowGraph::new(&function)).expect("graph was not reduced");
+        let groups = CFRGroups::new(&prims).expect("groups were not recovered");
+        (function, groups)
+    }
+
+    /// Returns `true` if `groups` is an if around the loop, followed by `x`.
+    fn is_nested(groups : &CFRGroups) -> bool {
+        matches!(groups.groups.as_slice(), [
+            CFRGroup::OnewayConditional { body, .. },
+            CFRGroup::Block(_)
+        ] if matches!(body.groups.as_slice(), [ CFRGroup::PreconditionLoop { .. } ]))
+    }
+
+    #[test]
+    fn guard_is_nested() -> () {
+        // Without merging, the guard is recovered as an if around the loop.
+        let (_, groups) = guarded_loop(0);
+        assert!(is_nested(&groups), "expected the loop nested in its guard, found\n{}", groups);
+    }
+
+    #[test]
+    fn same_guard_merged() -> () {
+        let (function, mut groups) = guarded_loop(0);
+        groups.merge_loop_guards(&function);
+        assert!(matches!(groups.groups.as_slice(), [
+            CFRGroup::Block(guard),
+            CFRGroup::PreconditionLoop { .. },
+            CFRGroup::Block(_)
+        ] if guard == &Name::from("g")), "expected the guard to be merged, found\n{}", groups);
+    }
+
+    #[test]
+    fn different_guard_kept() -> () {
+        let (function, mut groups) = guarded_loop(1);
+        groups.merge_loop_guards(&function);
+        assert!(is_nested(&groups), "expected the guard to be kept, found\n{}", groups);
+    }
+
+}
@@ -0,0 +1,72 @@
+use super::*;
+
+
+/// A summary of a single recovered loop.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LoopInfo {
+    /// The first block of the loop condition.
+    pub header      : Name,
+    /// Every block inside the loop, including the header and the blocks of any nested loops.
+    pub body_blocks : Vec<Name>,
+    /// The index of the innermost loop containing this one, or `None` if it is not nested.
+    pub parent      : Option<usize>
+}
+
+
+impl CFRGroups {
+
+
+    /// Lists every `PreconditionLoop`, `ForLoop`, `PostconditionLoop`, `InfiniteLoop`, and `SwitchLoop` in the tree.
+    /// 
+    /// An `InfiniteLoop` has no condition, so its header is the first block of its body.
+    /// 
+    /// Loops are listed in the order they appear, with outer loops before the loops nested inside of them.
+    pub fn loops(&self) -> Vec<LoopInfo> {
+        let mut out = Vec::new();
+        self.loops_inner(None, &mut out);
+        out
+    }
+
+    fn loops_inner(&self, parent : Option<usize>, out : &mut Vec<LoopInfo>) -> () {
+        for group in &self.groups {
+            let mut child_parent = parent;
+            let header_groups = match (group) {
+                CFRGroup::PreconditionLoop { cond, .. } | CFRGroup::ForLoop { cond, .. } | CFRGroup::PostconditionLoop { cond, .. } | CFRGroup::SwitchLoop { cond, .. } => Some(cond),
+                CFRGroup::InfiniteLoop { body } => Some(body),
+                _ => None
+            };
+            if let Some(header_groups) = header_groups {
+                // The `init` of a `for` loop runs before the loop is entered.
+                let mut body_blocks = Vec::new();
+                for (_, child) in group.children().into_iter().filter(|(role, _)| *role != "init") {
+                    for child_group in &child.groups {
+                        child_group.blocks(&mut body_blocks);
+                    }
+                }
+                let mut cond_blocks = Vec::new();
+                for cond_group in &header_groups.groups {
+                    cond_group.blocks(&mut cond_blocks);
+                }
+                if let Some(header) = cond_blocks.into_iter().next() {
+                    child_parent = Some(out.len());
+                    out.push(LoopInfo { header, body_blocks, parent });
+                }
+            }
+            for (_, child) in group.children() {
+                child.loops_inner(child_parent, out);
+            }
+        }
+    }
+
+
+}
+
+
+impl CFRGroup {
+
+    /// Appends the name of every block in this group to `out`, in the order they appear.
+    fn blocks(&self, out : &mut Vec<Name>) -> () {
+        self.for_each_block(0, &mut Vec::new(), &mut |name, _| out.push(name.clone()));
+    }
+
+}
@@ -21,7 +21,7 @@
 //!     %bb6
 //!   }
 //! }
-//! if (
+//! if (!
 //!   %bb7
 //! ) {
 //!   loop { if (!
@@ -33,125 +33,543 @@
 //! ```
 
 
-use crate::cfg::CFGNode;
+mod style;
+pub use style::{
+    FormatStyle,
+    BraceStyle
+};
+
+mod diff;
+pub use diff::StructuralDiff;
+
+mod phi;
+
+mod noreturn;
+
+mod switch_chain;
+
+mod sparse_switch;
+
+mod case_range;
+pub use case_range::CFRCaseRange;
+
+mod latch;
+
+mod empty;
+
+mod select;
+
+mod condition;
+pub use condition::Condition;
+
+mod common_transfer;
+
+mod guard_clause;
+
+mod loop_guard;
+
+mod for_loop;
+
+mod single_line;
+
+mod rust;
+
+mod sexpr;
+
+mod block_map;
+
+mod loops;
+pub use loops::LoopInfo;
+
+mod visit;
+pub use visit::{
+    CfrVisitor,
+    walk_groups,
+    walk_group
+};
+
+use crate::cfg::{
+    ControlFlowGraph,
+    CFGNode,
+    CFGCaseValue
+};
 use crate::cfa::{
     CFAPrim,
     CFAPrims,
     prims::*
 };
+use crate::theme::{
+    Theme,
+    Themed
+};
 
 use std::fmt;
+use std::ops::Index;
 
-use llvm_ir::Name;
+use llvm_ir::{
+    Name,
+    Operand
+};
 
 
 /// A sequence of recovered CFA instruction groups.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct CFRGroups {
     pub groups : Vec<CFRGroup>
 }
 
 /// A group of recovered CFA instructions.
-#[derive(Clone)]
+/// 
+/// More groups may be added in future versions, so matches outside of this crate need a wildcard arm.
+#[derive(Clone, PartialEq)]
+#[non_exhaustive]
 pub enum CFRGroup {
     Block(Name),
 
+    /// A block which ends in a call to a function that never returns, such as `abort`.
+    NoReturnCall {
+        name   : Name,
+        /// The function being called, or `None` if it is not known.
+        callee : Option<Name>
+    },
+
+    /// A block which ends in `unreachable` without calling a function that never returns.
+    /// 
+    /// Only appears if resolved by `CFRGroups::resolve_unreachables`.
+    Unreachable(Name),
+
+    /// A block which ends in an `asm goto`, whose destination is chosen by inline assembly.
+    /// 
+    /// Left by recovery in place of the block, as the blocks it continues to are only ever part of a
+    ///   `CFRGroup::Unstructured`.
+    AsmGoto {
+        name   : Name,
+        /// Every block it can continue to, starting with the one it falls through to.
+        labels : Vec<Name>
+    },
+
+    /// Assignments which all happen at once, such as those lifted from `phi` instructions.
+    Assign(Vec<(Name, Operand)>),
+
     PreconditionLoop {
-        cond : CFRGroups,
-        body : CFRGroups
+        cond    : CFRGroups,
+        body    : CFRGroups,
+        /// Whether the condition is negated.
+        negated : bool,
+        /// Groups run at the end of every iteration, like the increment of a `for` loop.
+        /// 
+        /// Empty unless filled in by `CFRGroups::fold_loop_latches`.
+        step    : CFRGroups
+    },
+
+    /// A `CFRGroup::PreconditionLoop` with a counter, written like `for (i = 0; i < n; i++)`.
+    /// 
+    /// Only appears if recovered by `CFRGroups::recover_for_loops`.
+    ForLoop {
+        /// Groups run once before the loop, like the `phi` assignments which start the counter.
+        init    : CFRGroups,
+        cond    : CFRGroups,
+        /// Groups run at the end of every iteration, including the block which steps the counter.
+        step    : CFRGroups,
+        body    : CFRGroups,
+        /// Whether the condition is negated.
+        negated : bool
     },
 
     PostconditionLoop {
-        cond : CFRGroups
+        cond         : CFRGroups,
+        /// The values carried out of the loop by `break`, as the `phi` each is assigned to and the value.
+        /// 
+        /// Empty unless filled in by `CFRGroups::resolve_phis`.
+        break_values : Vec<(Name, Operand)>
     },
 
-    OnewayConditional {
-        cond : CFRGroups,
+    /// A loop which never ends, as in a function which never returns, such as an event loop.
+    InfiniteLoop {
         body : CFRGroups
     },
 
+    OnewayConditional {
+        cond    : CFRGroups,
+        body    : CFRGroups,
+        /// Whether the condition is negated.
+        negated : bool
+    },
+
+    /// A conditional which leaves the function after running `body`.
+    /// 
+    /// Written with a `return` at the end of `body`, unless `body` ends in a `CFRGroup::NoReturnCall` or
+    ///   `CFRGroup::Unreachable`.
     OnewayReturnConditional {
-        cond : CFRGroups,
-        body : CFRGroups
+        cond    : CFRGroups,
+        body    : CFRGroups,
+        /// Whether the condition is negated.
+        negated : bool
+    },
+
+    /// A conditional which leaves the innermost loop around it after running `body`, which may be empty.
+    BreakConditional {
+        cond    : CFRGroups,
+        body    : CFRGroups,
+        /// Whether the condition is negated.
+        negated : bool
     },
 
     TwowayConditional {
         cond       : CFRGroups,
         body_true  : CFRGroups,
         body_false : CFRGroups
+    },
+
+    /// A `CFRGroup::TwowayConditional` which only chooses the value of a `phi`, written like `x = c ? a : b`.
+    /// 
+    /// Only appears if recovered by `CFRGroups::recover_selects`.
+    Select {
+        cond     : CFRGroups,
+        /// The value when the condition is true.
+        then_val : Operand,
+        /// The value when the condition is false.
+        else_val : Operand,
+        /// The `phi` the value is assigned to.
+        dest     : Name
+    },
+
+    /// True when `value` is any of `values`, as the condition of a conditional lowered from a `CFRGroup::Switch`.
+    /// 
+    /// Only appears as the last group of a condition, if lowered by `CFRGroups::lower_sparse_switches`.
+    CaseTest {
+        /// The value the switch was on.
+        value  : Operand,
+        values : Vec<CFGCaseValue>
+    },
+
+    /// `default` is `None` when there is no default arm, as opposed to an empty one.
+    Switch {
+        cond    : CFRGroups,
+        cases   : Vec<CFRSwitchCase>,
+        default : Option<CFRGroups>
+    },
+
+    /// A loop around a `switch` on `cond`, as compiled from a state machine like `loop { match state { .. } }`.
+    /// 
+    /// Every arm ends by either going around the loop again with `continue`, which runs `step` first, or leaving
+    ///   it with `break`. No arm falls out of the switch, so `break` always leaves the loop.
+    SwitchLoop {
+        cond    : CFRGroups,
+        cases   : Vec<CFRSwitchLoopCase>,
+        /// The arm taken when no case matches, which has no values.
+        default : CFRSwitchLoopCase,
+        /// Groups run at the end of every iteration, after the arms which go around the loop again.
+        step    : CFRGroups
+    },
+
+    /// Two conditions joined with `&&` or `||`, where `rhs` is only run if it is needed.
+    /// 
+    /// Only appears as the last group of the condition of another group. It is true exactly when the last block
+    ///   of `rhs` would branch as if its condition were true.
+    ShortCircuit {
+        lhs         : CFRGroups,
+        rhs         : CFRGroups,
+        /// Whether the conditions are joined with `||` rather than `&&`.
+        disjunction : bool,
+        /// Whether the condition of `lhs` is negated.
+        negated     : bool
+    },
+
+    /// A marker left by `CFRGroups::new_partial` in place of a region which could not be recovered.
+    Unrecovered(UnrecoveredRegion),
+
+    /// A region left by `CFRGroups::new_partial` where the graph could not be reduced, such as a loop entered
+    ///   through more than one of its blocks.
+    /// 
+    /// Only the region itself is left unstructured, so the groups before it are entered into it through an edge to
+    ///   one of its blocks, and it leaves through an edge to the groups after it.
+    Unstructured {
+        /// Every block of the region, in the order of the function.
+        blocks : Vec<Name>,
+        /// Every edge into, between, and out of the blocks, by block name.
+        edges  : Vec<(Name, Name)>
     }
 
 }
 
+/// A part of the control flow graph which could not be recovered, from the block `entry` to the block `exit`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnrecoveredRegion {
+    pub entry : Name,
+    pub exit  : Name
+}
+
+/// A single arm of a recovered `switch`.
+#[derive(Clone, PartialEq)]
+pub struct CFRSwitchCase {
+    /// All of the values which branch to this arm. Use `CFRSwitchCase::ranges` to get them with runs of
+    ///   consecutive values collapsed.
+    pub values : Vec<CFGCaseValue>,
+    pub body   : CFRGroups
+}
+
+/// A single arm of a recovered `CFRGroup::SwitchLoop`.
+#[derive(Clone, PartialEq)]
+pub struct CFRSwitchLoopCase {
+    /// All of the values which branch to this arm. Use `CFRSwitchLoopCase::ranges` to get them with runs of
+    ///   consecutive values collapsed.
+    pub values : Vec<CFGCaseValue>,
+    pub body   : CFRGroups,
+    /// Whether the arm leaves the loop after `body`, rather than going around it again.
+    pub breaks : bool
+}
+
 
 impl CFRGroups {
 
 
-    /// Recover groups from a function and its CFA primitives.
+    /// Recover groups from a function and its CFA primitives, or `None` if any part of it could not be recovered.
     pub fn new(prims : &CFAPrims) -> Option<Self> {
-        Some(Self::handle(prims, prims.entry())?)
+        let mut recoverer = Recoverer { prims, unrecovered : None, keep_temps : false };
+        Some(recoverer.handle(prims.entry())?)
+    }
+
+
+    /// Recover groups the same way as `CFRGroups::new`, but keep the temporary nodes inserted during CFA as
+    ///   `CFRGroup::Block`s instead of dropping them.
+    /// 
+    /// Temporaries are shown dimmed, such as `%@DECOMP_TEMPORARY_0`. Only useful for debugging, to see where the
+    ///   temporaries landed when recovery gives an unexpected structure.
+    pub fn new_keep_temps(prims : &CFAPrims) -> Option<Self> {
+        let mut recoverer = Recoverer { prims, unrecovered : None, keep_temps : true };
+        Some(recoverer.handle(prims.entry())?)
+    }
+
+
+    /// Recover groups from a function and its CFA primitives, leaving a `CFRGroup::Unrecovered` marker in place
+    ///   of any part which could not be recovered.
+    /// 
+    /// Also returns every region which could not be recovered, in the order they appear.
+    /// 
+    /// Each `CFAUnstructured` left by `CFAPrim::find_all_partial` is recovered as a `CFRGroup::Unstructured`.
+    pub fn new_partial(prims : &CFAPrims) -> (Self, Vec<UnrecoveredRegion>) {
+        let mut recoverer = Recoverer { prims, unrecovered : Some(Vec::new()), keep_temps : false };
+        let groups = recoverer.handle(prims.entry()).unwrap_or_else(|| CFRGroups { groups : Vec::new() });
+        (groups, recoverer.unrecovered.unwrap_or_default())
+    }
+
+
+    /// Recover groups from only the part of the CFA primitives under `root`, or `None` if any part of it could not
+    ///   be recovered.
+    /// 
+    /// `root` must be a node which was on the graph while it was being reduced: either a single block, or a node
+    ///   which a primitive was merged into, such as `(%bb2...%bb4)`. A merged node which no primitive was merged into
+    ///   gives `None`. Recovering from `CFAPrims::entry` is the same as `CFRGroups::new`.
+    /// 
+    /// Useful for recovering the region under a loop header again, without recovering the whole function.
+    pub fn recover_region(prims : &CFAPrims, root : &CFGNode) -> Option<Self> {
+        let mut recoverer = Recoverer { prims, unrecovered : None, keep_temps : false };
+        recoverer.handle(root)
+    }
+
+
+    /// Returns `true` if there is no `CFRGroup::Unrecovered` marker anywhere in the tree.
+    /// 
+    /// Groups returned by `CFRGroups::new` are always fully structured.
+    pub fn is_fully_structured(&self) -> bool {
+        self.groups.iter().all(|group| match (group) {
+            CFRGroup::Unrecovered(_)       => false,
+            CFRGroup::Unstructured { .. } => false,
+            group => group.children().into_iter().all(|(_, child)| child.is_fully_structured())
+        })
     }
 
 
-    fn handle(prims : &CFAPrims, at : &CFGNode) -> Option<CFRGroups> {
+    /// Returns `true` if the last group never finishes, so no `return` is needed after it.
+    pub(crate) fn diverges(&self) -> bool {
+        match (self.groups.last()) {
+            Some(CFRGroup::NoReturnCall { .. } | CFRGroup::Unreachable(_) | CFRGroup::InfiniteLoop { .. }) => true,
+            // A switch loop with no way out never ends either.
+            Some(CFRGroup::SwitchLoop { cases, default, .. }) => ! cases.iter().chain([ default ]).any(|case| case.breaks),
+            _ => false
+        }
+    }
+
+
+    /// Gets the number of groups in the sequence, not counting nested groups.
+    pub fn len(&self) -> usize { self.groups.len() }
+
+    /// Returns `true` if the sequence contains no groups.
+    pub fn is_empty(&self) -> bool { self.groups.is_empty() }
+
+    /// Gets the group at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index : usize) -> Option<&CFRGroup> { self.groups.get(index) }
+
+
+}
+
+
+impl Index<usize> for CFRGroups {
+    type Output = CFRGroup;
+    /// Gets the group at `index`.
+    /// 
+    /// Panics if `index` is out of bounds. See `CFRGroups::get` for a non-panicking version.
+    fn index(&self, index : usize) -> &Self::Output { &self.groups[index] }
+}
+
+
+/// State shared while recovering groups.
+struct Recoverer<'l> {
+    prims       : &'l CFAPrims,
+    /// The regions which could not be recovered, or `None` if recovery should fail instead.
+    unrecovered : Option<Vec<UnrecoveredRegion>>,
+    /// Whether temporary nodes are kept as blocks.
+    keep_temps  : bool
+}
+
+impl<'l> Recoverer<'l> {
+
+
+    fn handle(&mut self, at : &CFGNode) -> Option<CFRGroups> {
+        let prims = self.prims;
         for prim in prims.prims() {
             // Look for the subnode of at.
             let prim_entry = prim.entry();
             if (prim_entry.from_pred() == at.from_pred() && prim.exit().to_succ() == at.to_succ()) {
                 match (prim) {
 
-                    CFAPrim::PreconditionLoop(CFAPreconditionLoop { cond, body, exit }) => {
-                        let cond = Self::handle(prims, cond)?;
-                        let body = Self::handle(prims, body)?;
-                        let exit = Self::handle(prims, exit)?;
-                        let mut out = CFRGroups { groups : vec![ CFRGroup::PreconditionLoop { cond, body } ] };
+                    CFAPrim::PreconditionLoop(CFAPreconditionLoop { cond, body, exit, negated }) => {
+                        let cond = self.handle(cond)?;
+                        let body = self.handle(body)?;
+                        let exit = self.handle(exit)?;
+                        let mut out = CFRGroups { groups : vec![ CFRGroup::PreconditionLoop { cond, body, negated : *negated, step : CFRGroups { groups : Vec::new() } } ] };
                         out.groups.extend(exit.groups);
                         return Some(out);
                     },
 
                     CFAPrim::PostconditionLoop(CFAPostconditionLoop { cond, exit }) => {
-                        let cond = Self::handle(prims, cond)?;
-                        let exit = Self::handle(prims, exit)?;
-                        let mut out = CFRGroups { groups : vec![ CFRGroup::PostconditionLoop { cond } ] };
+                        let cond = self.handle(cond)?;
+                        let exit = self.handle(exit)?;
+                        let mut out = CFRGroups { groups : vec![ CFRGroup::PostconditionLoop { cond, break_values : Vec::new() } ] };
+                        out.groups.extend(exit.groups);
+                        return Some(out);
+                    },
+
+                    CFAPrim::InfiniteLoop(CFAInfiniteLoop { body, exit }) => {
+                        let body = self.handle(body)?;
+                        let exit = self.handle(exit)?;
+                        let mut out = CFRGroups { groups : vec![ CFRGroup::InfiniteLoop { body } ] };
                         out.groups.extend(exit.groups);
                         return Some(out);
                     },
 
-                    CFAPrim::OnewayConditional(CFAOnewayConditional { cond, body, exit }) => {
-                        let mut out  = Self::handle(prims, cond)?;
+                    CFAPrim::OnewayConditional(CFAOnewayConditional { cond, body, exit, negated }) => {
+                        let mut out  = self.handle(cond)?;
                         let     cond = CFRGroups { groups : vec![ out.groups.pop().unwrap() ] };
-                        let     body = Self::handle(prims, body)?;
-                        let     exit = Self::handle(prims, exit)?;
-                        out.groups.push(CFRGroup::OnewayConditional { cond, body });
+                        let     body = self.handle(body)?;
+                        let     exit = self.handle(exit)?;
+                        out.groups.push(CFRGroup::OnewayConditional { cond, body, negated : *negated });
                         out.groups.extend(exit.groups);
                         return Some(out);
                     },
 
-                    CFAPrim::OnewayReturnConditional(CFAOnewayReturnConditional { cond, body, exit }) => {
-                        let mut out  = Self::handle(prims, cond)?;
+                    CFAPrim::OnewayReturnConditional(CFAOnewayReturnConditional { cond, body, exit, negated }) => {
+                        let mut out  = self.handle(cond)?;
                         let     cond = CFRGroups { groups : vec![ out.groups.pop().unwrap() ] };
-                        let     body = Self::handle(prims, body)?;
-                        let     exit = Self::handle(prims, exit)?;
-                        out.groups.push(CFRGroup::OnewayReturnConditional { cond, body });
+                        let     body = self.handle(body)?;
+                        let     exit = self.handle(exit)?;
+                        out.groups.push(CFRGroup::OnewayReturnConditional { cond, body, negated : *negated });
+                        out.groups.extend(exit.groups);
+                        return Some(out);
+                    },
+
+                    CFAPrim::LoopBreak(CFALoopBreak { cond, body, exit, negated }) => {
+                        let mut out  = self.handle(cond)?;
+                        let     cond = CFRGroups { groups : vec![ out.groups.pop().unwrap() ] };
+                        let     body = match (body) {
+                            Some(body) => self.handle(body)?,
+                            None       => CFRGroups { groups : Vec::new() }
+                        };
+                        let     exit = self.handle(exit)?;
+                        out.groups.push(CFRGroup::BreakConditional { cond, body, negated : *negated });
                         out.groups.extend(exit.groups);
                         return Some(out);
                     },
 
                     CFAPrim::TwowayConditional(CFATwowayConditional { cond, body_a, body_b, exit }) => {
-                        let mut out    = Self::handle(prims, cond)?;
+                        let mut out    = self.handle(cond)?;
                         let     cond   = CFRGroups { groups : vec![ out.groups.pop().unwrap() ] };
-                        let     body_a = Self::handle(prims, body_a)?;
-                        let     body_b = Self::handle(prims, body_b)?;
-                        let     exit   = Self::handle(prims, exit)?;
+                        let     body_a = self.handle(body_a)?;
+                        let     body_b = self.handle(body_b)?;
+                        let     exit   = self.handle(exit)?;
                         out.groups.push(
                             CFRGroup::TwowayConditional { cond, body_true : body_a, body_false : body_b });
                         out.groups.extend(exit.groups);
                         return Some(out);
                     },
 
-                    CFAPrim::StatementSequence(CFAStatementSequence { entry, exit }) => {
-                        let mut out = Self::handle(prims, entry)?;
-                        out.groups.extend(Self::handle(prims, exit)?.groups);
+                    CFAPrim::Switch(CFASwitch { cond, cases, default, exit }) => {
+                        let mut out   = self.handle(cond)?;
+                        let     cond  = CFRGroups { groups : vec![ out.groups.pop().unwrap() ] };
+                        let mut arms  = Vec::new();
+                        for CFASwitchCase { values, body } in cases {
+                            let body = match (body) {
+                                Some(body) => self.handle(body)?,
+                                None       => CFRGroups { groups : Vec::new() }
+                            };
+                            arms.push(CFRSwitchCase { values : values.clone(), body });
+                        }
+                        let default = match (default) {
+                            Some(default) => Some(self.handle(default)?),
+                            None          => None
+                        };
+                        let exit = self.handle(exit)?;
+                        out.groups.push(CFRGroup::Switch { cond, cases : arms, default });
+                        out.groups.extend(exit.groups);
+                        return Some(out);
+                    },
+
+                    CFAPrim::SwitchLoop(CFASwitchLoop { cond, cases, default, latch, exit }) => {
+                        let cond = self.handle(cond)?;
+                        let mut arm = |case : &CFASwitchLoopCase| -> Option<CFRSwitchLoopCase> {
+                            let body = match (&case.body) {
+                                Some(body) => self.handle(body)?,
+                                None       => CFRGroups { groups : Vec::new() }
+                            };
+                            Some(CFRSwitchLoopCase { values : case.values.clone(), body, breaks : case.breaks })
+                        };
+                        let arms    = cases.iter().map(&mut arm).collect::<Option<Vec<_>>>()?;
+                        let default = arm(default)?;
+                        let step    = match (latch) {
+                            Some(latch) => self.handle(latch)?,
+                            None        => CFRGroups { groups : Vec::new() }
+                        };
+                        let exit = self.handle(exit)?;
+                        let mut out = CFRGroups { groups : vec![ CFRGroup::SwitchLoop { cond, cases : arms, default, step } ] };
+                        out.groups.extend(exit.groups);
+                        return Some(out);
+                    },
+
+                    CFAPrim::StatementSequence(CFAStatementSequence { entry, middle, exit }) => {
+                        let mut out = self.handle(entry)?;
+                        for node in middle {
+                            out.groups.extend(self.handle(node)?.groups);
+                        }
+                        out.groups.extend(self.handle(exit)?.groups);
+                        return Some(out);
+                    },
+
+                    CFAPrim::ShortCircuit(CFAShortCircuit { first, second, disjunction, negated }) => {
+                        let mut out = self.handle(first)?;
+                        let     lhs = CFRGroups { groups : vec![ out.groups.pop().unwrap() ] };
+                        let     rhs = self.handle(second)?;
+                        out.groups.push(CFRGroup::ShortCircuit { lhs, rhs, disjunction : *disjunction, negated : *negated });
+                        return Some(out);
+                    },
+
+                    CFAPrim::Unstructured(CFAUnstructured { entry, blocks, edges, exit, .. }) => {
+                        // Only recovered where parts which could not be recovered are allowed.
+                        self.unrecovered.as_ref()?;
+                        let mut out  = self.handle(entry)?;
+                        let     exit = self.handle(exit)?;
+                        out.groups.push(CFRGroup::Unstructured { blocks : blocks.clone(), edges : edges.clone() });
+                        out.groups.extend(exit.groups);
                         return Some(out);
                     }
 
@@ -160,12 +578,19 @@ impl CFRGroups {
         }
         let at_from_pred = at.from_pred();
         if (at_from_pred == at.to_succ()) {
-            let groups = if (! prims.temps().contains(at_from_pred)) {
+            let groups = if let Some(labels) = prims.asm_gotos().get(at_from_pred) {
+                vec![ CFRGroup::AsmGoto { name : at_from_pred.clone(), labels : labels.clone() } ]
+            } else if (self.keep_temps || ! prims.temps().contains(at_from_pred)) {
                 vec![ CFRGroup::Block(at_from_pred.clone()) ]
             } else {
                 Vec::new()
             };
             Some(CFRGroups { groups })
+        } else if let Some(unrecovered) = &mut self.unrecovered {
+            // No primitive covers this node, so leave a marker in its place.
+            let region = UnrecoveredRegion { entry : at_from_pred.clone(), exit : at.to_succ().clone() };
+            unrecovered.push(region.clone());
+            Some(CFRGroups { groups : vec![ CFRGroup::Unrecovered(region) ] })
         } else { None }
     }
 
@@ -173,75 +598,473 @@ impl CFRGroups {
 }
 
 
+impl CFRGroup {
+
+
+    /// A short name for the kind of this group.
+    pub(crate) fn kind(&self) -> &'static str {
+        match (self) {
+            Self::Block                   (_)    => "block",
+            Self::NoReturnCall            { .. } => "noreturn-call",
+            Self::Unreachable             (_)    => "unreachable",
+            Self::AsmGoto                 { .. } => "asm-goto",
+            Self::Assign                  (_)    => "assign",
+            Self::PreconditionLoop        { .. } => "while",
+            Self::ForLoop                 { .. } => "for",
+            Self::PostconditionLoop       { .. } => "loop",
+            Self::InfiniteLoop            { .. } => "infinite-loop",
+            Self::OnewayConditional       { .. } => "if",
+            Self::OnewayReturnConditional { .. } => "if-return",
+            Self::BreakConditional        { .. } => "if-break",
+            Self::TwowayConditional       { .. } => "if-else",
+            Self::Select                  { .. } => "select",
+            Self::CaseTest                { .. } => "case-test",
+            Self::Switch                  { .. } => "switch",
+            Self::SwitchLoop              { .. } => "switch-loop",
+            Self::ShortCircuit            { disjunction : true,  .. } => "or",
+            Self::ShortCircuit            { disjunction : false, .. } => "and",
+            Self::Unrecovered             (_)    => "unrecovered",
+            Self::Unstructured            { .. } => "unstructured"
+        }
+    }
+
+
+    /// The nested groups of this group, each with a short name for its role, in the order they are printed.
+    pub(crate) fn children(&self) -> Vec<(&'static str, &CFRGroups)> {
+        match (self) {
+            Self::Block                   (_)                                => Vec::new(),
+            Self::NoReturnCall            { .. }                             => Vec::new(),
+            Self::Unreachable             (_)                                => Vec::new(),
+            Self::AsmGoto                 { .. }                             => Vec::new(),
+            Self::Assign                  (_)                                => Vec::new(),
+            Self::PreconditionLoop        { cond, body, step, .. }           => vec![ ("condition", cond), ("body", body), ("step", step) ],
+            Self::ForLoop                 { init, cond, step, body, .. }     => vec![ ("init", init), ("condition", cond), ("step", step), ("body", body) ],
+            Self::PostconditionLoop       { cond, .. }                       => vec![ ("condition", cond) ],
+            Self::InfiniteLoop            { body }                           => vec![ ("body", body) ],
+            Self::OnewayConditional       { cond, body, .. }                 => vec![ ("condition", cond), ("body", body) ],
+            Self::OnewayReturnConditional { cond, body, .. }                 => vec![ ("condition", cond), ("body", body) ],
+            Self::BreakConditional        { cond, body, .. }                 => vec![ ("condition", cond), ("body", body) ],
+            Self::TwowayConditional       { cond, body_true, body_false }    => vec![ ("condition", cond), ("then", body_true), ("else", body_false) ],
+            Self::Select                  { cond, .. }                       => vec![ ("condition", cond) ],
+            Self::CaseTest                { .. }                             => Vec::new(),
+            Self::Switch                  { cond, cases, default }           => {
+                let mut children = vec![ ("condition", cond) ];
+                children.extend(cases.iter().map(|case| ("case", &case.body)));
+                children.extend(default.iter().map(|default| ("default", default)));
+                children
+            },
+            Self::SwitchLoop              { cond, cases, default, step }     => {
+                let mut children = vec![ ("condition", cond) ];
+                children.extend(cases.iter().map(|case| ("case", &case.body)));
+                children.push(("default", &default.body));
+                children.push(("step", step));
+                children
+            },
+            Self::ShortCircuit            { lhs, rhs, .. }                   => vec![ ("lhs", lhs), ("rhs", rhs) ],
+            Self::Unrecovered             (_)                                => Vec::new(),
+            Self::Unstructured            { .. }                             => Vec::new()
+        }
+    }
+
+    /// The nested groups of this group, mutably, in the same order as `children`.
+    pub(crate) fn children_mut(&mut self) -> Vec<&mut CFRGroups> {
+        match (self) {
+            Self::Block                   (_)                                => Vec::new(),
+            Self::NoReturnCall            { .. }                             => Vec::new(),
+            Self::Unreachable             (_)                                => Vec::new(),
+            Self::AsmGoto                 { .. }                             => Vec::new(),
+            Self::Assign                  (_)                                => Vec::new(),
+            Self::PreconditionLoop        { cond, body, step, .. }           => vec![ cond, body, step ],
+            Self::ForLoop                 { init, cond, step, body, .. }     => vec![ init, cond, step, body ],
+            Self::PostconditionLoop       { cond, .. }                       => vec![ cond ],
+            Self::InfiniteLoop            { body }                           => vec![ body ],
+            Self::OnewayConditional       { cond, body, .. }                 => vec![ cond, body ],
+            Self::OnewayReturnConditional { cond, body, .. }                 => vec![ cond, body ],
+            Self::BreakConditional        { cond, body, .. }                 => vec![ cond, body ],
+            Self::TwowayConditional       { cond, body_true, body_false }    => vec![ cond, body_true, body_false ],
+            Self::Select                  { cond, .. }                       => vec![ cond ],
+            Self::CaseTest                { .. }                             => Vec::new(),
+            Self::Switch                  { cond, cases, default }           => {
+                let mut children = vec![ cond ];
+                children.extend(cases.iter_mut().map(|case| &mut case.body));
+                children.extend(default.iter_mut());
+                children
+            },
+            Self::SwitchLoop              { cond, cases, default, step }     => {
+                let mut children = vec![ cond ];
+                children.extend(cases.iter_mut().map(|case| &mut case.body));
+                children.push(&mut default.body);
+                children.push(step);
+                children
+            },
+            Self::ShortCircuit            { lhs, rhs, .. }                   => vec![ lhs, rhs ],
+            Self::Unrecovered             (_)                                => Vec::new(),
+            Self::Unstructured            { .. }                             => Vec::new()
+        }
+    }
+
+
+}
+
+
 impl fmt::Display for CFRGroups {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt_inner(f, 0)
+        self.fmt_with_style(f, &FormatStyle::default())
+    }
+}
+impl Themed for CFRGroups {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        self.fmt_with_style(f, &FormatStyle { theme : *theme, ..FormatStyle::default() })
     }
 }
 impl CFRGroups {
-    fn fmt_inner(&self, f : &mut fmt::Formatter<'_>, depth : usize) -> fmt::Result {
+
+    /// Formats the groups using the given `FormatStyle`.
+    /// 
+    /// `Display` is equivalent to calling this with `FormatStyle::default()`.
+    pub fn fmt_with_style(&self, f : &mut fmt::Formatter<'_>, style : &FormatStyle) -> fmt::Result {
+        self.fmt_inner(f, style, 0)
+    }
+
+    fn fmt_inner(&self, f : &mut fmt::Formatter<'_>, style : &FormatStyle, depth : usize) -> fmt::Result {
         for group in &self.groups {
-            group.fmt_inner(f, depth)?;
+            group.fmt_inner(f, style, depth)?;
         }
         Ok(())
     }
+
 }
 
 impl fmt::Display for CFRGroup {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt_inner(f, 0)
+        self.fmt_inner(f, &FormatStyle::default(), 0)
+    }
+}
+impl Themed for CFRGroup {
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result {
+        self.fmt_inner(f, &FormatStyle { theme : *theme, ..FormatStyle::default() }, 0)
     }
 }
 impl CFRGroup {
-    fn fmt_inner(&self, f : &mut fmt::Formatter<'_>, depth : usize) -> fmt::Result {
+    fn fmt_inner(&self, f : &mut fmt::Formatter<'_>, style : &FormatStyle, depth : usize) -> fmt::Result {
+        let indent = style.indent(depth);
+        if (self.fmt_collapsed(f, style, &indent)?) { return Ok(()); }
+        let Theme { keyword, return_keyword, punctuation, block, negation, muted, error, reset, .. } = &style.theme;
         match (self) {
 
             Self::Block(name) => {
-                writeln!(f, "{}\x1b[36m{}\x1b[0m", "  ".repeat(depth), name)?
+                if (ControlFlowGraph::is_temporary_name(name)) {
+                    writeln!(f, "{}{muted}{}{reset}", indent, name)?
+                } else {
+                    writeln!(f, "{}{block}{}{reset}", indent, name)?
+                }
             },
 
-            Self::PreconditionLoop { cond, body } => {
-                writeln!(f, "{}\x1b[95m\x1b[1mwhile\x1b[0m \x1b[37m\x1b[1m(\x1b[0m", "  ".repeat(depth))?;
-                cond.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m) {{\x1b[0m", "  ".repeat(depth))?;
-                body.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m}}\x1b[0m", "  ".repeat(depth))?;
+            Self::NoReturnCall { name, callee } => {
+                writeln!(f, "{}{block}{}{reset}", indent, name)?;
+                match (callee) {
+                    Some(Name::Name(callee))   => write!(f, "{}{}@{}{reset}", indent, style.theme.callee, callee)?,
+                    Some(Name::Number(callee)) => write!(f, "{}{}@{}{reset}", indent, style.theme.callee, callee)?,
+                    None                       => write!(f, "{}{muted}<unknown>{reset}", indent)?
+                }
+                writeln!(f, "{punctuation}(){reset}{muted};{reset}")?;
             },
 
-            Self::PostconditionLoop { cond } => {
-                writeln!(f, "{}\x1b[95m\x1b[1mloop\x1b[0m \x1b[37m\x1b[1m{{\x1b[0m \x1b[95m\x1b[1mif\x1b[0m \x1b[37m\x1b[1m(\x1b[91m!\x1b[0m", "  ".repeat(depth))?;
-                cond.fmt_inner(f, depth + 2)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m) {{\x1b[0m \x1b[95m\x1b[1mbreak\x1b[0m\x1b[2m;\x1b[0m \x1b[37m\x1b[1m}}\x1b[0m \x1b[37m\x1b[1m}}\x1b[0m", "  ".repeat(depth))?;
+            Self::Unreachable(name) => {
+                writeln!(f, "{}{block}{}{reset}", indent, name)?;
+                writeln!(f, "{}{return_keyword}unreachable{reset}{muted};{reset}", indent)?;
             },
 
-            Self::OnewayConditional { cond, body } => {
-                writeln!(f, "{}\x1b[95m\x1b[1mif\x1b[0m \x1b[37m\x1b[1m(\x1b[0m", "  ".repeat(depth))?;
-                cond.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m) {{\x1b[0m", "  ".repeat(depth))?;
-                body.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m}}\x1b[0m", "  ".repeat(depth))?;
+            Self::AsmGoto { name, labels } => {
+                writeln!(f, "{}{block}{}{reset}", indent, name)?;
+                write!(f, "{}{keyword}asm goto{reset} ", indent)?;
+                let mut first = true;
+                for label in labels {
+                    if (first) { first = false; }
+                    else { write!(f, "{muted},{reset} ")?; }
+                    write!(f, "{block}{}{reset}", label)?;
+                }
+                writeln!(f, "{muted};{reset}")?;
             },
 
-            Self::OnewayReturnConditional { cond, body } => {
-                writeln!(f, "{}\x1b[95m\x1b[1mif\x1b[0m \x1b[37m\x1b[1m(\x1b[0m", "  ".repeat(depth))?;
-                cond.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m) {{\x1b[0m", "  ".repeat(depth))?;
-                body.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[35m\x1b[1mreturn\x1b[0m\x1b[2m;\x1b[0m", "  ".repeat(depth + 1))?;
-                writeln!(f, "{}\x1b[37m\x1b[1m}}\x1b[0m", "  ".repeat(depth))?;
+            Self::Assign(assigns) => {
+                write!(f, "{}", indent)?;
+                let mut first = true;
+                for (dest, _) in assigns {
+                    if (first) { first = false; }
+                    else { write!(f, "{muted},{reset} ")?; }
+                    write!(f, "{block}{}{reset}", dest)?;
+                }
+                write!(f, " {muted}={reset} ")?;
+                let mut first = true;
+                for (_, value) in assigns {
+                    if (first) { first = false; }
+                    else { write!(f, "{muted},{reset} ")?; }
+                    write!(f, "{}", value)?;
+                }
+                writeln!(f, "{muted};{reset}")?;
+            },
+
+            Self::PreconditionLoop { cond, body, negated, step } => {
+                if (*negated) {
+                    writeln!(f, "{}{keyword}while{reset} {punctuation}({negation}!{reset}", indent)?;
+                } else {
+                    writeln!(f, "{}{keyword}while{reset} {punctuation}({reset}", indent)?;
+                }
+                cond.fmt_inner(f, style, depth + 1)?;
+                style.write_open(f, depth, ")")?;
+                body.fmt_inner(f, style, depth + 1)?;
+                if (! step.groups.is_empty()) {
+                    style.write_between(f, depth, "continue")?;
+                    step.fmt_inner(f, style, depth + 1)?;
+                }
+                style.write_close(f, depth)?;
+            },
+
+            Self::ForLoop { init, cond, step, body, negated } => {
+                writeln!(f, "{}{keyword}for{reset} {punctuation}({reset}", indent)?;
+                init.fmt_inner(f, style, depth + 1)?;
+                if (*negated) {
+                    writeln!(f, "{}{punctuation};{negation}!{reset}", indent)?;
+                } else {
+                    writeln!(f, "{}{punctuation};{reset}", indent)?;
+                }
+                cond.fmt_inner(f, style, depth + 1)?;
+                writeln!(f, "{}{punctuation};{reset}", indent)?;
+                step.fmt_inner(f, style, depth + 1)?;
+                style.write_open(f, depth, ")")?;
+                body.fmt_inner(f, style, depth + 1)?;
+                style.write_close(f, depth)?;
+            },
+
+            Self::PostconditionLoop { cond, break_values } => match (style.brace_style) {
+                BraceStyle::KAndR => {
+                    writeln!(f, "{}{keyword}loop{reset} {punctuation}{{{reset} {keyword}if{reset} {punctuation}({negation}!{reset}", indent)?;
+                    cond.fmt_inner(f, style, depth + 2)?;
+                    write!(f, "{}{punctuation}) {{{reset} ", indent)?;
+                    Self::fmt_break(f, &style.theme, break_values)?;
+                    writeln!(f, " {punctuation}}}{reset} {punctuation}}}{reset}")?;
+                },
+                BraceStyle::Allman => {
+                    let inner_indent = style.indent(depth + 1);
+                    writeln!(f, "{}{keyword}loop{reset}", indent)?;
+                    writeln!(f, "{}{punctuation}{{{reset}", indent)?;
+                    writeln!(f, "{}{keyword}if{reset} {punctuation}({negation}!{reset}", inner_indent)?;
+                    cond.fmt_inner(f, style, depth + 2)?;
+                    style.write_open(f, depth + 1, ")")?;
+                    write!(f, "{}", style.indent(depth + 2))?;
+                    Self::fmt_break(f, &style.theme, break_values)?;
+                    writeln!(f)?;
+                    style.write_close(f, depth + 1)?;
+                    style.write_close(f, depth)?;
+                }
+            },
+
+            Self::InfiniteLoop { body } => {
+                match (style.brace_style) {
+                    BraceStyle::KAndR  => writeln!(f, "{}{keyword}loop{reset} {punctuation}{{{reset}", indent)?,
+                    BraceStyle::Allman => {
+                        writeln!(f, "{}{keyword}loop{reset}", indent)?;
+                        writeln!(f, "{}{punctuation}{{{reset}", indent)?;
+                    }
+                }
+                body.fmt_inner(f, style, depth + 1)?;
+                style.write_close(f, depth)?;
+            },
+
+            Self::OnewayConditional { cond, body, negated } => {
+                if (*negated) {
+                    writeln!(f, "{}{keyword}if{reset} {punctuation}({negation}!{reset}", indent)?;
+                } else {
+                    writeln!(f, "{}{keyword}if{reset} {punctuation}({reset}", indent)?;
+                }
+                cond.fmt_inner(f, style, depth + 1)?;
+                style.write_open(f, depth, ")")?;
+                body.fmt_inner(f, style, depth + 1)?;
+                style.write_close(f, depth)?;
+            },
+
+            Self::OnewayReturnConditional { cond, body, negated } => {
+                if (*negated) {
+                    writeln!(f, "{}{keyword}if{reset} {punctuation}({negation}!{reset}", indent)?;
+                } else {
+                    writeln!(f, "{}{keyword}if{reset} {punctuation}({reset}", indent)?;
+                }
+                cond.fmt_inner(f, style, depth + 1)?;
+                style.write_open(f, depth, ")")?;
+                body.fmt_inner(f, style, depth + 1)?;
+                // A body that never finishes does not need a return after it.
+                if (! body.diverges()) {
+                    writeln!(f, "{}{return_keyword}return{reset}{muted};{reset}", style.indent(depth + 1))?;
+                }
+                style.write_close(f, depth)?;
+            },
+
+            Self::BreakConditional { cond, body, negated } => {
+                if (*negated) {
+                    writeln!(f, "{}{keyword}if{reset} {punctuation}({negation}!{reset}", indent)?;
+                } else {
+                    writeln!(f, "{}{keyword}if{reset} {punctuation}({reset}", indent)?;
+                }
+                cond.fmt_inner(f, style, depth + 1)?;
+                style.write_open(f, depth, ")")?;
+                body.fmt_inner(f, style, depth + 1)?;
+                writeln!(f, "{}{keyword}break{reset}{muted};{reset}", style.indent(depth + 1))?;
+                style.write_close(f, depth)?;
             },
 
             Self::TwowayConditional { cond, body_true, body_false } => {
-                writeln!(f, "{}\x1b[95m\x1b[1mif\x1b[0m \x1b[37m\x1b[1m(\x1b[0m", "  ".repeat(depth))?;
-                cond.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m) {{\x1b[0m", "  ".repeat(depth))?;
-                body_true.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m}}\x1b[0m \x1b[95m\x1b[1melse\x1b[0m \x1b[37m\x1b[1m{{\x1b[0m", "  ".repeat(depth))?;
-                body_false.fmt_inner(f, depth + 1)?;
-                writeln!(f, "{}\x1b[37m\x1b[1m}}\x1b[0m", "  ".repeat(depth))?;
+                writeln!(f, "{}{keyword}if{reset} {punctuation}({reset}", indent)?;
+                cond.fmt_inner(f, style, depth + 1)?;
+                style.write_open(f, depth, ")")?;
+                body_true.fmt_inner(f, style, depth + 1)?;
+                style.write_else(f, depth)?;
+                body_false.fmt_inner(f, style, depth + 1)?;
+                style.write_close(f, depth)?;
+            },
+
+            Self::Select { cond, then_val, else_val, dest } => {
+                writeln!(f, "{}{block}{}{reset} {muted}={reset} {punctuation}({reset}", indent, dest)?;
+                cond.fmt_inner(f, style, depth + 1)?;
+                writeln!(f, "{}{punctuation}){reset} {muted}?{reset} {} {muted}:{reset} {}{muted};{reset}", indent, then_val, else_val)?;
+            },
+
+            Self::CaseTest { value, values } => {
+                write!(f, "{}", indent)?;
+                let mut first = true;
+                for case in values {
+                    if (first) { first = false; }
+                    else { write!(f, " {punctuation}||{reset} ")?; }
+                    write!(f, "{} {punctuation}=={reset} {}", value, case)?;
+                }
+                writeln!(f)?;
+            },
+
+            Self::Switch { cond, cases, default } => {
+                writeln!(f, "{}{keyword}switch{reset} {punctuation}({reset}", indent)?;
+                cond.fmt_inner(f, style, depth + 1)?;
+                style.write_open(f, depth, ")")?;
+                let case_indent = style.indent(depth + 1);
+                for case in cases {
+                    write!(f, "{}", case_indent)?;
+                    Self::fmt_case_label(f, &style.theme, &case.ranges())?;
+                    case.body.fmt_inner(f, style, depth + 2)?;
+                    writeln!(f, "{}{keyword}break{reset}{muted};{reset}", style.indent(depth + 2))?;
+                }
+                if let Some(default) = default {
+                    writeln!(f, "{}{keyword}default{reset}{muted}:{reset}", case_indent)?;
+                    default.fmt_inner(f, style, depth + 2)?;
+                }
+                style.write_close(f, depth)?;
+            },
+
+            Self::SwitchLoop { cond, cases, default, step } => {
+                match (style.brace_style) {
+                    BraceStyle::KAndR  => writeln!(f, "{}{keyword}loop{reset} {punctuation}{{{reset}", indent)?,
+                    BraceStyle::Allman => {
+                        writeln!(f, "{}{keyword}loop{reset}", indent)?;
+                        writeln!(f, "{}{punctuation}{{{reset}", indent)?;
+                    }
+                }
+                let switch_indent = style.indent(depth + 1);
+                writeln!(f, "{}{keyword}switch{reset} {punctuation}({reset}", switch_indent)?;
+                cond.fmt_inner(f, style, depth + 2)?;
+                style.write_open(f, depth + 1, ")")?;
+                let case_indent = style.indent(depth + 2);
+                let fmt_arm     = |f : &mut fmt::Formatter<'_>, case : &CFRSwitchLoopCase| {
+                    case.body.fmt_inner(f, style, depth + 3)?;
+                    let jump = if (case.breaks) { "break" } else { "continue" };
+                    writeln!(f, "{}{keyword}{}{reset}{muted};{reset}", style.indent(depth + 3), jump)
+                };
+                for case in cases {
+                    write!(f, "{}", case_indent)?;
+                    Self::fmt_case_label(f, &style.theme, &case.ranges())?;
+                    fmt_arm(f, case)?;
+                }
+                writeln!(f, "{}{keyword}default{reset}{muted}:{reset}", case_indent)?;
+                fmt_arm(f, default)?;
+                style.write_close(f, depth + 1)?;
+                if (! step.groups.is_empty()) {
+                    style.write_between(f, depth, "continue")?;
+                    step.fmt_inner(f, style, depth + 1)?;
+                }
+                style.write_close(f, depth)?;
+            },
+
+            Self::ShortCircuit { lhs, rhs, disjunction, negated } => {
+                if (*negated) {
+                    writeln!(f, "{}{punctuation}({negation}!{reset}", indent)?;
+                } else {
+                    writeln!(f, "{}{punctuation}({reset}", indent)?;
+                }
+                lhs.fmt_inner(f, style, depth + 1)?;
+                let op = if (*disjunction) { "||" } else { "&&" };
+                writeln!(f, "{}{punctuation}) {} ({reset}", indent, op)?;
+                rhs.fmt_inner(f, style, depth + 1)?;
+                writeln!(f, "{}{punctuation}){reset}", indent)?;
+            },
+
+            Self::Unrecovered(UnrecoveredRegion { entry, exit }) => {
+                writeln!(f, "{}{error}unrecovered{reset} {block}({}...{}){reset}{muted};{reset}", indent, entry, exit)?
+            },
+
+            Self::Unstructured { blocks, edges } => {
+                match (style.brace_style) {
+                    BraceStyle::KAndR  => writeln!(f, "{}{error}unstructured{reset} {punctuation}{{{reset}", indent)?,
+                    BraceStyle::Allman => {
+                        writeln!(f, "{}{error}unstructured{reset}", indent)?;
+                        writeln!(f, "{}{punctuation}{{{reset}", indent)?;
+                    }
+                }
+                for label in blocks {
+                    writeln!(f, "{}{block}{}{reset}{muted}:{reset}", style.indent(depth + 1), label)?;
+                    CFRGroup::Block(label.clone()).fmt_inner(f, style, depth + 2)?;
+                    let mut targets = edges.iter().filter(|(from, _)| from == label).map(|(_, to)| to).peekable();
+                    if (targets.peek().is_some()) {
+                        write!(f, "{}{keyword}goto{reset} ", style.indent(depth + 2))?;
+                        let mut first = true;
+                        for to in targets {
+                            if (first) { first = false; }
+                            else { write!(f, "{muted},{reset} ")?; }
+                            write!(f, "{block}{}{reset}", to)?;
+                        }
+                        writeln!(f, "{muted};{reset}")?;
+                    }
+                }
+                style.write_close(f, depth)?;
             }
 
         }
         Ok(())
     }
+
+    /// Writes the `case` line of a switch arm with the given values.
+    fn fmt_case_label(f : &mut fmt::Formatter<'_>, theme : &Theme, ranges : &Vec<CFRCaseRange>) -> fmt::Result {
+        let Theme { keyword, muted, reset, .. } = theme;
+        write!(f, "{keyword}case{reset} ")?;
+        let mut first = true;
+        for range in ranges {
+            if (first) { first = false; }
+            else { write!(f, "{muted},{reset} ")?; }
+            if (range.is_single()) {
+                write!(f, "{}", range.start)?;
+            } else {
+                write!(f, "{} {muted}...{reset} {}", range.start, range.end)?;
+            }
+        }
+        writeln!(f, "{muted}:{reset}")
+    }
+
+    /// Writes a `break` statement, followed by the values it carries out of the loop.
+    fn fmt_break(f : &mut fmt::Formatter<'_>, theme : &Theme, break_values : &Vec<(Name, Operand)>) -> fmt::Result {
+        let Theme { keyword, muted, reset, .. } = theme;
+        write!(f, "{keyword}break{reset}")?;
+        let mut first = true;
+        for (_, value) in break_values {
+            if (first) { first = false; write!(f, " ")?; }
+            else { write!(f, "{muted},{reset} ")?; }
+            write!(f, "{}", value)?;
+        }
+        write!(f, "{muted};{reset}")
+    }
 }
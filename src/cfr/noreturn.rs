@@ -0,0 +1,110 @@
+use super::*;
+
+use std::collections::{
+    HashMap,
+    HashSet
+};
+
+use llvm_ir::{
+    BasicBlock,
+    Constant,
+    Function,
+    Instruction,
+    Module,
+    Operand,
+    Terminator,
+    function::FunctionAttribute
+};
+
+
+impl CFRGroups {
+
+
+    /// Replaces the blocks of `function` which end in a call to a `noreturn` function with `CFRGroup::NoReturnCall`.
+    /// 
+    /// A block qualifies when its last instruction is a call with the `noreturn` attribute, and it is followed by
+    ///   an `unreachable` terminator. The attribute is taken from the call site, or from the function being called
+    ///   if it is defined in `module`. Declarations do not keep their attributes in `llvm_ir`, so a call to a
+    ///   function which is only declared needs the attribute at the call site.
+    pub fn resolve_noreturn_calls(&mut self, function : &Function, module : &Module) -> () {
+        let calls = function.basic_blocks.iter()
+            .filter_map(|block| Some((block.name.clone(), Self::noreturn_callee(block, module)?)))
+            .collect::<HashMap<_, _>>();
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut NoReturnCalls { calls });
+    }
+
+    /// Replaces the blocks of `function` which end in `unreachable` with `CFRGroup::Unreachable`, so that a
+    ///   conditional leaving through one is not written with a `return`.
+    /// 
+    /// Blocks which end in a call to a `noreturn` function are left for `CFRGroups::resolve_noreturn_calls`,
+    ///   whichever is run first.
+    pub fn resolve_unreachables(&mut self, function : &Function, module : &Module) -> () {
+        let blocks = function.basic_blocks.iter()
+            .filter(|block| matches!(block.term, Terminator::Unreachable(_)))
+            .filter(|block| Self::noreturn_callee(block, module).is_none())
+            .map(|block| block.name.clone())
+            .collect::<HashSet<_>>();
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut Unreachables { blocks });
+    }
+
+    /// Returns `Some` if `block` ends in a call to a `noreturn` function, containing the name of the callee if it
+    ///   is known.
+    fn noreturn_callee(block : &BasicBlock, module : &Module) -> Option<Option<Name>> {
+        let Terminator::Unreachable(_) = &block.term else { return None };
+        let Some(Instruction::Call(call)) = block.instrs.last() else { return None };
+        let callee = match (call.function.as_ref().right()) {
+            Some(Operand::ConstantOperand(constant)) => match (&**constant) {
+                Constant::GlobalReference { name, .. } => Some(name.clone()),
+                _ => None
+            },
+            _ => None
+        };
+        let definition = match (&callee) {
+            Some(Name::Name(callee)) => module.get_func_by_name(callee),
+            _                        => None
+        };
+        let noreturn = call.function_attributes.contains(&FunctionAttribute::NoReturn)
+            || definition.is_some_and(|definition| definition.function_attributes.contains(&FunctionAttribute::NoReturn));
+        if (! noreturn) { return None; }
+        Some(callee)
+    }
+
+
+}
+
+
+/// Replaces blocks with `CFRGroup::NoReturnCall`.
+struct NoReturnCalls {
+    /// The callee of each block which ends in a `noreturn` call.
+    calls : HashMap<Name, Option<Name>>
+}
+
+impl CfrVisitor for NoReturnCalls {
+    fn visit_block(&mut self, name : Name) -> CFRGroups {
+        let group = match (self.calls.get(&name)) {
+            Some(callee) => CFRGroup::NoReturnCall { callee : callee.clone(), name },
+            None         => CFRGroup::Block(name)
+        };
+        CFRGroups { groups : vec![ group ] }
+    }
+}
+
+
+/// Replaces blocks with `CFRGroup::Unreachable`.
+struct Unreachables {
+    /// The blocks which end in `unreachable`.
+    blocks : HashSet<Name>
+}
+
+impl CfrVisitor for Unreachables {
+    fn visit_block(&mut self, name : Name) -> CFRGroups {
+        let group = if (self.blocks.contains(&name)) {
+            CFRGroup::Unreachable(name)
+        } else {
+            CFRGroup::Block(name)
+        };
+        CFRGroups { groups : vec![ group ] }
+    }
+}
@@ -0,0 +1,243 @@
+use super::*;
+
+use std::collections::HashMap;
+
+use llvm_ir::{
+    Function,
+    Instruction,
+    Operand,
+    Terminator
+};
+
+
+impl CFRGroups {
+
+
+    /// Lifts the `phi` instructions of `function` into assignments at the end of each predecessor.
+    /// 
+    /// Every predecessor which ends in an unconditional `br` gets a `CFRGroup::Assign` directly after its block,
+    ///   containing one assignment for each `phi` in the destination. The assignments in a group happen all at
+    ///   once, so `phi`s which read each other (such as a swap) stay correct.
+    /// 
+    /// A predecessor which ends in a conditional branch needs its edge split to hold the assignment. Where it is
+    ///   the last block of the condition of a `CFRGroup::OnewayConditional` or `CFRGroup::TwowayConditional`, the
+    ///   assignment goes at the start of the body the edge enters, or into a new `else` body if the edge skips
+    ///   the body of a `CFRGroup::OnewayConditional`. An edge straight to the exit of a
+    ///   `CFRGroup::TwowayConditional` puts it into the empty body on that side.
+    /// 
+    /// ```text
+    /// if (                     if (
+    ///   %bb1                     %bb1
+    /// ) {             ->       ) {
+    ///   %bb2                     %bb2
+    /// }                          %x = 2;
+    /// %bb3                     } else {
+    ///                            %x = 1;
+    ///                          }
+    ///                          %bb3
+    /// ```
+    /// 
+    /// The conditional branch which leaves a `CFRGroup::PostconditionLoop` has its operands attached to the loop
+    ///   as the values carried by its `break`. Any other conditional edge, such as one leaving a short circuit or
+    ///   the condition of another loop, has no group to hold the assignment, so its `phi` operands are left alone.
+    pub fn resolve_phis(&mut self, function : &Function) -> () {
+        // Find the assignments needed at the end of each predecessor, or on each conditional edge.
+        let mut assigns      : HashMap<Name, Vec<(Name, Operand)>>         = HashMap::new();
+        let mut edge_assigns : HashMap<(Name, Name), Vec<(Name, Operand)>> = HashMap::new();
+        for block in &function.basic_blocks {
+            for instr in &block.instrs {
+                let Instruction::Phi(phi) = instr else { continue };
+                for (value, pred) in &phi.incoming_values {
+                    let Some(pred_block) = function.basic_blocks.iter().find(|pred_block| &pred_block.name == pred) else { continue };
+                    match (&pred_block.term) {
+                        Terminator::Br(_) => {
+                            assigns.entry(pred.clone()).or_default().push((phi.dest.clone(), value.clone()));
+                        },
+                        Terminator::CondBr(_) => {
+                            edge_assigns.entry((pred.clone(), block.name.clone())).or_default().push((phi.dest.clone(), value.clone()));
+                        },
+                        _ => { }
+                    }
+                }
+            }
+        }
+        self.insert_assigns(&assigns);
+        self.insert_edge_assigns(&edge_assigns);
+        self.insert_break_values(function);
+    }
+
+    fn insert_assigns(&mut self, assigns : &HashMap<Name, Vec<(Name, Operand)>>) -> () {
+        let mut i = 0;
+        while (i < self.groups.len()) {
+            if let CFRGroup::Block(name) = &self.groups[i] {
+                if let Some(block_assigns) = assigns.get(name) {
+                    i += 1;
+                    self.groups.insert(i, CFRGroup::Assign(block_assigns.clone()));
+                }
+            } else {
+                for child in self.groups[i].children_mut() {
+                    child.insert_assigns(assigns);
+                }
+            }
+            i += 1;
+        }
+    }
+
+    fn insert_edge_assigns(&mut self, assigns : &HashMap<(Name, Name), Vec<(Name, Operand)>>) -> () {
+        for group in &mut self.groups {
+            for child in group.children_mut() {
+                child.insert_edge_assigns(assigns);
+            }
+            // The edges out of the conditional branch at the end of the condition.
+            let edges = |cond : &CFRGroups| match (cond.groups.last()) {
+                Some(CFRGroup::Block(pred)) => assigns.iter()
+                    .filter(|((from, _), _)| from == pred)
+                    .map(|((_, dest), edge_assigns)| (dest.clone(), edge_assigns.clone()))
+                    .collect::<Vec<_>>(),
+                _ => Vec::new()
+            };
+            match (group) {
+                CFRGroup::OnewayConditional { cond, body, negated } => {
+                    // The body is only entered from the condition, so an edge to anything else skips it.
+                    let mut skip_assigns = None;
+                    for (dest, edge_assigns) in edges(cond) {
+                        if (body.contains_block(&dest)) {
+                            body.groups.insert(0, CFRGroup::Assign(edge_assigns));
+                        } else {
+                            skip_assigns = Some(CFRGroups { groups : vec![ CFRGroup::Assign(edge_assigns) ] });
+                        }
+                    }
+                    if let Some(skip) = skip_assigns {
+                        let cond = std::mem::replace(cond, CFRGroups { groups : Vec::new() });
+                        let body = std::mem::replace(body, CFRGroups { groups : Vec::new() });
+                        *group = if (*negated) {
+                            CFRGroup::TwowayConditional { cond, body_true : skip, body_false : body }
+                        } else {
+                            CFRGroup::TwowayConditional { cond, body_true : body, body_false : skip }
+                        };
+                    }
+                },
+                CFRGroup::TwowayConditional { cond, body_true, body_false } => {
+                    for (dest, edge_assigns) in edges(cond) {
+                        // An edge straight to the exit skips its body, which is left empty, so the assignment
+                        //   goes there instead.
+                        let skips = ! body_true.contains_block(&dest) && ! body_false.contains_block(&dest);
+                        for body in [ &mut *body_true, &mut *body_false ] {
+                            let holds = if (skips) { body.groups.is_empty() } else { body.contains_block(&dest) };
+                            if (holds) {
+                                body.groups.insert(0, CFRGroup::Assign(edge_assigns.clone()));
+                            }
+                        }
+                    }
+                },
+                _ => { }
+            }
+        }
+    }
+
+    fn insert_break_values(&mut self, function : &Function) -> () {
+        for group in &mut self.groups {
+            if let CFRGroup::PostconditionLoop { cond, break_values } = group {
+                // The loop is left by the conditional branch at the end of its last block.
+                if let Some(CFRGroup::Block(latch)) = cond.groups.last() {
+                    let latch_block = function.basic_blocks.iter().find(|block| &block.name == latch);
+                    if let Some(Terminator::CondBr(term)) = latch_block.map(|block| &block.term) {
+                        let exit = if (cond.contains_block(&term.true_dest)) { &term.false_dest } else { &term.true_dest };
+                        if let Some(exit_block) = function.basic_blocks.iter().find(|block| &block.name == exit) {
+                            *break_values = exit_block.instrs.iter()
+                                .filter_map(|instr| match (instr) {
+                                    Instruction::Phi(phi) => phi.incoming_values.iter()
+                                        .find(|(_, pred)| pred == latch)
+                                        .map(|(value, _)| (phi.dest.clone(), value.clone())),
+                                    _ => None
+                                })
+                                .collect();
+                        }
+                    }
+                }
+            }
+            for child in group.children_mut() {
+                child.insert_break_values(function);
+            }
+        }
+    }
+
+    /// Returns `true` if the block `name` is anywhere in the tree.
+    fn contains_block(&self, name : &Name) -> bool {
+        self.groups.iter().any(|group| match (group) {
+            CFRGroup::Block(block) | CFRGroup::NoReturnCall { name : block, .. } | CFRGroup::Unreachable(block) | CFRGroup::AsmGoto { name : block, .. } => block == name,
+            group => group.children().into_iter().any(|(_, child)| child.contains_block(name))
+        })
+    }
+
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::tests::{
+        add_phi,
+        function
+    };
+    use llvm_ir::Constant;
+
+    fn assigns(group : Option<&CFRGroup>) -> Vec<u64> {
+        let Some(CFRGroup::Assign(assigns)) = group else { return Vec::new() };
+        assigns.iter().filter_map(|(_, value)| match (value) {
+            Operand::ConstantOperand(constant) => match (&**constant) {
+                Constant::Int { value, .. } => Some(*value),
+                _                           => None
+            },
+            _ => None
+        }).collect()
+    }
+
+    #[test]
+    fn skipped_body_gets_else() -> () {
+        // `if (a) { b } c`, where `c` takes `1` from `a` and `2` from `b`.
+        let mut function = function(&[ ("a", &[ "b", "c" ]), ("b", &[ "c" ]), ("c", &[]) ]);
+        add_phi(&mut function, "c", "x", &[ (1, "a"), (2, "b") ]);
+        let prims      = CFAPrim::find_all(ControlFlowGraph::new(&function)).expect("graph was not reduced");
+        let mut groups = CFRGroups::new(&prims).expect("groups were not recovered");
+        groups.resolve_phis(&function);
+        let [ CFRGroup::TwowayConditional { body_true, body_false, .. }, CFRGroup::Block(_) ] = groups.groups.as_slice() else { panic!("expected an if-else, found\n{}", groups) };
+        assert!(body_true.groups.first() == Some(&CFRGroup::Block(Name::from("b"))));
+        assert!(assigns(body_true.groups.get(1)) == [ 2 ]);
+        assert!(assigns(body_false.groups.first()) == [ 1 ] && body_false.groups.len() == 1);
+    }
+
+    #[test]
+    fn entered_body_assigned_first() -> () {
+        // `if (a) { b } else { c } d`, where `b` has a `phi` taking `1` from `a`.
+        let mut function = function(&[ ("a", &[ "b", "c" ]), ("b", &[ "d" ]), ("c", &[ "d" ]), ("d", &[]) ]);
+        add_phi(&mut function, "b", "x", &[ (1, "a") ]);
+        let prims      = CFAPrim::find_all(ControlFlowGraph::new(&function)).expect("graph was not reduced");
+        let mut groups = CFRGroups::new(&prims).expect("groups were not recovered");
+        groups.resolve_phis(&function);
+        let [ CFRGroup::TwowayConditional { body_true, body_false, .. }, CFRGroup::Block(_) ] = groups.groups.as_slice() else { panic!("expected an if-else, found\n{}", groups) };
+        assert!(assigns(body_true.groups.first()) == [ 1 ]);
+        assert!(body_false.groups == vec![ CFRGroup::Block(Name::from("c")) ]);
+    }
+
+    #[test]
+    fn empty_body_gets_skip() -> () {
+        // `if (a) { } else { b } c`, where `c` takes `1` from `a` and `2` from `b`.
+        let mut function = function(&[ ("a", &[ "c", "b" ]), ("b", &[ "c" ]), ("c", &[]) ]);
+        add_phi(&mut function, "c", "x", &[ (1, "a"), (2, "b") ]);
+        let mut groups = CFRGroups { groups : vec![
+            CFRGroup::TwowayConditional {
+                cond       : CFRGroups { groups : vec![ CFRGroup::Block(Name::from("a")) ] },
+                body_true  : CFRGroups { groups : Vec::new() },
+                body_false : CFRGroups { groups : vec![ CFRGroup::Block(Name::from("b")) ] }
+            },
+            CFRGroup::Block(Name::from("c"))
+        ] };
+        groups.resolve_phis(&function);
+        let [ CFRGroup::TwowayConditional { body_true, body_false, .. }, CFRGroup::Block(_) ] = groups.groups.as_slice() else { panic!("expected an if-else, found\n{}", groups) };
+        assert!(assigns(body_true.groups.first()) == [ 1 ] && body_true.groups.len() == 1);
+        assert!(assigns(body_false.groups.get(1)) == [ 2 ]);
+    }
+
+}
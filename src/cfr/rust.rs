@@ -0,0 +1,297 @@
+use super::*;
+
+
+impl CFRGroups {
+
+
+    /// Formats the groups as Rust-like pseudocode, without colour.
+    /// 
+    /// Conditions are written without parentheses, and a condition made of more than one group is written as a
+    ///   block expression. Switches are written as `match`, with an empty `_` arm when there is no default,
+    ///   and runs of consecutive case values are written as ranges.
+    /// 
+    /// ```text
+    /// %start
+    /// while %bb1 {
+    ///     if %bb2 {
+    ///         %bb3
+    ///     }
+    ///     if %bb4 {
+    ///         %bb5
+    ///     } else {
+    ///         %bb6
+    ///     }
+    /// }
+    /// if !%bb7 {
+    ///     loop {
+    ///         if !%bb8 { break; }
+    ///     }
+    ///     %bb10
+    /// }
+    /// %bb13
+    /// ```
+    /// 
+    /// The `step` of a `CFRGroup::PreconditionLoop` is written at the end of its body. Rust has no C-style `for`,
+    ///   so a `CFRGroup::ForLoop` is written as its `init`, followed by a `while` loop.
+    pub fn to_rust_pseudocode(&self) -> String {
+        let mut out = String::new();
+        self.write_rust(&mut out, 0);
+        out
+    }
+
+    fn write_rust(&self, out : &mut String, depth : usize) -> () {
+        for group in &self.groups {
+            group.write_rust(out, depth);
+        }
+    }
+
+    /// Writes the groups as a condition, followed by a space.
+    fn write_rust_cond(&self, out : &mut String, depth : usize, negated : bool) -> () {
+        if (negated) { out.push('!'); }
+        match (self.groups.as_slice()) {
+            [ CFRGroup::Block(name) ] => out.push_str(&format!("{} ", name)),
+            [ CFRGroup::CaseTest { value, values } ] => out.push_str(&format!("{} ", rust_case_test(value, values))),
+            [ CFRGroup::ShortCircuit { lhs, rhs, disjunction, negated : lhs_negated } ] => {
+                if (negated) { out.push('('); }
+                lhs.write_rust_cond(out, depth, *lhs_negated);
+                out.push_str(if (*disjunction) { "|| " } else { "&& " });
+                rhs.write_rust_cond(out, depth, false);
+                if (negated) {
+                    out.pop();
+                    out.push_str(") ");
+                }
+            },
+            _ => {
+                out.push_str("{\n");
+                self.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}} ", rust_indent(depth)));
+            }
+        }
+    }
+
+
+}
+
+
+impl CFRGroup {
+    fn write_rust(&self, out : &mut String, depth : usize) -> () {
+        let indent = rust_indent(depth);
+        match (self) {
+
+            Self::Block(name) => {
+                out.push_str(&format!("{}{}\n", indent, name));
+            },
+
+            Self::NoReturnCall { name, callee } => {
+                out.push_str(&format!("{}{}\n", indent, name));
+                match (callee) {
+                    Some(Name::Name(callee))   => out.push_str(&format!("{}@{}();\n", indent, callee)),
+                    Some(Name::Number(callee)) => out.push_str(&format!("{}@{}();\n", indent, callee)),
+                    None                       => out.push_str(&format!("{}<unknown>();\n", indent))
+                }
+            },
+
+            Self::Unreachable(name) => {
+                out.push_str(&format!("{}{}\n", indent, name));
+                out.push_str(&format!("{}unreachable!();\n", indent));
+            },
+
+            Self::AsmGoto { name, labels } => {
+                let labels = labels.iter().map(|label| label.to_string()).collect::<Vec<_>>();
+                out.push_str(&format!("{}{}\n", indent, name));
+                out.push_str(&format!("{}// asm goto {}\n", indent, labels.join(", ")));
+            },
+
+            Self::Assign(assigns) => {
+                let dests  = assigns.iter().map(|(dest, _)| dest.to_string()).collect::<Vec<_>>();
+                let values = assigns.iter().map(|(_, value)| value.to_string()).collect::<Vec<_>>();
+                if (assigns.len() == 1) {
+                    out.push_str(&format!("{}{} = {};\n", indent, dests[0], values[0]));
+                } else {
+                    out.push_str(&format!("{}({}) = ({});\n", indent, dests.join(", "), values.join(", ")));
+                }
+            },
+
+            Self::PreconditionLoop { cond, body, negated, step } => {
+                out.push_str(&format!("{}while ", indent));
+                cond.write_rust_cond(out, depth, *negated);
+                out.push_str("{\n");
+                body.write_rust(out, depth + 1);
+                step.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::ForLoop { init, cond, step, body, negated } => {
+                init.write_rust(out, depth);
+                out.push_str(&format!("{}while ", indent));
+                cond.write_rust_cond(out, depth, *negated);
+                out.push_str("{\n");
+                body.write_rust(out, depth + 1);
+                step.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::PostconditionLoop { cond, break_values } => {
+                out.push_str(&format!("{}loop {{\n", indent));
+                out.push_str(&format!("{}if ", rust_indent(depth + 1)));
+                cond.write_rust_cond(out, depth + 1, true);
+                let values = break_values.iter().map(|(_, value)| value.to_string()).collect::<Vec<_>>();
+                match (values.len()) {
+                    0 => out.push_str("{ break; }\n"),
+                    1 => out.push_str(&format!("{{ break {}; }}\n", values[0])),
+                    _ => out.push_str(&format!("{{ break ({}); }}\n", values.join(", ")))
+                }
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::InfiniteLoop { body } => {
+                out.push_str(&format!("{}loop {{\n", indent));
+                body.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::OnewayConditional { cond, body, negated } => {
+                out.push_str(&format!("{}if ", indent));
+                cond.write_rust_cond(out, depth, *negated);
+                out.push_str("{\n");
+                body.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::OnewayReturnConditional { cond, body, negated } => {
+                out.push_str(&format!("{}if ", indent));
+                cond.write_rust_cond(out, depth, *negated);
+                out.push_str("{\n");
+                body.write_rust(out, depth + 1);
+                // A body that never finishes does not need a return after it.
+                if (! body.diverges()) {
+                    out.push_str(&format!("{}return;\n", rust_indent(depth + 1)));
+                }
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::BreakConditional { cond, body, negated } => {
+                out.push_str(&format!("{}if ", indent));
+                cond.write_rust_cond(out, depth, *negated);
+                out.push_str("{\n");
+                body.write_rust(out, depth + 1);
+                out.push_str(&format!("{}break;\n", rust_indent(depth + 1)));
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::Select { cond, then_val, else_val, dest } => {
+                out.push_str(&format!("{}{} = if ", indent, dest));
+                cond.write_rust_cond(out, depth, false);
+                out.push_str(&format!("{{ {} }} else {{ {} }};\n", then_val, else_val));
+            },
+
+            Self::CaseTest { value, values } => {
+                out.push_str(&format!("{}{}\n", indent, rust_case_test(value, values)));
+            },
+
+            Self::TwowayConditional { cond, body_true, body_false } => {
+                out.push_str(&format!("{}if ", indent));
+                cond.write_rust_cond(out, depth, false);
+                out.push_str("{\n");
+                body_true.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}} else {{\n", indent));
+                body_false.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::Switch { cond, cases, default } => {
+                out.push_str(&format!("{}match ", indent));
+                cond.write_rust_cond(out, depth, false);
+                out.push_str("{\n");
+                let arm_indent = rust_indent(depth + 1);
+                for case in cases {
+                    let values = case.ranges().iter().map(|range| {
+                        if (range.is_single()) { range.start.to_string() }
+                        else { format!("{}..={}", range.start, range.end) }
+                    }).collect::<Vec<_>>();
+                    out.push_str(&format!("{}{} => {{\n", arm_indent, values.join(" | ")));
+                    case.body.write_rust(out, depth + 2);
+                    out.push_str(&format!("{}}},\n", arm_indent));
+                }
+                match (default) {
+                    Some(default) => {
+                        out.push_str(&format!("{}_ => {{\n", arm_indent));
+                        default.write_rust(out, depth + 2);
+                        out.push_str(&format!("{}}}\n", arm_indent));
+                    },
+                    None => out.push_str(&format!("{}_ => {{ }}\n", arm_indent))
+                }
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            // Arms which go around the loop again fall out of the `match`, so that the step is run after them.
+            Self::SwitchLoop { cond, cases, default, step } => {
+                out.push_str(&format!("{}loop {{\n", indent));
+                let match_indent = rust_indent(depth + 1);
+                let arm_indent   = rust_indent(depth + 2);
+                out.push_str(&format!("{}match ", match_indent));
+                cond.write_rust_cond(out, depth + 1, false);
+                out.push_str("{\n");
+                let write_arm = |out : &mut String, pattern : String, case : &CFRSwitchLoopCase| {
+                    out.push_str(&format!("{}{} => {{\n", arm_indent, pattern));
+                    case.body.write_rust(out, depth + 3);
+                    if (case.breaks) {
+                        out.push_str(&format!("{}break;\n", rust_indent(depth + 3)));
+                    }
+                    out.push_str(&format!("{}}},\n", arm_indent));
+                };
+                for case in cases {
+                    let values = case.ranges().iter().map(|range| {
+                        if (range.is_single()) { range.start.to_string() }
+                        else { format!("{}..={}", range.start, range.end) }
+                    }).collect::<Vec<_>>();
+                    write_arm(out, values.join(" | "), case);
+                }
+                write_arm(out, "_".to_string(), default);
+                out.push_str(&format!("{}}}\n", match_indent));
+                step.write_rust(out, depth + 1);
+                out.push_str(&format!("{}}}\n", indent));
+            },
+
+            Self::ShortCircuit { .. } => {
+                out.push_str(&indent);
+                CFRGroups { groups : vec![ self.clone() ] }.write_rust_cond(out, depth, false);
+                out.pop();
+                out.push_str(";\n");
+            },
+
+            Self::Unrecovered(UnrecoveredRegion { entry, exit }) => {
+                out.push_str(&format!("{}// unrecovered ({}...{})\n", indent, entry, exit));
+            },
+
+            // Rust has no `goto`, so each block is a labelled block, ending in a comment listing where it goes.
+            //   Numbered blocks are prefixed with `bb`, so that their labels are legal.
+            Self::Unstructured { blocks, edges } => {
+                out.push_str(&format!("{}// unstructured\n", indent));
+                for label in blocks {
+                    let node : CFGNode = label.into();
+                    out.push_str(&format!("{}'{}: {{\n", indent, node.display_name("bb")));
+                    CFRGroup::Block(label.clone()).write_rust(out, depth + 1);
+                    let targets = edges.iter().filter(|(from, _)| from == label).map(|(_, to)| to.to_string()).collect::<Vec<_>>();
+                    if (! targets.is_empty()) {
+                        out.push_str(&format!("{}// goto {}\n", rust_indent(depth + 1), targets.join(", ")));
+                    }
+                    out.push_str(&format!("{}}}\n", indent));
+                }
+            }
+
+        }
+    }
+}
+
+
+/// Get the indentation for the given depth, using four spaces per level.
+fn rust_indent(depth : usize) -> String {
+    "    ".repeat(depth)
+}
+
+/// Writes a `CFRGroup::CaseTest` as a `matches!`, such as `matches!(x, 1 | 2)`.
+fn rust_case_test(value : &Operand, values : &[CFGCaseValue]) -> String {
+    let values = values.iter().map(|case| case.to_string()).collect::<Vec<_>>();
+    format!("matches!({}, {})", value, values.join(" | "))
+}
@@ -0,0 +1,95 @@
+use super::*;
+
+use llvm_ir::{
+    Function,
+    Instruction,
+    Terminator
+};
+
+
+impl CFRGroups {
+
+
+    /// Replaces each `CFRGroup::TwowayConditional` which only chooses the value of a `phi` with a
+    ///   `CFRGroup::Select`.
+    /// 
+    /// Both bodies must be a single block of `function` with no instructions, which branches straight to the same
+    ///   exit. The exit must have exactly one `phi`, with one incoming value from each body. A body may also be
+    ///   followed by the `CFRGroup::Assign` left by `CFRGroups::resolve_phis`, which is replaced too.
+    /// 
+    /// ```text
+    /// %start                   %start
+    /// if (                     %x = (
+    ///   %bb1                     %bb1
+    /// ) {             ->       ) ? 1 : 2;
+    ///   %bb2                   %bb4
+    /// } else {
+    ///   %bb3
+    /// }
+    /// %bb4
+    /// ```
+    /// 
+    /// `CFRGroups::simplify_empty_conditionals` removes the bodies these are found from, so this should be run
+    ///   before it.
+    pub fn recover_selects(&mut self, function : &Function) -> () {
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut Selects { function });
+    }
+
+
+}
+
+
+/// Replaces conditionals which only choose the value of a `phi`.
+struct Selects<'l> {
+    function : &'l Function
+}
+
+impl<'l> Selects<'l> {
+
+    /// Gets the block of `body` and the block it branches to, if it does nothing else.
+    fn empty_body<'b>(&'b self, body : &'b CFRGroups) -> Option<(&'b Name, &'b Name)> {
+        let name = match (body.groups.as_slice()) {
+            [ CFRGroup::Block(name) ]                      => name,
+            [ CFRGroup::Block(name), CFRGroup::Assign(_) ] => name,
+            _                                              => return None
+        };
+        let block = self.function.basic_blocks.iter().find(|block| &block.name == name)?;
+        if (! block.instrs.is_empty()) { return None; }
+        let Terminator::Br(br) = &block.term else { return None };
+        Some((name, &br.dest))
+    }
+
+    /// Gets the `phi` chosen between `body_true` and `body_false`, as its destination and the value from each body.
+    fn find_select(&self, body_true : &CFRGroups, body_false : &CFRGroups) -> Option<(Name, Operand, Operand)> {
+        let (block_true,  exit)       = self.empty_body(body_true)?;
+        let (block_false, exit_false) = self.empty_body(body_false)?;
+        if (exit != exit_false) { return None; }
+        let exit = self.function.basic_blocks.iter().find(|block| &block.name == exit)?;
+
+        // Only one value can be chosen, so any other `phi` keeps the conditional.
+        let mut phis = exit.instrs.iter().filter_map(|instr| match (instr) {
+            Instruction::Phi(phi) => Some(phi),
+            _                     => None
+        });
+        let phi = phis.next()?;
+        if (phis.next().is_some() || phi.incoming_values.len() != 2) { return None; }
+
+        let value_from = |pred : &Name| phi.incoming_values.iter().find(|(_, from)| from == pred).map(|(value, _)| value.clone());
+        Some((phi.dest.clone(), value_from(block_true)?, value_from(block_false)?))
+    }
+
+}
+
+impl<'l> CfrVisitor for Selects<'l> {
+    fn visit_twoway_conditional(&mut self, cond : CFRGroups, body_true : CFRGroups, body_false : CFRGroups) -> CFRGroups {
+        let cond       = self.visit_groups(cond);
+        let body_true  = self.visit_groups(body_true);
+        let body_false = self.visit_groups(body_false);
+        let group = match (self.find_select(&body_true, &body_false)) {
+            Some((dest, then_val, else_val)) => CFRGroup::Select { cond, then_val, else_val, dest },
+            None                             => CFRGroup::TwowayConditional { cond, body_true, body_false }
+        };
+        CFRGroups { groups : vec![ group ] }
+    }
+}
@@ -0,0 +1,238 @@
+use super::*;
+
+
+/// The width past which a list is split over multiple lines.
+const MAX_WIDTH : usize = 100;
+
+
+impl CFRGroups {
+
+
+    /// Formats the groups as S-expressions, one top level group per line, without colour.
+    /// 
+    /// Blocks are atoms, and every other group is a list tagged with its kind, holding a list for each of its
+    ///   nested groups tagged with its role. Negated groups are marked with `:negated` after their kind. Names
+    ///   and operands which are not plain atoms are written as strings. Lists which are too wide to fit on one
+    ///   line have each nested list on its own line.
+    /// 
+    /// ```text
+    /// %start
+    /// (while
+    ///   (condition %bb1)
+    ///   (body (if (condition %bb2) (body %bb3)) (if-else (condition %bb4) (then %bb5) (else %bb6)))
+    ///   (step))
+    /// (if :negated (condition %bb7) (body (loop (condition %bb8)) %bb10))
+    /// %bb13
+    /// ```
+    /// 
+    /// Switch cases are written with their values first, such as `(case (1 (range 3 5)) %bb2)`. The arms of a switch
+    ///   loop are marked with `:break` or `:continue` after their values.
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        for expr in self.to_sexprs() {
+            expr.write(&mut out, 0);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn to_sexprs(&self) -> Vec<SExpr> {
+        self.groups.iter().map(CFRGroup::to_sexpr).collect()
+    }
+
+
+}
+
+
+impl CFRGroup {
+    fn to_sexpr(&self) -> SExpr {
+        let mut list = vec![ SExpr::atom(self.kind()) ];
+        match (self) {
+
+            Self::Block(name) => { return SExpr::name(name); },
+
+            Self::NoReturnCall { name, callee } => {
+                list.push(SExpr::name(name));
+                if let Some(callee) = callee {
+                    list.push(SExpr::atom(&format!("@{}", callee.to_string().trim_start_matches('%'))));
+                }
+            },
+
+            Self::Unreachable(name) => { list.push(SExpr::name(name)); },
+
+            Self::AsmGoto { name, labels } => {
+                list.push(SExpr::name(name));
+                list.push(SExpr::List([ SExpr::atom("goto") ].into_iter().chain(labels.iter().map(SExpr::name)).collect()));
+            },
+
+            Self::Assign(assigns) => {
+                list.extend(assigns.iter().map(|(name, value)| SExpr::assign(name, value)));
+            },
+
+            Self::PostconditionLoop { cond, break_values } => {
+                list.push(SExpr::role("condition", cond));
+                if (! break_values.is_empty()) {
+                    list.push(SExpr::List([ SExpr::atom("break") ].into_iter()
+                        .chain(break_values.iter().map(|(name, value)| SExpr::assign(name, value)))
+                        .collect()
+                    ));
+                }
+            },
+
+            // Cases are written with their values, so are not taken from `children`.
+            Self::Switch { cond, cases, default } => {
+                list.push(SExpr::role("condition", cond));
+                for case in cases {
+                    let mut arm = vec![ SExpr::atom("case"), SExpr::ranges(&case.ranges()) ];
+                    arm.extend(case.body.to_sexprs());
+                    list.push(SExpr::List(arm));
+                }
+                if let Some(default) = default {
+                    list.push(SExpr::role("default", default));
+                }
+            },
+
+            Self::SwitchLoop { cond, cases, default, step } => {
+                let jump = |case : &CFRSwitchLoopCase| SExpr::atom(if (case.breaks) { ":break" } else { ":continue" });
+                list.push(SExpr::role("condition", cond));
+                for case in cases {
+                    let mut arm = vec![ SExpr::atom("case"), SExpr::ranges(&case.ranges()), jump(case) ];
+                    arm.extend(case.body.to_sexprs());
+                    list.push(SExpr::List(arm));
+                }
+                let mut arm = vec![ SExpr::atom("default"), jump(default) ];
+                arm.extend(default.body.to_sexprs());
+                list.push(SExpr::List(arm));
+                list.push(SExpr::role("step", step));
+            },
+
+            Self::Unrecovered(UnrecoveredRegion { entry, exit }) => {
+                list.push(SExpr::name(entry));
+                list.push(SExpr::name(exit));
+            },
+
+            Self::Unstructured { blocks, edges } => {
+                for label in blocks {
+                    let mut block = vec![ SExpr::atom("block"), SExpr::name(label) ];
+                    let targets = edges.iter().filter(|(from, _)| from == label).map(|(_, to)| SExpr::name(to)).collect::<Vec<_>>();
+                    if (! targets.is_empty()) {
+                        block.push(SExpr::List([ SExpr::atom("goto") ].into_iter().chain(targets).collect()));
+                    }
+                    list.push(SExpr::List(block));
+                }
+            },
+
+            Self::PreconditionLoop        { negated, .. }
+            | Self::ForLoop                 { negated, .. }
+            | Self::OnewayConditional       { negated, .. }
+            | Self::OnewayReturnConditional { negated, .. }
+            | Self::BreakConditional        { negated, .. }
+            | Self::ShortCircuit            { negated, .. } => {
+                if (*negated) { list.push(SExpr::atom(":negated")); }
+                list.extend(self.children().into_iter().map(|(role, child)| SExpr::role(role, child)));
+            },
+
+            Self::Select { cond, then_val, else_val, dest } => {
+                list.push(SExpr::name(dest));
+                list.push(SExpr::role("condition", cond));
+                list.push(SExpr::List(vec![ SExpr::atom("then"), SExpr::atom(&then_val.to_string()) ]));
+                list.push(SExpr::List(vec![ SExpr::atom("else"), SExpr::atom(&else_val.to_string()) ]));
+            },
+
+            Self::CaseTest { value, values } => {
+                list.push(SExpr::atom(&value.to_string()));
+                list.extend(values.iter().map(|case| SExpr::atom(&case.to_string())));
+            },
+
+            Self::InfiniteLoop { .. } | Self::TwowayConditional { .. } => {
+                list.extend(self.children().into_iter().map(|(role, child)| SExpr::role(role, child)));
+            }
+
+        }
+        SExpr::List(list)
+    }
+}
+
+
+/// A single S-expression, before it is written.
+enum SExpr {
+    Atom(String),
+    List(Vec<SExpr>)
+}
+
+impl SExpr {
+
+    /// Creates an atom, written as a string if it would not be read back as a single atom.
+    fn atom(text : &str) -> Self {
+        let is_plain = ! text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || "%@._-:$".contains(c));
+        if (is_plain) {
+            SExpr::Atom(text.to_string())
+        } else {
+            SExpr::Atom(format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")))
+        }
+    }
+
+    fn name(name : &Name) -> Self { Self::atom(&name.to_string()) }
+
+    /// Creates a list of the name assigned to, and the value assigned to it.
+    fn assign(name : &Name, value : &Operand) -> Self {
+        SExpr::List(vec![ Self::name(name), Self::atom(&value.to_string()) ])
+    }
+
+    /// Creates a list of the values of a switch arm, with each range written as `(range start end)`.
+    fn ranges(ranges : &[CFRCaseRange]) -> Self {
+        SExpr::List(ranges.iter().map(|range| if (range.is_single()) {
+            SExpr::atom(&range.start.to_string())
+        } else {
+            SExpr::List(vec![ SExpr::atom("range"), SExpr::atom(&range.start.to_string()), SExpr::atom(&range.end.to_string()) ])
+        }).collect())
+    }
+
+    /// Creates a list of `groups`, tagged with `role`.
+    fn role(role : &str, groups : &CFRGroups) -> Self {
+        let mut list = vec![ SExpr::atom(role) ];
+        list.extend(groups.to_sexprs());
+        SExpr::List(list)
+    }
+
+    /// Writes the expression on a single line.
+    fn write_flat(&self, out : &mut String) -> () {
+        match (self) {
+            Self::Atom(atom) => out.push_str(atom),
+            Self::List(list) => {
+                out.push('(');
+                for (i, expr) in list.iter().enumerate() {
+                    if (i > 0) { out.push(' '); }
+                    expr.write_flat(out);
+                }
+                out.push(')');
+            }
+        }
+    }
+
+    /// Writes the expression, splitting lists which are too wide so that each nested list is on its own line.
+    fn write(&self, out : &mut String, depth : usize) -> () {
+        let mut flat = String::new();
+        self.write_flat(&mut flat);
+        let Self::List(list) = self else { out.push_str(&flat); return; };
+        if (depth * 2 + flat.len() <= MAX_WIDTH) {
+            out.push_str(&flat);
+            return;
+        }
+        // The leading atoms stay on the first line, such as the kind and `:negated`.
+        let head = list.iter().take_while(|expr| matches!(expr, Self::Atom(_))).count();
+        out.push('(');
+        for (i, expr) in list.iter().enumerate() {
+            if (i < head) {
+                if (i > 0) { out.push(' '); }
+                expr.write_flat(out);
+            } else {
+                out.push('\n');
+                out.push_str(&"  ".repeat(depth + 1));
+                expr.write(out, depth + 1);
+            }
+        }
+        out.push(')');
+    }
+
+}
@@ -0,0 +1,150 @@
+use super::*;
+
+
+impl CFRGroups {
+
+    /// Formats the groups as a parenthesised condition on a single line, or `None` if they are not a single group
+    ///   which can be.
+    fn fmt_cond_line(&self, style : &FormatStyle, negated : bool) -> Option<String> {
+        let Theme { punctuation, negation, reset, .. } = &style.theme;
+        let [ group ] = self.groups.as_slice() else { return None };
+        let negation = if (negated) { format!("{negation}!{reset}") } else { String::new() };
+        Some(format!("{punctuation}({reset}{}{}{punctuation}){reset}", negation, group.fmt_line(style)?))
+    }
+
+    /// Formats the groups as a braced body on a single line, followed by `suffix` inside of the braces, or `None`
+    ///   if there is more than one group, so that statements are never run together on one line.
+    fn fmt_body_line(&self, style : &FormatStyle, suffix : Option<String>) -> Option<String> {
+        let Theme { punctuation, reset, .. } = &style.theme;
+        let mut body = match (self.groups.as_slice()) {
+            []        => String::new(),
+            [ group ] => group.fmt_line(style)?,
+            _         => return None
+        };
+        if let Some(suffix) = suffix {
+            if (! body.is_empty()) { body.push(' '); }
+            body.push_str(&suffix);
+        }
+        if (body.is_empty()) {
+            Some(format!("{punctuation}{{ }}{reset}"))
+        } else {
+            Some(format!("{punctuation}{{{reset} {} {punctuation}}}{reset}", body))
+        }
+    }
+
+}
+
+
+impl CFRGroup {
+
+    /// Writes the group on one line, in place of its usual layout, if it has nested groups and fits within
+    ///   `FormatStyle::max_width`. Returns `Ok(false)` if the usual layout should be used instead.
+    pub(super) fn fmt_collapsed(&self, f : &mut fmt::Formatter<'_>, style : &FormatStyle, indent : &str) -> Result<bool, fmt::Error> {
+        let Some(max_width) = style.max_width else { return Ok(false) };
+        if (self.children().is_empty()) { return Ok(false); }
+        let Some(line) = self.fmt_line(style) else { return Ok(false) };
+        if (indent.chars().count() + visible_width(&line) > max_width) { return Ok(false); }
+        writeln!(f, "{}{}", indent, line)?;
+        Ok(true)
+    }
+
+    /// Formats the group on a single line, or `None` if it has no single line form.
+    /// 
+    /// Groups without nested groups are written as usual, with their lines joined by spaces. `for` loops, selects,
+    ///   switches, loops which carry values out, and unstructured regions are always written over several lines.
+    fn fmt_line(&self, style : &FormatStyle) -> Option<String> {
+        let Theme { keyword, return_keyword, punctuation, muted, reset, .. } = &style.theme;
+        match (self) {
+
+            Self::PreconditionLoop { cond, body, negated, step } => {
+                let mut line = format!("{keyword}while{reset} {} {}", cond.fmt_cond_line(style, *negated)?, body.fmt_body_line(style, None)?);
+                if (! step.groups.is_empty()) {
+                    line.push_str(&format!(" {keyword}continue{reset} {}", step.fmt_body_line(style, None)?));
+                }
+                Some(line)
+            },
+
+            Self::PostconditionLoop { cond, break_values } if (break_values.is_empty()) => {
+                let test = format!("{keyword}if{reset} {} {punctuation}{{{reset} {keyword}break{reset}{muted};{reset} {punctuation}}}{reset}", cond.fmt_cond_line(style, true)?);
+                Some(format!("{keyword}loop{reset} {punctuation}{{{reset} {} {punctuation}}}{reset}", test))
+            },
+
+            Self::InfiniteLoop { body } => Some(format!("{keyword}loop{reset} {}", body.fmt_body_line(style, None)?)),
+
+            Self::OnewayConditional { cond, body, negated } => {
+                Some(format!("{keyword}if{reset} {} {}", cond.fmt_cond_line(style, *negated)?, body.fmt_body_line(style, None)?))
+            },
+
+            Self::OnewayReturnConditional { cond, body, negated } => {
+                // A body that never finishes does not need a return after it.
+                let suffix = (! body.diverges()).then(|| format!("{return_keyword}return{reset}{muted};{reset}"));
+                Some(format!("{keyword}if{reset} {} {}", cond.fmt_cond_line(style, *negated)?, body.fmt_body_line(style, suffix)?))
+            },
+
+            Self::BreakConditional { cond, body, negated } => {
+                let suffix = Some(format!("{keyword}break{reset}{muted};{reset}"));
+                Some(format!("{keyword}if{reset} {} {}", cond.fmt_cond_line(style, *negated)?, body.fmt_body_line(style, suffix)?))
+            },
+
+            Self::TwowayConditional { cond, body_true, body_false } => {
+                Some(format!("{keyword}if{reset} {} {} {keyword}else{reset} {}",
+                    cond.fmt_cond_line(style, false)?, body_true.fmt_body_line(style, None)?, body_false.fmt_body_line(style, None)?
+                ))
+            },
+
+            Self::ShortCircuit { lhs, rhs, disjunction, negated } => {
+                let lhs = lhs.fmt_cond_line(style, *negated)?;
+                let rhs = rhs.fmt_cond_line(style, false)?;
+                let op  = if (*disjunction) { "||" } else { "&&" };
+                Some(format!("{} {punctuation}{}{reset} {}", lhs, op, rhs))
+            },
+
+            Self::ForLoop { .. }
+            | Self::PostconditionLoop { .. }
+            | Self::Select { .. }
+            | Self::Switch { .. }
+            | Self::SwitchLoop { .. }
+            | Self::Unstructured { .. } => None,
+
+            Self::Block(_)
+            | Self::NoReturnCall { .. }
+            | Self::Unreachable(_)
+            | Self::AsmGoto { .. }
+            | Self::Assign(_)
+            | Self::CaseTest { .. }
+            | Self::Unrecovered(_) => {
+                let text = Expanded(self, &style.theme).to_string();
+                Some(text.lines().collect::<Vec<_>>().join(" "))
+            }
+
+        }
+    }
+
+}
+
+
+/// A group written in its usual layout, at no depth.
+struct Expanded<'l>(&'l CFRGroup, &'l Theme);
+
+impl<'l> fmt::Display for Expanded<'l> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_themed(f, self.1)
+    }
+}
+
+
+/// Gets the number of characters in `text` which are shown, skipping the escape sequences which colour it.
+fn visible_width(text : &str) -> usize {
+    let mut width     = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        if (in_escape) {
+            if (c == 'm') { in_escape = false; }
+        } else if (c == '\x1b') {
+            in_escape = true;
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
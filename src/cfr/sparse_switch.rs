@@ -0,0 +1,100 @@
+use super::*;
+
+use llvm_ir::{
+    Function,
+    Terminator
+};
+
+
+impl CFRGroups {
+
+
+    /// Lowers each `CFRGroup::Switch` with at most `max_values` case values into a chain of conditionals, each
+    ///   testing for the values of one case with a `CFRGroup::CaseTest`.
+    /// 
+    /// Cases which do nothing are tested together, so a switch whose only real arm is its default becomes a single
+    ///   negated conditional.
+    /// 
+    /// ```text
+    /// switch (                 if (!
+    ///   %bb1                     %bb1
+    /// ) {                        %x == 1 || %x == 2
+    ///   case 1, 2:    ->       ) {
+    ///     break;                 %bb2
+    ///   default:               }
+    ///     %bb2
+    /// }
+    /// ```
+    /// 
+    /// Switches with more values are left alone, so dense switches stay as they are. Only switches whose
+    ///   condition ends in a block with a `switch` terminator can be lowered.
+    pub fn lower_sparse_switches(&mut self, function : &Function, max_values : usize) -> () {
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut SparseSwitches { function, max_values });
+    }
+
+
+}
+
+
+/// Lowers switches with few values into chains of conditionals.
+struct SparseSwitches<'l> {
+    function   : &'l Function,
+    max_values : usize
+}
+
+impl<'l> SparseSwitches<'l> {
+
+    /// Gets the value switched on by the block `name`.
+    fn switch_value(&self, name : &Name) -> Option<Operand> {
+        let block = self.function.basic_blocks.iter().find(|block| &block.name == name)?;
+        let Terminator::Switch(term) = &block.term else { return None };
+        Some(term.operand.clone())
+    }
+
+}
+
+impl<'l> CfrVisitor for SparseSwitches<'l> {
+    fn visit_switch(&mut self, cond : CFRGroups, cases : Vec<CFRSwitchCase>, default : Option<CFRGroups>) -> CFRGroups {
+        let mut cond = self.visit_groups(cond);
+        let cases    = cases.into_iter().map(|case| CFRSwitchCase { values : case.values, body : self.visit_groups(case.body) }).collect::<Vec<_>>();
+        let default  = default.map(|default| self.visit_groups(default));
+
+        let value = match (cond.groups.last()) {
+            Some(CFRGroup::Block(name)) => self.switch_value(name),
+            _                           => None
+        };
+        let value_count = cases.iter().map(|case| case.values.len()).sum::<usize>();
+        let Some(value) = value.filter(|_| (! cases.is_empty() && value_count <= self.max_values)) else {
+            return CFRGroups { groups : vec![ CFRGroup::Switch { cond, cases, default } ] };
+        };
+
+        // Cases which do nothing all lead to the same place, so are tested together where the first one was.
+        let mut arms : Vec<(Vec<CFGCaseValue>, CFRGroups)> = Vec::new();
+        for case in cases {
+            match (arms.iter_mut().find(|(_, body)| body.groups.is_empty() && case.body.groups.is_empty())) {
+                Some((values, _)) => values.extend(case.values),
+                None              => arms.push((case.values, case.body))
+            }
+        }
+
+        // Build the chain from the last arm back to the first, each one running the next when it does not match.
+        let mut rest = default.unwrap_or_else(|| CFRGroups { groups : Vec::new() });
+        for (i, (values, body)) in arms.into_iter().enumerate().rev() {
+            // The blocks of the switch run before the first test.
+            let mut test = if (i == 0) {
+                std::mem::replace(&mut cond, CFRGroups { groups : Vec::new() })
+            } else { CFRGroups { groups : Vec::new() } };
+            test.groups.push(CFRGroup::CaseTest { value : value.clone(), values });
+            let group = if (rest.groups.is_empty()) {
+                CFRGroup::OnewayConditional { cond : test, body, negated : false }
+            } else if (body.groups.is_empty()) {
+                CFRGroup::OnewayConditional { cond : test, body : rest, negated : true }
+            } else {
+                CFRGroup::TwowayConditional { cond : test, body_true : body, body_false : rest }
+            };
+            rest = CFRGroups { groups : vec![ group ] };
+        }
+        rest
+    }
+}
@@ -0,0 +1,113 @@
+use super::*;
+
+
+/// Options controlling how `CFRGroups` are laid out when formatted.
+/// 
+/// The default style matches the `Display` implementation: two space indentation with K&R braces, in the colours of
+///   `Theme::DARK`.
+#[derive(Clone, Debug)]
+pub struct FormatStyle {
+    /// The number of indentation characters per depth level.
+    pub indent_width : usize,
+    /// Whether to indent with tabs instead of spaces.
+    pub use_tabs     : bool,
+    /// Where opening braces are placed.
+    pub brace_style  : BraceStyle,
+    /// The column which a group must end before to be written on one line, such as `if (%bb2) { %bb3 }`, or
+    ///   `None` to always write groups over several lines.
+    /// 
+    /// Groups which are too wide are written over several lines as usual, with each of their nested groups
+    ///   written on one line if it fits. Bodies of more than one group are always written over several lines, so
+    ///   that statements are not run together. Only the characters shown are counted, not colours.
+    pub max_width    : Option<usize>,
+    /// The colours to write with.
+    pub theme        : Theme
+}
+
+/// Placement of opening braces.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BraceStyle {
+    /// ```text
+    /// if (
+    ///   COND
+    /// ) {
+    ///   BODY
+    /// } else {
+    ///   BODY
+    /// }
+    /// ```
+    KAndR,
+    /// ```text
+    /// if (
+    ///   COND
+    /// )
+    /// {
+    ///   BODY
+    /// }
+    /// else
+    /// {
+    ///   BODY
+    /// }
+    /// ```
+    Allman
+}
+
+
+impl Default for FormatStyle {
+    fn default() -> Self { Self {
+        indent_width : 2,
+        use_tabs     : false,
+        brace_style  : BraceStyle::KAndR,
+        max_width    : None,
+        theme        : Theme::default()
+    } }
+}
+
+
+impl FormatStyle {
+
+    /// Get the indentation for the given depth.
+    pub fn indent(&self, depth : usize) -> String {
+        let unit = if (self.use_tabs) { "\t" } else { " " };
+        unit.repeat(self.indent_width * depth)
+    }
+
+    /// Writes a closing `prefix` (such as `)`) followed by an opening brace.
+    pub(super) fn write_open(&self, f : &mut fmt::Formatter<'_>, depth : usize, prefix : &str) -> fmt::Result {
+        let indent = self.indent(depth);
+        let Theme { punctuation, reset, .. } = &self.theme;
+        match (self.brace_style) {
+            BraceStyle::KAndR  => writeln!(f, "{}{punctuation}{} {{{reset}", indent, prefix),
+            BraceStyle::Allman => {
+                writeln!(f, "{}{punctuation}{}{reset}", indent, prefix)?;
+                writeln!(f, "{}{punctuation}{{{reset}", indent)
+            }
+        }
+    }
+
+    /// Writes a closing brace followed by an `else` and an opening brace.
+    pub(super) fn write_else(&self, f : &mut fmt::Formatter<'_>, depth : usize) -> fmt::Result {
+        self.write_between(f, depth, "else")
+    }
+
+    /// Writes a closing brace followed by `keyword` and an opening brace.
+    pub(super) fn write_between(&self, f : &mut fmt::Formatter<'_>, depth : usize, keyword : &str) -> fmt::Result {
+        let indent = self.indent(depth);
+        let Theme { punctuation, reset, .. } = &self.theme;
+        match (self.brace_style) {
+            BraceStyle::KAndR  => writeln!(f, "{}{punctuation}}}{reset} {}{}{reset} {punctuation}{{{reset}", indent, self.theme.keyword, keyword),
+            BraceStyle::Allman => {
+                writeln!(f, "{}{punctuation}}}{reset}", indent)?;
+                writeln!(f, "{}{}{}{reset}", indent, self.theme.keyword, keyword)?;
+                writeln!(f, "{}{punctuation}{{{reset}", indent)
+            }
+        }
+    }
+
+    /// Writes a closing brace.
+    pub(super) fn write_close(&self, f : &mut fmt::Formatter<'_>, depth : usize) -> fmt::Result {
+        let Theme { punctuation, reset, .. } = &self.theme;
+        writeln!(f, "{}{punctuation}}}{reset}", self.indent(depth))
+    }
+
+}
@@ -0,0 +1,172 @@
+use super::*;
+
+use std::collections::HashMap;
+
+use llvm_ir::{
+    BasicBlock,
+    Constant,
+    Function,
+    Instruction,
+    IntPredicate,
+    Terminator
+};
+
+
+impl CFRGroups {
+
+
+    /// Reassembles chains of conditionals which compare the same value against constants into `CFRGroup::Switch`.
+    /// 
+    /// ```text
+    /// if (x == 1) {                 switch (x) {
+    ///     A                             case 1: A
+    /// } else if (x == 2) {    ->        case 2: B
+    ///     B                             default: C
+    /// } else {                      }
+    ///     C
+    /// }
+    /// ```
+    /// 
+    /// Backends often lower a `switch` on sparse values this way. A chain needs at least two comparisons, and the
+    ///   blocks after the first may contain nothing but their `icmp`, so that no instructions are dropped.
+    /// 
+    /// This is a heuristic, so it is not run by `CFRGroups::new`.
+    pub fn recover_switch_chains(&mut self, function : &Function) -> () {
+        let blocks = function.basic_blocks.iter()
+            .map(|block| (block.name.clone(), block))
+            .collect::<HashMap<_, _>>();
+        let groups = std::mem::replace(self, CFRGroups { groups : Vec::new() });
+        *self = groups.visit(&mut SwitchChains { blocks });
+    }
+
+
+}
+
+
+/// A conditional which compares a value against a constant.
+struct Comparison {
+    value   : Operand,
+    case    : CFGCaseValue,
+    /// Whether a match runs the body taken when the condition is true.
+    on_true : bool
+}
+
+
+/// Replaces chains of conditionals with `CFRGroup::Switch`.
+struct SwitchChains<'l> {
+    blocks : HashMap<Name, &'l BasicBlock>
+}
+
+impl<'l> SwitchChains<'l> {
+
+    /// Gets the comparison made by a conditional, or `None` if it does not compare a value against a constant.
+    /// 
+    /// Unless `first` is set, the condition must be a single block containing nothing but the comparison.
+    fn comparison(&self, group : &CFRGroup, first : bool) -> Option<Comparison> {
+        let (cond, on_true) = match (group) {
+            CFRGroup::TwowayConditional { cond, .. } => {
+                let (value, case, eq) = self.block_comparison(cond, first)?;
+                return Some(Comparison { value, case, on_true : eq });
+            },
+            // A chain without a final `else` ends in a one-way conditional, which must run its body on a match.
+            CFRGroup::OnewayConditional { cond, negated, .. } => (cond, ! negated),
+            _ => return None
+        };
+        let (value, case, eq) = self.block_comparison(cond, first)?;
+        if (eq != on_true) { return None; }
+        Some(Comparison { value, case, on_true })
+    }
+
+    /// Gets the value and constant compared by the `icmp eq` or `icmp ne` which the last block of `cond` branches
+    ///   on, and whether the condition is true when they are equal.
+    fn block_comparison(&self, cond : &CFRGroups, first : bool) -> Option<(Operand, CFGCaseValue, bool)> {
+        let name = match (cond.groups.as_slice()) {
+            [ CFRGroup::Block(name) ] => name,
+            [ .., CFRGroup::Block(name) ] if (first) => name,
+            _ => return None
+        };
+        let block = self.blocks.get(name)?;
+        if (! first && block.instrs.len() != 1) { return None; }
+
+        let Terminator::CondBr(term) = &block.term else { return None };
+        let Operand::LocalOperand { name : condition, .. } = &term.condition else { return None };
+        let icmp = block.instrs.iter().find_map(|instr| match (instr) {
+            Instruction::ICmp(icmp) if (&icmp.dest == condition) => Some(icmp),
+            _ => None
+        })?;
+
+        let eq = match (icmp.predicate) {
+            IntPredicate::EQ => true,
+            IntPredicate::NE => false,
+            _ => return None
+        };
+        let constant = |operand : &Operand| match (operand) {
+            Operand::ConstantOperand(constant) => match (&**constant) {
+                Constant::Int { .. } => CFGCaseValue::from_constant(constant),
+                _ => None
+            },
+            _ => None
+        };
+        // The constant may be on either side.
+        constant(&icmp.operand1).map(|case| (icmp.operand0.clone(), case, eq))
+            .or_else(|| constant(&icmp.operand0).map(|case| (icmp.operand1.clone(), case, eq)))
+    }
+
+    /// Gets the group run when a conditional does not match, if it is a single group which could continue the chain.
+    fn next(group : &CFRGroup, on_true : bool) -> Option<&CFRGroup> {
+        let CFRGroup::TwowayConditional { body_true, body_false, .. } = group else { return None };
+        let rest = if (on_true) { body_false } else { body_true };
+        match (rest.groups.as_slice()) {
+            [ group ] => Some(group),
+            _ => None
+        }
+    }
+
+}
+
+impl<'l> CfrVisitor for SwitchChains<'l> {
+    fn visit_group(&mut self, group : CFRGroup) -> CFRGroups {
+
+        // Follow the chain for as long as each link compares the same value against a new constant.
+        let mut links : Vec<(CFGCaseValue, bool)> = Vec::new();
+        let mut value = None;
+        let mut at = Some(&group);
+        while let Some(link) = at {
+            let Some(comparison) = self.comparison(link, links.is_empty()) else { break };
+            if (value.get_or_insert_with(|| comparison.value.clone()) != &comparison.value) { break; }
+            if (links.iter().any(|(case, _)| case == &comparison.case)) { break; }
+            links.push((comparison.case, comparison.on_true));
+            at = Self::next(link, comparison.on_true);
+        }
+
+        // A single comparison is left as a conditional.
+        if (links.len() < 2) {
+            return walk_group(self, group);
+        }
+
+        let mut switch_cond = None;
+        let mut cases       = Vec::new();
+        let mut default     = None;
+        let mut group       = group;
+        let last = links.len() - 1;
+        for (i, (case, on_true)) in links.into_iter().enumerate() {
+            let (cond, body, mut rest) = match (group) {
+                CFRGroup::TwowayConditional { cond, body_true, body_false } => {
+                    if (on_true) { (cond, body_true, body_false) } else { (cond, body_false, body_true) }
+                },
+                CFRGroup::OnewayConditional { cond, body, .. } => (cond, body, CFRGroups { groups : Vec::new() }),
+                _ => unreachable!()
+            };
+            switch_cond.get_or_insert(cond);
+            cases.push(CFRSwitchCase { values : vec![ case ], body : self.visit_groups(body) });
+            if (i == last) {
+                if (! rest.groups.is_empty()) { default = Some(self.visit_groups(rest)); }
+                break;
+            }
+            group = rest.groups.pop().unwrap();
+        }
+
+        let cond = self.visit_groups(switch_cond.unwrap());
+        CFRGroups { groups : vec![ CFRGroup::Switch { cond, cases, default } ] }
+    }
+}
@@ -0,0 +1,221 @@
+use super::*;
+
+
+/// A transformation pass over recovered `CFRGroups`.
+/// 
+/// Each `visit_*` method receives a group by value and returns the groups to replace it with, so a group can be
+///   kept, rewritten, removed, or expanded into several groups. The default implementations rewrite the nested
+///   groups first, then rebuild the group unchanged.
+/// 
+/// Apply a visitor with `CFRGroups::visit`.
+/// 
+/// ```rust
+/// use decomp::prelude::*;
+/// use decomp::cfr::{ CFRGroup, CfrVisitor };
+/// 
+/// /// Rewrites `if (c) { } else { body }` into `if (!c) { body }`.
+/// struct FlipEmptyThen;
+/// impl CfrVisitor for FlipEmptyThen {
+///     fn visit_twoway_conditional(&mut self, cond : CFRGroups, body_true : CFRGroups, body_false : CFRGroups) -> CFRGroups {
+///         let cond       = self.visit_groups(cond);
+///         let body_true  = self.visit_groups(body_true);
+///         let body_false = self.visit_groups(body_false);
+///         let group = if (body_true.groups.is_empty()) {
+///             CFRGroup::OnewayConditional { cond, body : body_false, negated : true }
+///         } else {
+///             CFRGroup::TwowayConditional { cond, body_true, body_false }
+///         };
+///         CFRGroups { groups : vec![ group ] }
+///     }
+/// }
+/// 
+/// # let groups = CFRGroups { groups : Vec::new() };
+/// let groups = groups.visit(&mut FlipEmptyThen);
+/// ```
+pub trait CfrVisitor {
+
+    /// Visits a sequence of groups, replacing each group with the groups returned by `visit_group`.
+    fn visit_groups(&mut self, groups : CFRGroups) -> CFRGroups { walk_groups(self, groups) }
+
+    /// Visits a single group, passing it to the `visit_*` method for its kind.
+    fn visit_group(&mut self, group : CFRGroup) -> CFRGroups { walk_group(self, group) }
+
+    /// Visits a `CFRGroup::Block`.
+    fn visit_block(&mut self, name : Name) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Block(name) ] }
+    }
+
+    /// Visits a `CFRGroup::NoReturnCall`.
+    fn visit_noreturn_call(&mut self, name : Name, callee : Option<Name>) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::NoReturnCall { name, callee } ] }
+    }
+
+    /// Visits a `CFRGroup::Unreachable`.
+    fn visit_unreachable(&mut self, name : Name) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Unreachable(name) ] }
+    }
+
+    /// Visits a `CFRGroup::AsmGoto`.
+    fn visit_asm_goto(&mut self, name : Name, labels : Vec<Name>) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::AsmGoto { name, labels } ] }
+    }
+
+    /// Visits a `CFRGroup::Assign`.
+    fn visit_assign(&mut self, assigns : Vec<(Name, Operand)>) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Assign(assigns) ] }
+    }
+
+    /// Visits a `CFRGroup::PreconditionLoop`.
+    fn visit_precondition_loop(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool, step : CFRGroups) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        let step = self.visit_groups(step);
+        CFRGroups { groups : vec![ CFRGroup::PreconditionLoop { cond, body, negated, step } ] }
+    }
+
+    /// Visits a `CFRGroup::ForLoop`.
+    fn visit_for_loop(&mut self, init : CFRGroups, cond : CFRGroups, step : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let init = self.visit_groups(init);
+        let cond = self.visit_groups(cond);
+        let step = self.visit_groups(step);
+        let body = self.visit_groups(body);
+        CFRGroups { groups : vec![ CFRGroup::ForLoop { init, cond, step, body, negated } ] }
+    }
+
+    /// Visits a `CFRGroup::PostconditionLoop`.
+    fn visit_postcondition_loop(&mut self, cond : CFRGroups, break_values : Vec<(Name, Operand)>) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        CFRGroups { groups : vec![ CFRGroup::PostconditionLoop { cond, break_values } ] }
+    }
+
+    /// Visits a `CFRGroup::InfiniteLoop`.
+    fn visit_infinite_loop(&mut self, body : CFRGroups) -> CFRGroups {
+        let body = self.visit_groups(body);
+        CFRGroups { groups : vec![ CFRGroup::InfiniteLoop { body } ] }
+    }
+
+    /// Visits a `CFRGroup::OnewayConditional`.
+    fn visit_oneway_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        CFRGroups { groups : vec![ CFRGroup::OnewayConditional { cond, body, negated } ] }
+    }
+
+    /// Visits a `CFRGroup::OnewayReturnConditional`.
+    fn visit_oneway_return_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        CFRGroups { groups : vec![ CFRGroup::OnewayReturnConditional { cond, body, negated } ] }
+    }
+
+    /// Visits a `CFRGroup::BreakConditional`.
+    fn visit_break_conditional(&mut self, cond : CFRGroups, body : CFRGroups, negated : bool) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        let body = self.visit_groups(body);
+        CFRGroups { groups : vec![ CFRGroup::BreakConditional { cond, body, negated } ] }
+    }
+
+    /// Visits a `CFRGroup::Select`.
+    fn visit_select(&mut self, cond : CFRGroups, then_val : Operand, else_val : Operand, dest : Name) -> CFRGroups {
+        let cond = self.visit_groups(cond);
+        CFRGroups { groups : vec![ CFRGroup::Select { cond, then_val, else_val, dest } ] }
+    }
+
+    /// Visits a `CFRGroup::TwowayConditional`.
+    fn visit_twoway_conditional(&mut self, cond : CFRGroups, body_true : CFRGroups, body_false : CFRGroups) -> CFRGroups {
+        let cond       = self.visit_groups(cond);
+        let body_true  = self.visit_groups(body_true);
+        let body_false = self.visit_groups(body_false);
+        CFRGroups { groups : vec![ CFRGroup::TwowayConditional { cond, body_true, body_false } ] }
+    }
+
+    /// Visits a `CFRGroup::CaseTest`.
+    fn visit_case_test(&mut self, value : Operand, values : Vec<CFGCaseValue>) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::CaseTest { value, values } ] }
+    }
+
+    /// Visits a `CFRGroup::Switch`.
+    fn visit_switch(&mut self, cond : CFRGroups, cases : Vec<CFRSwitchCase>, default : Option<CFRGroups>) -> CFRGroups {
+        let cond    = self.visit_groups(cond);
+        let cases   = cases.into_iter().map(|case| CFRSwitchCase { values : case.values, body : self.visit_groups(case.body) }).collect();
+        let default = default.map(|default| self.visit_groups(default));
+        CFRGroups { groups : vec![ CFRGroup::Switch { cond, cases, default } ] }
+    }
+
+    /// Visits a `CFRGroup::SwitchLoop`.
+    fn visit_switch_loop(&mut self, cond : CFRGroups, cases : Vec<CFRSwitchLoopCase>, default : CFRSwitchLoopCase, step : CFRGroups) -> CFRGroups {
+        let cond    = self.visit_groups(cond);
+        let cases   = cases.into_iter().map(|case| CFRSwitchLoopCase { body : self.visit_groups(case.body), ..case }).collect();
+        let default = CFRSwitchLoopCase { body : self.visit_groups(default.body), ..default };
+        let step    = self.visit_groups(step);
+        CFRGroups { groups : vec![ CFRGroup::SwitchLoop { cond, cases, default, step } ] }
+    }
+
+    /// Visits a `CFRGroup::ShortCircuit`.
+    fn visit_short_circuit(&mut self, lhs : CFRGroups, rhs : CFRGroups, disjunction : bool, negated : bool) -> CFRGroups {
+        let lhs = self.visit_groups(lhs);
+        let rhs = self.visit_groups(rhs);
+        CFRGroups { groups : vec![ CFRGroup::ShortCircuit { lhs, rhs, disjunction, negated } ] }
+    }
+
+    /// Visits a `CFRGroup::Unrecovered`.
+    fn visit_unrecovered(&mut self, region : UnrecoveredRegion) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Unrecovered(region) ] }
+    }
+
+    /// Visits a `CFRGroup::Unstructured`.
+    fn visit_unstructured(&mut self, blocks : Vec<Name>, edges : Vec<(Name, Name)>) -> CFRGroups {
+        CFRGroups { groups : vec![ CFRGroup::Unstructured { blocks, edges } ] }
+    }
+
+}
+
+
+/// Visits every group in `groups`, concatenating the results.
+/// 
+/// This is the default behaviour of `CfrVisitor::visit_groups`, for use by visitors which override it.
+pub fn walk_groups<V : CfrVisitor + ?Sized>(visitor : &mut V, groups : CFRGroups) -> CFRGroups {
+    let mut out = CFRGroups { groups : Vec::new() };
+    for group in groups.groups {
+        out.groups.extend(visitor.visit_group(group).groups);
+    }
+    out
+}
+
+/// Passes `group` to the `visit_*` method of `visitor` for its kind.
+/// 
+/// This is the default behaviour of `CfrVisitor::visit_group`, for use by visitors which override it.
+pub fn walk_group<V : CfrVisitor + ?Sized>(visitor : &mut V, group : CFRGroup) -> CFRGroups {
+    match (group) {
+        CFRGroup::Block                   (name)                          => visitor.visit_block(name),
+        CFRGroup::NoReturnCall            { name, callee }                => visitor.visit_noreturn_call(name, callee),
+        CFRGroup::Unreachable             (name)                          => visitor.visit_unreachable(name),
+        CFRGroup::AsmGoto                 { name, labels }                => visitor.visit_asm_goto(name, labels),
+        CFRGroup::Assign                  (assigns)                       => visitor.visit_assign(assigns),
+        CFRGroup::PreconditionLoop        { cond, body, negated, step }   => visitor.visit_precondition_loop(cond, body, negated, step),
+        CFRGroup::ForLoop                 { init, cond, step, body, negated } => visitor.visit_for_loop(init, cond, step, body, negated),
+        CFRGroup::PostconditionLoop       { cond, break_values }          => visitor.visit_postcondition_loop(cond, break_values),
+        CFRGroup::InfiniteLoop            { body }                        => visitor.visit_infinite_loop(body),
+        CFRGroup::OnewayConditional       { cond, body, negated }         => visitor.visit_oneway_conditional(cond, body, negated),
+        CFRGroup::OnewayReturnConditional { cond, body, negated }         => visitor.visit_oneway_return_conditional(cond, body, negated),
+        CFRGroup::BreakConditional        { cond, body, negated }         => visitor.visit_break_conditional(cond, body, negated),
+        CFRGroup::TwowayConditional       { cond, body_true, body_false } => visitor.visit_twoway_conditional(cond, body_true, body_false),
+        CFRGroup::Select                  { cond, then_val, else_val, dest } => visitor.visit_select(cond, then_val, else_val, dest),
+        CFRGroup::CaseTest                { value, values }               => visitor.visit_case_test(value, values),
+        CFRGroup::Switch                  { cond, cases, default }        => visitor.visit_switch(cond, cases, default),
+        CFRGroup::SwitchLoop              { cond, cases, default, step }  => visitor.visit_switch_loop(cond, cases, default, step),
+        CFRGroup::ShortCircuit            { lhs, rhs, disjunction, negated } => visitor.visit_short_circuit(lhs, rhs, disjunction, negated),
+        CFRGroup::Unrecovered             (region)                        => visitor.visit_unrecovered(region),
+        CFRGroup::Unstructured            { blocks, edges }               => visitor.visit_unstructured(blocks, edges)
+    }
+}
+
+
+impl CFRGroups {
+
+    /// Applies a `CfrVisitor` to every group in the tree, returning the rewritten tree.
+    pub fn visit<V : CfrVisitor + ?Sized>(self, visitor : &mut V) -> CFRGroups {
+        visitor.visit_groups(self)
+    }
+
+}
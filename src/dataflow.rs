@@ -0,0 +1,128 @@
+//! Dataflow
+//! 
+//! Utilities for finding which values the blocks of an LLVM `Function` define and use.
+
+
+use llvm_ir::{
+    BasicBlock,
+    Instruction,
+    Name,
+    Operand,
+    Terminator
+};
+
+
+/// Gets the names defined by `block`, and the local names it uses, each in the order they first appear.
+/// 
+/// Uses include names which are also defined earlier in the same block, and the incoming values of `phi`
+///   instructions. Constants and globals are not included.
+pub fn block_defs_uses(block : &BasicBlock) -> (Vec<Name>, Vec<Name>) {
+    let mut defs = Vec::new();
+    let mut uses = Vec::new();
+    let push = |names : &mut Vec<Name>, name : &Name| if (! names.contains(name)) { names.push(name.clone()); };
+
+    for instr in &block.instrs {
+        for operand in instruction_operands(instr) {
+            if let Operand::LocalOperand { name, .. } = operand { push(&mut uses, name); }
+        }
+        if let Some(dest) = instr.try_get_result() { push(&mut defs, dest); }
+    }
+
+    for operand in terminator_operands(&block.term) {
+        if let Operand::LocalOperand { name, .. } = operand { push(&mut uses, name); }
+    }
+    let term_dest = match (&block.term) {
+        Terminator::Invoke      (term) => Some(&term.result),
+        Terminator::CatchSwitch (term) => Some(&term.result),
+        Terminator::CallBr      (term) => Some(&term.result),
+        _ => None
+    };
+    if let Some(dest) = term_dest { push(&mut defs, dest); }
+
+    (defs, uses)
+}
+
+
+/// Gets every operand read by an instruction.
+fn instruction_operands(instr : &Instruction) -> Vec<&Operand> {
+    match (instr) {
+        Instruction::Add  (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::Sub  (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::Mul  (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::UDiv (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::SDiv (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::URem (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::SRem (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::And  (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::Or   (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::Xor  (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::Shl  (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::LShr (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::AShr (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::FAdd (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::FSub (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::FMul (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::FDiv (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::FRem (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::FNeg (instr) => vec![ &instr.operand ],
+
+        Instruction::ExtractElement (instr) => vec![ &instr.vector, &instr.index ],
+        Instruction::InsertElement  (instr) => vec![ &instr.vector, &instr.element, &instr.index ],
+        Instruction::ShuffleVector  (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::ExtractValue   (instr) => vec![ &instr.aggregate ],
+        Instruction::InsertValue    (instr) => vec![ &instr.aggregate, &instr.element ],
+
+        Instruction::Alloca        (instr) => vec![ &instr.num_elements ],
+        Instruction::Load          (instr) => vec![ &instr.address ],
+        Instruction::Store         (instr) => vec![ &instr.address, &instr.value ],
+        Instruction::Fence         (_)     => vec![ ],
+        Instruction::CmpXchg       (instr) => vec![ &instr.address, &instr.expected, &instr.replacement ],
+        Instruction::AtomicRMW     (instr) => vec![ &instr.address, &instr.value ],
+        Instruction::GetElementPtr (instr) => [ &instr.address ].into_iter().chain(&instr.indices).collect(),
+
+        Instruction::Trunc         (instr) => vec![ &instr.operand ],
+        Instruction::ZExt          (instr) => vec![ &instr.operand ],
+        Instruction::SExt          (instr) => vec![ &instr.operand ],
+        Instruction::FPTrunc       (instr) => vec![ &instr.operand ],
+        Instruction::FPExt         (instr) => vec![ &instr.operand ],
+        Instruction::FPToUI        (instr) => vec![ &instr.operand ],
+        Instruction::FPToSI        (instr) => vec![ &instr.operand ],
+        Instruction::UIToFP        (instr) => vec![ &instr.operand ],
+        Instruction::SIToFP        (instr) => vec![ &instr.operand ],
+        Instruction::PtrToInt      (instr) => vec![ &instr.operand ],
+        Instruction::IntToPtr      (instr) => vec![ &instr.operand ],
+        Instruction::BitCast       (instr) => vec![ &instr.operand ],
+        Instruction::AddrSpaceCast (instr) => vec![ &instr.operand ],
+
+        Instruction::ICmp       (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::FCmp       (instr) => vec![ &instr.operand0, &instr.operand1 ],
+        Instruction::Phi        (instr) => instr.incoming_values.iter().map(|(value, _)| value).collect(),
+        Instruction::Select     (instr) => vec![ &instr.condition, &instr.true_value, &instr.false_value ],
+        #[cfg(not(feature = "llvm-9"))]
+        Instruction::Freeze     (instr) => vec![ &instr.operand ],
+        Instruction::Call       (instr) => instr.function.as_ref().right().into_iter().chain(instr.arguments.iter().map(|(arg, _)| arg)).collect(),
+        Instruction::VAArg      (instr) => vec![ &instr.arg_list ],
+        Instruction::LandingPad (_)     => vec![ ],
+        Instruction::CatchPad   (instr) => [ &instr.catch_switch ].into_iter().chain(&instr.args).collect(),
+        Instruction::CleanupPad (instr) => [ &instr.parent_pad ].into_iter().chain(&instr.args).collect()
+    }
+}
+
+
+/// Gets every operand read by a terminator.
+fn terminator_operands(term : &Terminator) -> Vec<&Operand> {
+    match (term) {
+        Terminator::Ret         (term) => term.return_operand.iter().collect(),
+        Terminator::Br          (_)    => vec![ ],
+        Terminator::CondBr      (term) => vec![ &term.condition ],
+        Terminator::Switch      (term) => vec![ &term.operand ],
+        Terminator::IndirectBr  (term) => vec![ &term.operand ],
+        Terminator::Invoke      (term) => term.function.as_ref().right().into_iter().chain(term.arguments.iter().map(|(arg, _)| arg)).collect(),
+        Terminator::Resume      (term) => vec![ &term.operand ],
+        Terminator::Unreachable (_)    => vec![ ],
+        Terminator::CleanupRet  (term) => vec![ &term.cleanup_pad ],
+        Terminator::CatchRet    (term) => vec![ &term.catch_pad ],
+        Terminator::CatchSwitch (term) => vec![ &term.parent_pad ],
+        Terminator::CallBr      (term) => term.function.as_ref().right().into_iter().chain(term.arguments.iter().map(|(arg, _)| arg)).collect()
+    }
+}
@@ -0,0 +1,167 @@
+//! Induction Variables
+//! 
+//! Utilities for recognising the counters of simple loops, such as `for (i = 0; i < n; i++)`.
+
+
+use std::collections::HashMap;
+
+use llvm_ir::{
+    BasicBlock,
+    Function,
+    Instruction,
+    IntPredicate,
+    Name,
+    Operand,
+    Terminator
+};
+
+
+/// A counter which is stepped by the same amount on every iteration of a loop.
+#[derive(Clone, PartialEq, Debug)]
+pub struct InductionInfo {
+    /// The `phi` in the loop header which holds the counter.
+    pub variable  : Name,
+    /// The value of the counter when the loop is entered.
+    pub init      : Operand,
+    /// The amount added to the counter on every iteration.
+    pub step      : Operand,
+    /// The name the stepped counter is assigned to, before it is passed back to `variable`.
+    pub next      : Name,
+    /// The block which steps the counter and branches back to the header.
+    pub latch     : Name,
+    /// The value the counter is compared against.
+    pub bound     : Operand,
+    /// The loop runs while `variable predicate bound` is true.
+    pub predicate : IntPredicate
+}
+
+
+/// Finds the induction variable of the loop whose condition is in the block `header`, or `None` if the loop does
+///   not have the expected shape.
+/// 
+/// The header must end in a conditional branch on an `icmp` between a `phi` in the header and a bound. The `phi`
+///   must have two incoming values, one of which is an `add` of the `phi` itself and a step, coming from the
+///   block which branches back to the header. Subtracting is only recognised as adding a negative step, which is
+///   how LLVM usually writes it.
+/// 
+/// This is a heuristic, so some counters which could be recognised are not.
+pub fn analyze_induction(function : &Function, header : &Name) -> Option<InductionInfo> {
+    let blocks = function.basic_blocks.iter()
+        .map(|block| (&block.name, block))
+        .collect::<HashMap<_, _>>();
+    let header_block = *blocks.get(header)?;
+
+    // Find the comparison the header branches on.
+    let Terminator::CondBr(term) = &header_block.term else { return None };
+    let Operand::LocalOperand { name : condition, .. } = &term.condition else { return None };
+    let icmp = header_block.instrs.iter().find_map(|instr| match (instr) {
+        Instruction::ICmp(icmp) if (&icmp.dest == condition) => Some(icmp),
+        _ => None
+    })?;
+
+    for instr in &header_block.instrs {
+        let Instruction::Phi(phi) = instr else { continue };
+        if (phi.incoming_values.len() != 2) { continue; }
+
+        // The counter may be on either side of the comparison.
+        let (bound, predicate) = if (is_local(&icmp.operand0, &phi.dest)) {
+            (&icmp.operand1, icmp.predicate)
+        } else if (is_local(&icmp.operand1, &phi.dest)) {
+            (&icmp.operand0, swapped(icmp.predicate))
+        } else { continue };
+
+        // One incoming value steps the counter, and the other is where it starts.
+        for (i, (value, latch)) in phi.incoming_values.iter().enumerate() {
+            let Operand::LocalOperand { name : next, .. } = value else { continue };
+            let Some(step) = find_step(&blocks, latch, next, &phi.dest) else { continue };
+            let (init, _) = &phi.incoming_values[1 - i];
+
+            // The branch which leads back around to the latch continues the loop.
+            let predicate = if (reaches(&blocks, &term.true_dest, latch, header)) {
+                predicate
+            } else if (reaches(&blocks, &term.false_dest, latch, header)) {
+                inverted(predicate)
+            } else { continue };
+
+            return Some(InductionInfo {
+                variable  : phi.dest.clone(),
+                init      : init.clone(),
+                step      : step.clone(),
+                next      : next.clone(),
+                latch     : latch.clone(),
+                bound     : bound.clone(),
+                predicate
+            });
+        }
+    }
+    None
+}
+
+
+/// Returns `true` if `operand` is the local `name`.
+fn is_local(operand : &Operand, name : &Name) -> bool {
+    matches!(operand, Operand::LocalOperand { name : local, .. } if (local == name))
+}
+
+/// Gets the step of the `add` in `latch` which assigns `variable` plus a step to `next`.
+fn find_step<'l>(blocks : &HashMap<&Name, &'l BasicBlock>, latch : &Name, next : &Name, variable : &Name) -> Option<&'l Operand> {
+    let latch_block = *blocks.get(latch)?;
+    latch_block.instrs.iter().find_map(|instr| match (instr) {
+        Instruction::Add(add) if (&add.dest == next) => {
+            if (is_local(&add.operand0, variable)) { Some(&add.operand1) }
+            else if (is_local(&add.operand1, variable)) { Some(&add.operand0) }
+            else { None }
+        },
+        _ => None
+    })
+}
+
+/// Returns `true` if `to` can be reached from `from` without passing through `avoid`.
+fn reaches(blocks : &HashMap<&Name, &BasicBlock>, from : &Name, to : &Name, avoid : &Name) -> bool {
+    let mut stack   = vec![ from ];
+    let mut visited = Vec::new();
+    while let Some(at) = stack.pop() {
+        if (at == to) { return true; }
+        if (at == avoid || visited.contains(&at)) { continue; }
+        visited.push(at);
+        let Some(block) = blocks.get(at) else { continue };
+        match (&block.term) {
+            Terminator::Br     (term) => stack.push(&term.dest),
+            Terminator::CondBr (term) => stack.extend([ &term.true_dest, &term.false_dest ]),
+            Terminator::Switch (term) => stack.extend(term.dests.iter().map(|(_, dest)| dest).chain([ &term.default_dest ])),
+            _ => { }
+        }
+    }
+    false
+}
+
+/// Gets the predicate which gives the same result with its operands swapped.
+pub(crate) fn swapped(predicate : IntPredicate) -> IntPredicate {
+    match (predicate) {
+        IntPredicate::UGT => IntPredicate::ULT,
+        IntPredicate::UGE => IntPredicate::ULE,
+        IntPredicate::ULT => IntPredicate::UGT,
+        IntPredicate::ULE => IntPredicate::UGE,
+        IntPredicate::SGT => IntPredicate::SLT,
+        IntPredicate::SGE => IntPredicate::SLE,
+        IntPredicate::SLT => IntPredicate::SGT,
+        IntPredicate::SLE => IntPredicate::SGE,
+        predicate         => predicate
+    }
+}
+
+/// Gets the predicate which gives the opposite result.
+pub(crate) fn inverted(predicate : IntPredicate) -> IntPredicate {
+    match (predicate) {
+        IntPredicate::EQ  => IntPredicate::NE,
+        IntPredicate::NE  => IntPredicate::EQ,
+        IntPredicate::UGT => IntPredicate::ULE,
+        IntPredicate::UGE => IntPredicate::ULT,
+        IntPredicate::ULT => IntPredicate::UGE,
+        IntPredicate::ULE => IntPredicate::UGT,
+        IntPredicate::SGT => IntPredicate::SLE,
+        IntPredicate::SGE => IntPredicate::SLT,
+        IntPredicate::SLT => IntPredicate::SGE,
+        IntPredicate::SLE => IntPredicate::SGT
+    }
+}
@@ -20,6 +20,8 @@
 //! ### Load an LLVM Module:
 //! ```rust
 //! use decomp::prelude::*;
+//! // From a textual or bitcode LLVM IR file.
+//! let module = decomp::load_module("/path/to/file.ll").unwrap();
 //! // From a textual LLVM IR file.
 //! let module = Module::from_ir_path("/path/to/file.ll").unwrap();
 //! // From a bitcode LLVM IR file.
@@ -59,26 +61,70 @@
 //! }
 //! ```
 //! 
+//! ### Process a Whole Module:
+//! See [`pipeline`](mod@crate::pipeline).
+//! ```rust,no_run
+//! use decomp::prelude::*;
+//! # let module = Module::from_bc_path("/path/to/file.bc").unwrap();
+//! decomp::for_each_function(&module, |function, groups| {
+//!     if let Ok(groups) = groups {
+//!         println!("{}", groups);
+//!     }
+//! });
+//! ```
+//! 
 
 
 pub mod cfg;
 pub mod cfa;
 pub mod cfr;
+pub mod dataflow;
+pub mod induction;
+pub mod module;
+pub mod pipeline;
+pub mod signature;
+pub mod target;
+pub mod theme;
 pub mod util;
 
+pub use pipeline::{
+    decompile_function,
+    for_each_function,
+    recover_matching,
+    DecompError
+};
+pub use signature::function_signature;
+pub use dataflow::block_defs_uses;
+pub use induction::{
+    analyze_induction,
+    InductionInfo
+};
+pub use target::pointer_size;
+pub use module::{
+    load_module,
+    ModuleLoadError
+};
+
 
 /// The essentials for restructuring LLVM IR.
 pub mod prelude {
 
     pub use crate::cfg::{
         ControlFlowGraph,
-        CFGNode
+        CFGNode,
+        CfgError
     };
     pub use crate::cfa::{
         CFAPrim,
+        CfaError,
         prims::*
     };
     pub use crate::cfr::CFRGroups;
+    pub use crate::pipeline::DecompError;
+    pub use crate::theme::{
+        Theme,
+        Themed
+    };
 
     /// Re-export `llvm-ir`.
     pub use llvm_ir;
@@ -0,0 +1,104 @@
+//! Module Loading
+//! 
+//! Utilities for loading an LLVM `Module` from a file, with helpful errors.
+
+
+use std::fmt;
+use std::fs::File;
+use std::io::{ self, Read };
+use std::path::{ Path, PathBuf };
+
+use llvm_ir::Module;
+
+
+/// An error encountered while loading a `Module` with `load_module`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ModuleLoadError {
+
+    /// The file could not be read.
+    Io {
+        path  : PathBuf,
+        error : io::Error
+    },
+
+    /// The file was read, but `llvm-ir` could not parse it.
+    Parse {
+        path    : PathBuf,
+        /// Whether the file was parsed as bitcode, rather than textual IR.
+        bitcode : bool,
+        /// The error given by `llvm-ir`.
+        message : String,
+        /// A likely cause of the error, if one is known.
+        hint    : Option<&'static str>
+    }
+
+}
+
+
+/// Loads a `Module` from a textual (`.ll`) or bitcode (`.bc`) LLVM IR file.
+/// 
+/// The format is detected from the contents of the file rather than its extension, so a bitcode file with a
+///   `.ll` extension still loads. If parsing fails, the error includes the path, and a hint when the extension
+///   does not match the contents.
+/// 
+/// ```rust
+/// match (decomp::load_module("/path/to/file.ll")) {
+///     Ok(module) => println!("{} functions", module.functions.len()),
+///     Err(err)   => eprintln!("{}", err)
+/// }
+/// ```
+pub fn load_module<P : AsRef<Path>>(path : P) -> Result<Module, ModuleLoadError> {
+    let path = path.as_ref();
+    let bitcode = is_bitcode(path).map_err(|error| ModuleLoadError::Io { path : path.to_path_buf(), error })?;
+    let result = if (bitcode) { Module::from_bc_path(path) } else { Module::from_ir_path(path) };
+    result.map_err(|message| {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let hint = match (extension, bitcode) {
+            (Some("bc"), false) => Some("the file has a `.bc` extension, but does not start with the bitcode magic number, so it was parsed as textual IR"),
+            (Some("ll"), true)  => Some("the file has a `.ll` extension, but starts with the bitcode magic number, so it was parsed as bitcode"),
+            _                   => None
+        };
+        ModuleLoadError::Parse { path : path.to_path_buf(), bitcode, message, hint }
+    })
+}
+
+
+/// Returns `true` if the file at `path` starts with the magic number of LLVM bitcode, or of its wrapper.
+fn is_bitcode(path : &Path) -> io::Result<bool> {
+    let mut magic = [0u8; 4];
+    let mut file  = File::open(path)?;
+    let mut read  = 0;
+    while (read < magic.len()) {
+        let n = file.read(&mut magic[read..])?;
+        if (n == 0) { return Ok(false); }
+        read += n;
+    }
+    Ok(magic == [ b'B', b'C', 0xC0, 0xDE ] || magic == [ 0xDE, 0xC0, 0x17, 0x0B ])
+}
+
+
+impl fmt::Display for ModuleLoadError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::Io { path, error } => write!(f, "Failed to read {}: {}", path.display(), error),
+            Self::Parse { path, bitcode, message, hint } => {
+                let format = if (*bitcode) { "bitcode" } else { "textual IR" };
+                write!(f, "Failed to parse {} as {}: {}", path.display(), format, message)?;
+                if let Some(hint) = hint {
+                    write!(f, " (hint: {})", hint)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModuleLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match (self) {
+            Self::Io { error, .. } => Some(error),
+            _                      => None
+        }
+    }
+}
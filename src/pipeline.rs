@@ -0,0 +1,157 @@
+//! Pipeline
+//! 
+//! Utilities for running every stage of the pipeline at once.
+
+
+use crate::cfg::{
+    ControlFlowGraph,
+    CfgError
+};
+use crate::cfa::{
+    CFAPrim,
+    CfaError
+};
+use crate::cfr::CFRGroups;
+use crate::util::glob;
+
+use std::fmt;
+
+use llvm_ir::{
+    Function,
+    Module
+};
+
+
+/// An error encountered while running the pipeline on a `Function`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum DecompError {
+
+    /// The control flow graph could not be built.
+    Cfg(CfgError),
+
+    /// The control flow graph could not be reduced to primitives.
+    Analysis(CfaError),
+
+    /// The primitives could not be recovered into groups.
+    Recovery
+
+}
+
+
+/// Runs the full pipeline on a single `Function`.
+pub fn decompile_function(function : &Function) -> Result<CFRGroups, DecompError> {
+    let cfg    = ControlFlowGraph::try_new(function).map_err(DecompError::Cfg)?;
+    let prims  = CFAPrim::find_all_with_budget(cfg, usize::MAX).map_err(DecompError::Analysis)?;
+    let groups = CFRGroups::new(&prims).ok_or(DecompError::Recovery)?;
+    Ok(groups)
+}
+
+
+/// Runs the full pipeline on every `Function` in a `Module`, one at a time, passing each result to `f`.
+/// 
+/// The intermediate state of each function is dropped before the next one is started, so peak memory use does
+///   not grow with the size of the module.
+/// 
+/// ```rust,no_run
+/// use decomp::prelude::*;
+/// # let module = Module::from_bc_path("/path/to/file.bc").unwrap();
+/// decomp::for_each_function(&module, |function, groups| {
+///     match (groups) {
+///         Ok(groups) => println!("{}", groups),
+///         Err(err)   => println!("{:?}: {}", function.name, err)
+///     }
+/// });
+/// ```
+pub fn for_each_function<F : FnMut(&Function, Result<CFRGroups, DecompError>)>(module : &Module, mut f : F) -> () {
+    for function in &module.functions {
+        f(function, decompile_function(function));
+    }
+}
+
+
+/// Runs the full pipeline on every `Function` in a `Module` whose name matches the glob `pattern`, such as
+///   `handle_*`.
+/// 
+/// `*` matches any number of characters and `?` matches exactly one. The whole name must match, so `handle`
+///   does not match `handle_key`. Each result is paired with the name of its function, in the order of the
+///   functions in the module.
+/// 
+/// ```rust,no_run
+/// use decomp::prelude::*;
+/// # let module = Module::from_bc_path("/path/to/file.bc").unwrap();
+/// for (name, groups) in decomp::recover_matching(&module, "handle_*") {
+///     if let Ok(groups) = groups {
+///         println!("{}:\n{}", name, groups);
+///     }
+/// }
+/// ```
+pub fn recover_matching(module : &Module, pattern : &str) -> Vec<(String, Result<CFRGroups, DecompError>)> {
+    module.functions.iter()
+        .filter(|function| glob::matches(pattern, &function.name))
+        .map(|function| (function.name.clone(), decompile_function(function)))
+        .collect()
+}
+
+
+impl fmt::Display for DecompError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::Cfg(err)      => write!(f, "{}", err),
+            Self::Analysis(err) => write!(f, "{}", err),
+            Self::Recovery      => write!(f, "Failed to recover control flow groups")
+        }
+    }
+}
+
+impl std::error::Error for DecompError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match (self) {
+            Self::Cfg(err)      => Some(err),
+            Self::Analysis(err) => Some(err),
+            Self::Recovery      => None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::CFRGroup;
+    use crate::cfg::tests::function;
+    use llvm_ir::Name;
+
+    #[test]
+    fn single_block() -> () {
+        // A function which only returns, with no instructions before its terminator.
+        let groups = decompile_function(&function(&[ ("entry", &[]) ])).expect("single block was not recovered");
+        assert!(matches!(groups.groups.as_slice(), [ CFRGroup::Block(name) ] if name == &Name::from("entry")));
+    }
+
+    #[test]
+    fn single_block_looping() -> () {
+        // `loop { }`, where the only block branches back to itself.
+        let groups = decompile_function(&function(&[ ("entry", &[ "entry" ]) ])).expect("single block loop was not recovered");
+        assert!(matches!(groups.groups.as_slice(), [ CFRGroup::InfiniteLoop { .. } ]));
+    }
+
+    #[test]
+    fn no_blocks() -> () {
+        // A declaration, which has no blocks at all.
+        let result = decompile_function(&Function::new("declared"));
+        assert!(matches!(result, Err(DecompError::Cfg(CfgError::NoBlocks))));
+    }
+
+    #[test]
+    fn irreducible_keeps_error() -> () {
+        // A loop entered through both `a` and `b`.
+        let result = decompile_function(&function(&[
+            ("entry", &[ "a", "b" ]),
+            ("a",     &[ "b" ]),
+            ("b",     &[ "a" ])
+        ]));
+        assert!(matches!(result, Err(DecompError::Analysis(CfaError::Unreducible { .. }))));
+    }
+
+}
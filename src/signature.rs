@@ -0,0 +1,25 @@
+//! Function Signatures
+//! 
+//! Utilities for rendering the signature of an LLVM `Function` as pseudocode.
+//! 
+//! Outputs a structure like:
+//! ```text
+//! void foo(i32 %a, ptr %b)
+//! ```
+
+
+use llvm_ir::Function;
+
+
+/// Renders the return type, name, and parameters of `function` as a pseudocode function header.
+/// 
+/// Variadic functions end their parameter list with `...`.
+pub fn function_signature(function : &Function) -> String {
+    let mut params = function.parameters.iter()
+        .map(|param| format!("{} {}", param.ty, param.name))
+        .collect::<Vec<_>>();
+    if (function.is_var_arg) {
+        params.push("...".to_string());
+    }
+    format!("{} {}({})", function.return_type, function.name, params.join(", "))
+}
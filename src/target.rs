@@ -0,0 +1,25 @@
+//! Target
+//! 
+//! Utilities for reading details of the target machine from an LLVM `Module`.
+
+
+use llvm_ir::Module;
+
+
+/// Gets the size of a pointer in the default address space, in bits, from the datalayout string of `module`.
+/// 
+/// Returns `None` if the datalayout does not specify the pointer size, or it could not be parsed.
+/// 
+/// ```text
+/// e-m:e-p:32:32-i64:64-n32-S128               ->    Some(32)
+/// e-m:e-p270:32:32-i64:64-n8:16:32:64-S128    ->    None
+/// ```
+pub fn pointer_size(module : &Module) -> Option<u32> {
+    module.data_layout.layout_str.split('-').find_map(|spec| {
+        // Pointer specs are written `p[n]:<size>:<abi>[:<pref>][:<idx>]`, where `n` is the address space.
+        let mut fields = spec.split(':');
+        let space = fields.next()?.strip_prefix('p')?;
+        if (! (space.is_empty() || space == "0")) { return None; }
+        fields.next()?.parse().ok()
+    })
+}
@@ -0,0 +1,147 @@
+//! Colours used when displaying graphs, primitives, and groups.
+//! 
+//! Every type which is displayed with colour implements `Themed`, and its `Display` implementation uses
+//!   `Theme::DARK`. Use `Themed::with_theme` to display it with a different theme:
+//! ```rust,no_run
+//! use decomp::prelude::*;
+//! # let module = Module::from_bc_path("/path/to/file.bc").unwrap();
+//! for function in &module.functions {
+//!     let cfg    = ControlFlowGraph::new(function);
+//!     let prims  = CFAPrim::find_all(cfg).unwrap();
+//!     let groups = CFRGroups::new(&prims).unwrap();
+//!     println!("{}", groups.with_theme(Theme::LIGHT));
+//! }
+//! ```
+
+
+use std::fmt;
+
+
+/// The ANSI escape codes written before each kind of text.
+/// 
+/// Each code is followed by the text it colours and then `reset`, so a code can be left empty to leave that kind of
+///   text uncoloured.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    /// Keywords, such as `if`, `while`, and `break`.
+    pub keyword        : &'static str,
+    /// The `return` keyword of recovered groups.
+    pub return_keyword : &'static str,
+    /// Brackets, braces, and the `;` of `for` loops.
+    pub punctuation    : &'static str,
+    /// Names of blocks and nodes.
+    pub block          : &'static str,
+    /// The `!` of negated conditions.
+    pub negation       : &'static str,
+    /// Arrows, separators, temporary nodes, and the kinds of groups in a `StructuralDiff`.
+    pub muted          : &'static str,
+    /// The function called by a call which never returns.
+    pub callee         : &'static str,
+    /// Regions which could not be recovered.
+    pub error          : &'static str,
+    /// The entry node of a `ControlFlowGraph`.
+    pub entry          : &'static str,
+    /// Every other node of a `ControlFlowGraph`.
+    pub node           : &'static str,
+    /// The marker of a group added in a `StructuralDiff`.
+    pub added          : &'static str,
+    /// The marker of a group removed in a `StructuralDiff`.
+    pub removed        : &'static str,
+    /// The marker of a group changed in a `StructuralDiff`.
+    pub changed        : &'static str,
+    /// Ends the colour of the text before it.
+    pub reset          : &'static str
+}
+
+
+impl Theme {
+
+    /// Bright colours for a terminal with a dark background.
+    pub const DARK : Self = Self {
+        keyword        : "\x1b[95m\x1b[1m",
+        return_keyword : "\x1b[35m\x1b[1m",
+        punctuation    : "\x1b[37m\x1b[1m",
+        block          : "\x1b[36m",
+        negation       : "\x1b[91m",
+        muted          : "\x1b[2m",
+        callee         : "\x1b[93m",
+        error          : "\x1b[91m\x1b[1m",
+        entry          : "\x1b[92m\x1b[1m",
+        node           : "\x1b[97m\x1b[1m",
+        added          : "\x1b[92m\x1b[1m",
+        removed        : "\x1b[91m\x1b[1m",
+        changed        : "\x1b[93m\x1b[1m",
+        reset          : "\x1b[0m"
+    };
+
+    /// Dark colours for a terminal with a light background.
+    pub const LIGHT : Self = Self {
+        keyword        : "\x1b[35m\x1b[1m",
+        return_keyword : "\x1b[35m\x1b[1m",
+        punctuation    : "\x1b[30m\x1b[1m",
+        block          : "\x1b[34m",
+        negation       : "\x1b[31m",
+        muted          : "\x1b[2m",
+        callee         : "\x1b[33m",
+        error          : "\x1b[31m\x1b[1m",
+        entry          : "\x1b[32m\x1b[1m",
+        node           : "\x1b[30m\x1b[1m",
+        added          : "\x1b[32m\x1b[1m",
+        removed        : "\x1b[31m\x1b[1m",
+        changed        : "\x1b[33m\x1b[1m",
+        reset          : "\x1b[0m"
+    };
+
+    /// No colour at all, such as for writing to a file.
+    pub const PLAIN : Self = Self {
+        keyword        : "",
+        return_keyword : "",
+        punctuation    : "",
+        block          : "",
+        negation       : "",
+        muted          : "",
+        callee         : "",
+        error          : "",
+        entry          : "",
+        node           : "",
+        added          : "",
+        removed        : "",
+        changed        : "",
+        reset          : ""
+    };
+
+}
+
+
+impl Default for Theme {
+    fn default() -> Self { Self::DARK }
+}
+
+
+/// A type which can be displayed with the colours of a `Theme`.
+pub trait Themed {
+
+    /// Formats the value the same way as `Display`, using the colours of `theme`.
+    fn fmt_themed(&self, f : &mut fmt::Formatter<'_>, theme : &Theme) -> fmt::Result;
+
+    /// Wraps the value so that it is displayed using the colours of `theme`.
+    fn with_theme(&self, theme : Theme) -> WithTheme<'_, Self> {
+        WithTheme { value : self, theme }
+    }
+
+}
+
+
+/// A value displayed using the colours of a `Theme`.
+/// 
+/// Created by `Themed::with_theme`.
+pub struct WithTheme<'l, T : ?Sized> {
+    value : &'l T,
+    theme : Theme
+}
+
+impl<'l, T : Themed + ?Sized> fmt::Display for WithTheme<'l, T> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt_themed(f, &self.theme)
+    }
+}
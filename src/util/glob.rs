@@ -0,0 +1,37 @@
+//! Minimal glob matching, for picking out names.
+
+
+/// Returns `true` if all of `text` matches `pattern`.
+/// 
+/// `*` matches any number of characters, including none, and `?` matches exactly one. Every other character
+///   matches only itself.
+pub(crate) fn matches(pattern : &str, text : &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text    = text.chars().collect::<Vec<_>>();
+    let mut p = 0;
+    let mut t = 0;
+    // The position of the last `*` in the pattern, and of the text it was tried at.
+    let mut star : Option<(usize, usize)> = None;
+    while (t < text.len()) {
+        match (pattern.get(p)) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            },
+            Some(&c) if (c == '?' || c == text[t]) => {
+                p += 1;
+                t += 1;
+            },
+            // Let the last `*` match one more character, and try again after it.
+            _ => match (star) {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                },
+                None => return false
+            }
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
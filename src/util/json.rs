@@ -0,0 +1,22 @@
+//! Minimal helpers for writing JSON by hand.
+
+
+use std::fmt::Write;
+
+
+/// Appends `value` to `out` as a quoted JSON string.
+pub(crate) fn write_string(out : &mut String, value : &str) -> () {
+    out.push('"');
+    for c in value.chars() {
+        match (c) {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c < ' ') => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
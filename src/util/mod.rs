@@ -1 +1,3 @@
 pub mod unique_vec;
+pub(crate) mod json;
+pub(crate) mod glob;
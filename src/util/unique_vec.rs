@@ -178,6 +178,14 @@ impl<T> UniqueVec<T> {
 
     pub fn get(&self, index : usize) -> Option<&T> { self.vec.get(index) }
 
+    /// Sorts the slice with a key extraction function, preserving the initial order of equal elements.
+    /// 
+    /// This sort is stable (i.e., does not reorder equal elements) and *O(m \* n \* log(n))* worst-case, where the key
+    ///   function is *O(m)*.
+    /// 
+    /// Copied from [`slice::sort_by_key`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by_key).
+    pub fn sort_by_key<K : Ord, F : FnMut(&T) -> K>(&mut self, f : F) -> () { self.vec.sort_by_key(f) }
+
 }
 
 impl<T : Eq> UniqueVec<T> {
@@ -267,3 +275,22 @@ impl<'l, T> IntoIterator for &'l mut UniqueVec<T> {
         (&mut self.vec).into_iter()
     }
 }
+
+
+impl<T : Eq> FromIterator<T> for UniqueVec<T> {
+    /// Collects the values into a new vector, keeping only the first of any equal values.
+    fn from_iter<I : IntoIterator<Item = T>>(iter : I) -> Self {
+        let mut out = Self::new();
+        out.extend(iter);
+        out
+    }
+}
+
+impl<T : Eq> Extend<T> for UniqueVec<T> {
+    /// Inserts each value into the vector, skipping any which are already present.
+    fn extend<I : IntoIterator<Item = T>>(&mut self, iter : I) -> () {
+        for x in iter {
+            self.insert(x);
+        }
+    }
+}